@@ -0,0 +1,926 @@
+// Usage:
+//
+//     cargo run --release -- info serde
+//     cargo run --release -- --dump path/to/db-dump.tar.gz rdeps serde --limit 20
+//     cargo run --release -- --format json owners serde
+//     cargo run --release -- user dtolnay
+//     cargo run --release -- --format csv downloads serde --since 2024-01-01
+//     cargo run --release -- search parser
+//     cargo run --release -- stats
+//
+// Answers common questions about a crates.io database dump without writing a
+// program for each one. Every subcommand loads only the tables that it needs.
+//
+// Output is aligned text by default. With --format json, a list prints as an
+// array of objects and a single record as one object, and a subcommand that
+// produces several sections prints an object keyed by section name. With
+// --format csv, each section is printed with a header row, and sections are
+// separated by a blank line.
+
+#![allow(clippy::uninlined_format_args)]
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use db_dump::crate_owners::OwnerId;
+use db_dump::crates::CrateId;
+use db_dump::{Date, DbDump, Index};
+use serde_json::{Map as JsonMap, Value};
+use std::cell::Cell;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap as Map};
+use std::io::{self, Write};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(name = "db-dump", author, version)]
+struct Opt {
+    /// Path of the db-dump.tar.gz to query
+    #[arg(short, long, default_value = "./db-dump.tar.gz", global = true)]
+    dump: PathBuf,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Text, global = true)]
+    format: Format,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Versions, owners, downloads and dependencies of a crate
+    Info { name: String },
+
+    /// Crates whose default version depends on a crate
+    Rdeps {
+        name: String,
+
+        /// Print only this many of the most downloaded dependents
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+    },
+
+    /// Users and teams that own a crate
+    Owners { name: String },
+
+    /// A user and the crates they own
+    User { login: String },
+
+    /// Daily downloads of a crate, summed across its versions
+    Downloads {
+        name: String,
+
+        /// First date to include, as YYYY-MM-DD
+        #[arg(long)]
+        since: Option<NaiveDate>,
+    },
+
+    /// Crates tagged with a keyword
+    Search {
+        keyword: String,
+
+        /// Print only this many of the most downloaded crates
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+    },
+
+    /// Number of rows in each table of the dump
+    Stats,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug)]
+enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+// One section of output.
+struct Table {
+    title: &'static str,
+    columns: &'static [&'static str],
+    rows: Vec<Vec<Value>>,
+    // In text format, print the single row as one "column: value" line per
+    // column, rather than as a table that is too wide to read.
+    vertical: bool,
+}
+
+impl Table {
+    fn new(title: &'static str, columns: &'static [&'static str]) -> Self {
+        Table {
+            title,
+            columns,
+            rows: Vec::new(),
+            vertical: false,
+        }
+    }
+
+    fn record(title: &'static str, columns: &'static [&'static str], row: Vec<Value>) -> Self {
+        Table {
+            title,
+            columns,
+            rows: vec![row],
+            vertical: true,
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let opt = Opt::parse();
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    run(&opt, &mut stdout)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn run(opt: &Opt, out: &mut dyn Write) -> Result<()> {
+    let tables = match &opt.command {
+        Command::Info { name } => info(&opt.dump, name)?,
+        Command::Rdeps { name, limit } => rdeps(&opt.dump, name, *limit)?,
+        Command::Owners { name } => owners(&opt.dump, name)?,
+        Command::User { login } => user(&opt.dump, login)?,
+        Command::Downloads { name, since } => downloads(&opt.dump, name, *since)?,
+        Command::Search { keyword, limit } => search(&opt.dump, keyword, *limit)?,
+        Command::Stats => stats(&opt.dump)?,
+    };
+
+    match opt.format {
+        Format::Text => print_text(out, &tables)?,
+        Format::Json => print_json(out, &tables)?,
+        Format::Csv => print_csv(out, &tables)?,
+    }
+    Ok(())
+}
+
+// Load the named tables of the dump, leaving the others empty.
+fn load(path: &Path, tables: &[&str]) -> Result<DbDump> {
+    let mut db = DbDump::default();
+    let want = |table: &str| tables.contains(&table);
+
+    let mut loader = db_dump::Loader::new();
+    if want("crate_downloads") {
+        loader.crate_downloads(|row| db.crate_downloads.push(row));
+    }
+    if want("crate_owners") {
+        loader.crate_owners(|row| db.crate_owners.push(row));
+    }
+    if want("crates") {
+        loader.crates(|row| db.crates.push(row));
+    }
+    if want("crates_keywords") {
+        loader.crates_keywords(|row| db.crates_keywords.push(row));
+    }
+    if want("default_versions") {
+        loader.default_versions(|row| db.default_versions.push(row));
+    }
+    if want("dependencies") {
+        loader.dependencies(|row| db.dependencies.push(row));
+    }
+    if want("keywords") {
+        loader.keywords(|row| db.keywords.push(row));
+    }
+    if want("teams") {
+        loader.teams(|row| db.teams.push(row));
+    }
+    if want("users") {
+        loader.users(|row| db.users.push(row));
+    }
+    if want("version_downloads") {
+        loader.version_downloads(|row| db.version_downloads.push(row));
+    }
+    if want("versions") {
+        loader.versions(|row| db.versions.push(row));
+    }
+    loader.load(path)?;
+    drop(loader);
+
+    Ok(db)
+}
+
+fn info(path: &Path, name: &str) -> Result<Vec<Table>> {
+    let db = load(
+        path,
+        &[
+            "crate_downloads",
+            "crate_owners",
+            "crates",
+            "default_versions",
+            "dependencies",
+            "teams",
+            "users",
+            "versions",
+        ],
+    )?;
+    let index = db.index();
+    let krate = crate_by_name(&index, name)?;
+    let downloads = crate_downloads(&db);
+    let default_version = index.default_version(krate.id);
+
+    let mut summary = vec![
+        Value::from(krate.name.clone()),
+        Value::Null,
+        Value::from(krate.description.clone()),
+        Value::from(downloads.get(&krate.id).copied().unwrap_or(0)),
+        Value::from(date(krate.created_at)),
+        Value::from(date(krate.updated_at)),
+        Value::from(krate.repository.clone()),
+    ];
+    if let Some(version) = default_version {
+        summary[1] = Value::from(version.num.to_string());
+    }
+
+    let mut versions: Vec<_> = index.versions_of(krate.id).collect();
+    versions.sort_by(|a, b| b.num.cmp(&a.num));
+    let mut versions_table = Table::new(
+        "versions",
+        &["version", "created_at", "downloads", "yanked", "license"],
+    );
+    for version in versions {
+        versions_table.rows.push(vec![
+            Value::from(version.num.to_string()),
+            Value::from(date(version.created_at)),
+            Value::from(version.downloads),
+            Value::from(version.yanked),
+            Value::from(version.license.to_string()),
+        ]);
+    }
+
+    let mut dependencies_table = Table::new(
+        "dependencies",
+        &["crate", "req", "kind", "optional", "target"],
+    );
+    if let Some(version) = default_version {
+        let mut dependencies: Vec<_> = index
+            .dependencies_of(version.id)
+            .filter_map(|dependency| {
+                let dependency_crate = index.crate_by_id(dependency.crate_id)?;
+                Some((dependency, dependency_crate))
+            })
+            .collect();
+        dependencies.sort_by_key(|(dependency, dependency_crate)| {
+            (dependency.kind.as_str(), &dependency_crate.name)
+        });
+        for (dependency, dependency_crate) in dependencies {
+            dependencies_table.rows.push(vec![
+                Value::from(dependency_crate.name.clone()),
+                Value::from(dependency.req.to_string()),
+                Value::from(dependency.kind.as_str()),
+                Value::from(dependency.optional),
+                Value::from(dependency.target.to_string()),
+            ]);
+        }
+    }
+
+    Ok(vec![
+        Table::record(
+            "crate",
+            &[
+                "name",
+                "default_version",
+                "description",
+                "downloads",
+                "created_at",
+                "updated_at",
+                "repository",
+            ],
+            summary,
+        ),
+        versions_table,
+        owners_table(&index, krate.id),
+        dependencies_table,
+    ])
+}
+
+fn rdeps(path: &Path, name: &str, limit: Option<usize>) -> Result<Vec<Table>> {
+    let db = load(
+        path,
+        &[
+            "crate_downloads",
+            "crates",
+            "default_versions",
+            "dependencies",
+            "versions",
+        ],
+    )?;
+    let index = db.index();
+    let krate = crate_by_name(&index, name)?;
+    let downloads = crate_downloads(&db);
+
+    let mut dependents = index.reverse_dependencies(krate.id);
+    dependents.sort_by_key(|dependent| {
+        let downloads = downloads.get(&dependent.krate.id).copied().unwrap_or(0);
+        (Reverse(downloads), &dependent.krate.name)
+    });
+
+    let mut table = Table::new(
+        "dependents",
+        &["crate", "version", "req", "kind", "optional", "downloads"],
+    );
+    for dependent in dependents.into_iter().take(limit.unwrap_or(usize::MAX)) {
+        let dependency = dependent.dependency;
+        table.rows.push(vec![
+            Value::from(dependent.krate.name.clone()),
+            Value::from(dependent.version.num.to_string()),
+            Value::from(dependency.req.to_string()),
+            Value::from(dependency.kind.as_str()),
+            Value::from(dependency.optional),
+            Value::from(downloads.get(&dependent.krate.id).copied().unwrap_or(0)),
+        ]);
+    }
+    Ok(vec![table])
+}
+
+fn owners(path: &Path, name: &str) -> Result<Vec<Table>> {
+    let db = load(path, &["crate_owners", "crates", "teams", "users"])?;
+    let index = db.index();
+    let krate = crate_by_name(&index, name)?;
+    Ok(vec![owners_table(&index, krate.id)])
+}
+
+fn owners_table(index: &Index, crate_id: CrateId) -> Table {
+    let mut table = Table::new("owners", &["kind", "login", "name", "since"]);
+    let mut owners: Vec<_> = index.owners_of(crate_id).collect();
+    owners.sort_by_key(|owner| owner.created_at);
+    for owner in owners {
+        let (kind, login, name) = match owner.owner_id {
+            OwnerId::User(user_id) => {
+                let Some(user) = index.user_by_id(user_id) else {
+                    continue;
+                };
+                ("user", &user.gh_login, user.name.clone())
+            }
+            OwnerId::Team(team_id) => {
+                let Some(team) = index.team_by_id(team_id) else {
+                    continue;
+                };
+                ("team", &team.login, Some(team.name.clone()))
+            }
+        };
+        table.rows.push(vec![
+            Value::from(kind),
+            Value::from(login.clone()),
+            Value::from(name),
+            Value::from(date(owner.created_at)),
+        ]);
+    }
+    table
+}
+
+fn user(path: &Path, login: &str) -> Result<Vec<Table>> {
+    let db = load(
+        path,
+        &["crate_downloads", "crate_owners", "crates", "users"],
+    )?;
+    let index = db.index();
+    let Some(user) = index.user_by_login(login) else {
+        bail!("no user with login {}", login);
+    };
+    let downloads = crate_downloads(&db);
+
+    let mut crates: Vec<_> = index.crates_owned_by(user.id).collect();
+    crates.sort_by_key(|krate| {
+        let downloads = downloads.get(&krate.id).copied().unwrap_or(0);
+        (Reverse(downloads), &krate.name)
+    });
+    let mut crates_table = Table::new("crates", &["crate", "downloads", "created_at"]);
+    for krate in crates {
+        crates_table.rows.push(vec![
+            Value::from(krate.name.clone()),
+            Value::from(downloads.get(&krate.id).copied().unwrap_or(0)),
+            Value::from(date(krate.created_at)),
+        ]);
+    }
+
+    Ok(vec![
+        Table::record(
+            "user",
+            &["login", "name", "id", "avatar"],
+            vec![
+                Value::from(user.gh_login.clone()),
+                Value::from(user.name.clone()),
+                Value::from(user.id.0),
+                Value::from(user.gh_avatar.clone()),
+            ],
+        ),
+        crates_table,
+    ])
+}
+
+fn downloads(path: &Path, name: &str, since: Option<NaiveDate>) -> Result<Vec<Table>> {
+    let mut db = load(path, &["crates", "version_downloads", "versions"])?;
+    db.version_downloads.sort();
+    let index = db.index();
+    let krate = crate_by_name(&index, name)?;
+
+    let since = since.map_or(Bound::Unbounded, |since| Bound::Included(Date::from(since)));
+    let mut daily = BTreeMap::<NaiveDate, u64>::new();
+    for version in index.versions_of(krate.id) {
+        for row in db
+            .version_downloads
+            .range(version.id, (since, Bound::Unbounded))
+        {
+            *daily.entry(row.date.naive_utc()).or_insert(0) += row.downloads;
+        }
+    }
+
+    let mut table = Table::new("downloads", &["date", "downloads"]);
+    for (date, downloads) in daily {
+        table
+            .rows
+            .push(vec![Value::from(date.to_string()), Value::from(downloads)]);
+    }
+    Ok(vec![table])
+}
+
+fn search(path: &Path, keyword: &str, limit: Option<usize>) -> Result<Vec<Table>> {
+    let db = load(
+        path,
+        &["crate_downloads", "crates", "crates_keywords", "keywords"],
+    )?;
+    let index = db.index();
+    let keyword = keyword.to_lowercase();
+    let Some(keyword) = db.keywords.iter().find(|row| row.keyword == keyword) else {
+        bail!("no crates have the keyword {}", keyword);
+    };
+    let downloads = crate_downloads(&db);
+
+    let mut crates: Vec<_> = db
+        .crates_keywords
+        .iter()
+        .filter(|row| row.keyword_id == keyword.id)
+        .filter_map(|row| index.crate_by_id(row.crate_id))
+        .collect();
+    crates.sort_by_key(|krate| {
+        let downloads = downloads.get(&krate.id).copied().unwrap_or(0);
+        (Reverse(downloads), &krate.name)
+    });
+
+    let mut table = Table::new("crates", &["crate", "downloads", "description"]);
+    for krate in crates.into_iter().take(limit.unwrap_or(usize::MAX)) {
+        table.rows.push(vec![
+            Value::from(krate.name.clone()),
+            Value::from(downloads.get(&krate.id).copied().unwrap_or(0)),
+            Value::from(krate.description.clone()),
+        ]);
+    }
+    Ok(vec![table])
+}
+
+fn stats(path: &Path) -> Result<Vec<Table>> {
+    // Rows are counted as they are loaded rather than kept, so that this works
+    // within much less memory than the whole dump.
+    fn count<T>(counter: &Cell<u64>) -> impl FnMut(T) + '_ {
+        move |_row| counter.set(counter.get() + 1)
+    }
+
+    const TABLES: [&str; 15] = [
+        "categories",
+        "crate_downloads",
+        "crate_owners",
+        "crates",
+        "crates_categories",
+        "crates_keywords",
+        "default_versions",
+        "deleted_crates",
+        "dependencies",
+        "keywords",
+        "reserved_crate_names",
+        "teams",
+        "users",
+        "version_downloads",
+        "versions",
+    ];
+    let counts: [Cell<u64>; TABLES.len()] = Default::default();
+    let mut total_downloads = 0;
+
+    db_dump::Loader::new()
+        .categories(count(&counts[0]))
+        .crate_downloads(count(&counts[1]))
+        .crate_owners(count(&counts[2]))
+        .crates(count(&counts[3]))
+        .crates_categories(count(&counts[4]))
+        .crates_keywords(count(&counts[5]))
+        .default_versions(count(&counts[6]))
+        .deleted_crates(count(&counts[7]))
+        .dependencies(count(&counts[8]))
+        .keywords(count(&counts[9]))
+        .reserved_crate_names(count(&counts[10]))
+        .teams(count(&counts[11]))
+        .users(count(&counts[12]))
+        .version_downloads(count(&counts[13]))
+        .versions(count(&counts[14]))
+        .metadata(|row| total_downloads = row.total_downloads)
+        .load(path)?;
+
+    let mut tables = Table::new("tables", &["table", "rows"]);
+    for (table, count) in TABLES.iter().zip(counts) {
+        tables
+            .rows
+            .push(vec![Value::from(*table), Value::from(count.get())]);
+    }
+
+    Ok(vec![
+        tables,
+        Table::record(
+            "metadata",
+            &["total_downloads"],
+            vec![Value::from(total_downloads)],
+        ),
+    ])
+}
+
+fn crate_by_name<'a>(index: &Index<'a>, name: &str) -> Result<&'a db_dump::crates::Row> {
+    match index.crate_by_name(name) {
+        Some(krate) => Ok(krate),
+        None => bail!("no crate named {}", name),
+    }
+}
+
+fn crate_downloads(db: &DbDump) -> Map<CrateId, u64> {
+    db.crate_downloads
+        .iter()
+        .map(|row| (row.crate_id, row.downloads))
+        .collect()
+}
+
+fn date(datetime: DateTime<Utc>) -> String {
+    datetime.format("%Y-%m-%d").to_string()
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(string) => string.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn print_text(out: &mut dyn Write, tables: &[Table]) -> io::Result<()> {
+    for (i, table) in tables.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        if tables.len() > 1 && !table.vertical {
+            writeln!(out, "{}:", table.title)?;
+        }
+
+        if table.vertical {
+            let width = table.columns.iter().map(|column| column.len()).max();
+            for row in &table.rows {
+                for (column, value) in table.columns.iter().zip(row) {
+                    let width = width.unwrap_or(0) + 1;
+                    let label = format!("{}:", column);
+                    writeln!(out, "{:width$} {}", label, cell(value), width = width)?;
+                }
+            }
+            continue;
+        }
+
+        let rows: Vec<Vec<String>> = table
+            .rows
+            .iter()
+            .map(|row| row.iter().map(cell).collect())
+            .collect();
+        let mut widths: Vec<usize> = table.columns.iter().map(|column| column.len()).collect();
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let header: Vec<String> = table.columns.iter().map(ToString::to_string).collect();
+        for row in std::iter::once(&header).chain(&rows) {
+            let mut line = String::new();
+            for (i, (cell, width)) in row.iter().zip(&widths).enumerate() {
+                if i > 0 {
+                    line.push_str("  ");
+                }
+                line.push_str(cell);
+                if i + 1 < row.len() {
+                    let padding = width - cell.chars().count();
+                    line.extend(std::iter::repeat_n(' ', padding));
+                }
+            }
+            writeln!(out, "{}", line.trim_end())?;
+        }
+    }
+    Ok(())
+}
+
+fn print_json(out: &mut dyn Write, tables: &[Table]) -> Result<()> {
+    let json = |table: &Table| -> Value {
+        let objects = table.rows.iter().map(|row| {
+            let object: JsonMap<String, Value> = table
+                .columns
+                .iter()
+                .map(|column| (*column).to_owned())
+                .zip(row.iter().cloned())
+                .collect();
+            Value::Object(object)
+        });
+        if table.vertical {
+            objects.into_iter().next().unwrap_or(Value::Null)
+        } else {
+            Value::Array(objects.collect())
+        }
+    };
+
+    let value = if let [table] = tables {
+        json(table)
+    } else {
+        Value::Object(
+            tables
+                .iter()
+                .map(|table| (table.title.to_owned(), json(table)))
+                .collect(),
+        )
+    };
+    serde_json::to_writer_pretty(&mut *out, &value)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn print_csv(out: &mut dyn Write, tables: &[Table]) -> Result<()> {
+    for (i, table) in tables.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        let mut writer = csv::Writer::from_writer(&mut *out);
+        writer.write_record(table.columns)?;
+        for row in &table.rows {
+            writer.write_record(row.iter().map(cell))?;
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Opt;
+    use anyhow::Result;
+    use chrono::DateTime;
+    use clap::Parser;
+    use db_dump::dependencies::DependencyKind;
+    use db_dump::testing::{DumpBuilder, TempArchive};
+    use db_dump::Date;
+    use serde_json::{json, Value};
+
+    fn fixture() -> TempArchive {
+        let mut builder = DumpBuilder::new();
+        let serde = builder.krate("serde");
+        serde.description = "A serialization framework".to_owned();
+        serde.repository = Some("https://github.com/serde-rs/serde".to_owned());
+        let serde = serde.id;
+        let serde_derive = builder.krate("serde_derive").id;
+        let trybuild = builder.krate("trybuild").id;
+        let dtolnay = builder.user("dtolnay");
+        dtolnay.name = Some("David Tolnay".to_owned());
+        let dtolnay = dtolnay.id;
+        builder.owner(serde, dtolnay);
+        builder.keyword(serde, "serialization");
+        let v100 = builder.version(serde, "1.0.0").id;
+        builder.downloads(v100, Date::from_ymd(2024, 1, 1), 10);
+        builder.downloads(v100, Date::from_ymd(2024, 2, 1), 5);
+
+        builder.timestamp(DateTime::from_timestamp(1_706_745_600, 0).unwrap());
+        let team = builder.team("github:serde-rs:owners").id;
+        builder.owner(serde, team);
+        let v101 = builder.version(serde, "1.0.1").id;
+        builder.downloads(v101, Date::from_ymd(2024, 2, 1), 20);
+        builder.downloads(v101, Date::from_ymd(2024, 2, 2), 30);
+        builder.dependency(v101, serde_derive, "^1").optional = true;
+        builder.dependency(v101, trybuild, "^1.0").kind = DependencyKind::Dev;
+
+        for (name, req, downloads) in [("serde_json", "^1.0", 40), ("toml", "^1.0.100", 15)] {
+            let krate = builder.krate(name).id;
+            builder.keyword(krate, "serialization");
+            builder.owner(krate, dtolnay);
+            let version = builder.version(krate, "1.0.0").id;
+            builder.dependency(version, serde, req);
+            builder.downloads(version, Date::from_ymd(2024, 2, 1), downloads);
+        }
+
+        builder.temp_archive().unwrap()
+    }
+
+    fn query(archive: &TempArchive, args: &[&str]) -> Result<String> {
+        let dump = archive.path().to_str().unwrap();
+        let opt = Opt::try_parse_from(["db-dump", "--dump", dump].iter().chain(args))?;
+        let mut out = Vec::new();
+        super::run(&opt, &mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    fn json(archive: &TempArchive, args: &[&str]) -> Value {
+        let args = [&["--format", "json"], args].concat();
+        serde_json::from_str(&query(archive, &args).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_info() {
+        let archive = fixture();
+        let output = json(&archive, &["info", "serde"]);
+        assert_eq!(
+            output["crate"],
+            json!({
+                "name": "serde",
+                "default_version": "1.0.1",
+                "description": "A serialization framework",
+                "downloads": 65,
+                "created_at": "2024-01-01",
+                "updated_at": "2024-01-01",
+                "repository": "https://github.com/serde-rs/serde",
+            }),
+        );
+        assert_eq!(
+            output["versions"],
+            json!([
+                {
+                    "version": "1.0.1",
+                    "created_at": "2024-02-01",
+                    "downloads": 50,
+                    "yanked": false,
+                    "license": "MIT OR Apache-2.0",
+                },
+                {
+                    "version": "1.0.0",
+                    "created_at": "2024-01-01",
+                    "downloads": 15,
+                    "yanked": false,
+                    "license": "MIT OR Apache-2.0",
+                },
+            ]),
+        );
+        let owners: Vec<_> = output["owners"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|owner| owner["login"].as_str().unwrap())
+            .collect();
+        assert_eq!(owners, ["dtolnay", "github:serde-rs:owners"]);
+        assert_eq!(
+            output["dependencies"],
+            json!([
+                {
+                    "crate": "trybuild",
+                    "req": "^1.0",
+                    "kind": "dev",
+                    "optional": false,
+                    "target": "",
+                },
+                {
+                    "crate": "serde_derive",
+                    "req": "^1",
+                    "kind": "normal",
+                    "optional": true,
+                    "target": "",
+                },
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_rdeps() {
+        let archive = fixture();
+        let output = json(&archive, &["rdeps", "serde"]);
+        assert_eq!(
+            output,
+            json!([
+                {
+                    "crate": "serde_json",
+                    "version": "1.0.0",
+                    "req": "^1.0",
+                    "kind": "normal",
+                    "optional": false,
+                    "downloads": 40,
+                },
+                {
+                    "crate": "toml",
+                    "version": "1.0.0",
+                    "req": "^1.0.100",
+                    "kind": "normal",
+                    "optional": false,
+                    "downloads": 15,
+                },
+            ]),
+        );
+
+        let output = json(&archive, &["rdeps", "serde", "--limit", "1"]);
+        assert_eq!(output.as_array().unwrap().len(), 1);
+        assert_eq!(output[0]["crate"], "serde_json");
+    }
+
+    #[test]
+    fn test_owners() {
+        let archive = fixture();
+        let output = query(&archive, &["owners", "serde"]).unwrap();
+        let expected = "\
+            kind  login                   name          since\n\
+            user  dtolnay                 David Tolnay  2024-01-01\n\
+            team  github:serde-rs:owners  owners        2024-02-01\n";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_user() {
+        let archive = fixture();
+        let output = query(&archive, &["user", "dtolnay"]).unwrap();
+        let expected = "\
+            login:  dtolnay\n\
+            name:   David Tolnay\n\
+            id:     1\n\
+            avatar: https://avatars.githubusercontent.com/u/1001?v=4\n\
+            \n\
+            crates:\n\
+            crate       downloads  created_at\n\
+            serde       65         2024-01-01\n\
+            serde_json  40         2024-02-01\n\
+            toml        15         2024-02-01\n";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_downloads() {
+        let archive = fixture();
+        let output = query(&archive, &["--format", "csv", "downloads", "serde"]).unwrap();
+        let expected = "\
+            date,downloads\n\
+            2024-01-01,10\n\
+            2024-02-01,25\n\
+            2024-02-02,30\n";
+        assert_eq!(output, expected);
+
+        let args = [
+            "--format",
+            "csv",
+            "downloads",
+            "serde",
+            "--since",
+            "2024-02-02",
+        ];
+        let output = query(&archive, &args).unwrap();
+        assert_eq!(output, "date,downloads\n2024-02-02,30\n");
+    }
+
+    #[test]
+    fn test_search() {
+        let archive = fixture();
+        let output = json(&archive, &["search", "Serialization", "-n", "2"]);
+        assert_eq!(
+            output,
+            json!([
+                {
+                    "crate": "serde",
+                    "downloads": 65,
+                    "description": "A serialization framework",
+                },
+                {
+                    "crate": "serde_json",
+                    "downloads": 40,
+                    "description": "",
+                },
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let archive = fixture();
+        let output = query(&archive, &["--format", "csv", "stats"]).unwrap();
+        let expected = "\
+            table,rows\n\
+            categories,0\n\
+            crate_downloads,5\n\
+            crate_owners,4\n\
+            crates,5\n\
+            crates_categories,0\n\
+            crates_keywords,3\n\
+            default_versions,3\n\
+            deleted_crates,0\n\
+            dependencies,4\n\
+            keywords,1\n\
+            reserved_crate_names,0\n\
+            teams,1\n\
+            users,1\n\
+            version_downloads,6\n\
+            versions,4\n\
+            \n\
+            total_downloads\n\
+            120\n";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_errors() {
+        let archive = fixture();
+        let error = query(&archive, &["info", "serde-json"]).unwrap_err();
+        assert_eq!(error.to_string(), "no crate named serde-json");
+        let error = query(&archive, &["user", "nobody"]).unwrap_err();
+        assert_eq!(error.to_string(), "no user with login nobody");
+        let error = query(&archive, &["search", "parser"]).unwrap_err();
+        assert_eq!(error.to_string(), "no crates have the keyword parser");
+    }
+}
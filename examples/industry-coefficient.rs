@@ -7,7 +7,7 @@
 use chrono::{Datelike, TimeDelta, Weekday};
 use db_dump::crates::CrateId;
 use db_dump::versions::VersionId;
-use std::collections::BTreeMap as Map;
+use db_dump::IdMap;
 
 const DOWNLOADS_CUTOFF: u64 = 1_000_000;
 
@@ -18,8 +18,8 @@ struct Downloads {
 }
 
 fn main() -> db_dump::Result<()> {
-    let mut crates: Map<CrateId, String> = Map::new();
-    let mut versions: Map<VersionId, CrateId> = Map::new();
+    let mut crates: IdMap<CrateId, String> = IdMap::new();
+    let mut versions: IdMap<VersionId, CrateId> = IdMap::new();
     let mut version_downloads = Vec::new();
     db_dump::Loader::new()
         .crates(|row| {
@@ -35,13 +35,13 @@ fn main() -> db_dump::Result<()> {
     let start_date = max_date - TimeDelta::try_weeks(6).unwrap();
 
     // Add up downloads by crate by date
-    let mut downloads: Map<CrateId, Downloads> = Map::new();
+    let mut downloads: IdMap<CrateId, Downloads> = IdMap::new();
     for row in version_downloads {
         // Deliberately cut out the largest date in the db-dump, because the
         // data is partial.
         if row.date >= start_date && row.date < max_date {
             let crate_id = versions[&row.version_id];
-            let downloads = downloads.entry(crate_id).or_insert_with(Downloads::default);
+            let downloads = downloads.entry(crate_id).or_default();
             match row.date.weekday() {
                 Weekday::Tue | Weekday::Wed | Weekday::Thu => downloads.weekday += row.downloads,
                 Weekday::Sat | Weekday::Sun => downloads.weekend += row.downloads,
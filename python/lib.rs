@@ -0,0 +1,546 @@
+// Usage:
+//
+//     pip install ./python            # or: maturin develop -m python/Cargo.toml
+//
+// Python bindings over db_dump::Loader, so that analyses in Python read the
+// dump through the same parsing as Rust ones instead of reimplementing the
+// quirks of the csv files.
+//
+//     import db_dump
+//
+//     db = db_dump.load_all("db-dump.tar.gz")
+//     for row in db["crates"]:
+//         print(row["name"], row["created_at"])
+//
+//     loader = db_dump.Loader()
+//     loader.versions(lambda row: ...).crates(lambda row: ...)
+//     loader.load("db-dump.tar.gz")
+//
+// Rows are dicts keyed by the columns of the csv files. Datetimes are
+// timezone-aware datetime.datetime in UTC, dates are datetime.date, booleans
+// are bool, list-valued columns are lists of str, the features of a version
+// are a dict of feature to list of str, and checksums are bytes or None.
+// Columns are the same as in the Arrow schema of the db_dump::parquet module:
+// crate_owners has owner_id and owner_kind (0 for a user, 1 for a team),
+// dependency kind is 0 for normal, 1 for build and 2 for dev, and versions
+// has major, minor and patch alongside num.
+//
+// With arrow=True, Loader callbacks instead receive pyarrow.RecordBatch of up
+// to 8192 rows and load_all returns a pyarrow.Table per table. This requires
+// pyarrow to be installed.
+
+#![allow(clippy::needless_pass_by_value, clippy::uninlined_format_args)]
+
+use arrow_pyarrow::ToPyArrow;
+use db_dump::parquet::{self, TABLES};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList};
+use std::cell::RefCell;
+use std::collections::BTreeMap as Map;
+use std::io;
+use std::path::PathBuf;
+
+create_exception!(db_dump, Error, PyException, "Failed to load a dump.");
+
+fn error(e: db_dump::Error) -> PyErr {
+    Error::new_err(e.to_string())
+}
+
+/// Load every table of a dump.
+///
+/// Returns a dict mapping each table name to a list of rows, or to a
+/// pyarrow.Table if arrow is true.
+#[pyfunction]
+#[pyo3(signature = (path, *, arrow = false))]
+fn load_all(py: Python<'_>, path: PathBuf, arrow: bool) -> PyResult<Py<PyDict>> {
+    let mut loader = Loader {
+        arrow,
+        callbacks: Map::new(),
+    };
+    let mut lists = Vec::new();
+    for table in TABLES {
+        let list = PyList::empty(py);
+        loader
+            .callbacks
+            .insert(table, list.getattr("append")?.unbind());
+        lists.push((table, list));
+    }
+    loader.load(py, path)?;
+
+    let db = PyDict::new(py);
+    if arrow {
+        let pyarrow = py.import("pyarrow")?;
+        for (table, batches) in lists {
+            let schema = parquet::schema(table).unwrap().to_pyarrow(py)?;
+            let table_type = pyarrow.getattr("Table")?;
+            let value = table_type.call_method1("from_batches", (batches, schema))?;
+            db.set_item(table, value)?;
+        }
+    } else {
+        for (table, rows) in lists {
+            db.set_item(table, rows)?;
+        }
+    }
+    Ok(db.unbind())
+}
+
+/// Streams the rows of selected tables of a dump into callbacks.
+///
+/// Register a callable per table of interest, then call load. Tables without
+/// a callback are not parsed.
+#[pyclass(unsendable)]
+struct Loader {
+    arrow: bool,
+    callbacks: Map<&'static str, Py<PyAny>>,
+}
+
+#[pymethods]
+impl Loader {
+    #[new]
+    #[pyo3(signature = (*, arrow = false))]
+    fn new(arrow: bool) -> Self {
+        Loader {
+            arrow,
+            callbacks: Map::new(),
+        }
+    }
+
+    fn categories(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("categories", f);
+        slf
+    }
+
+    fn crate_downloads(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("crate_downloads", f);
+        slf
+    }
+
+    fn crate_owners(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("crate_owners", f);
+        slf
+    }
+
+    fn crates(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("crates", f);
+        slf
+    }
+
+    fn crates_categories(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("crates_categories", f);
+        slf
+    }
+
+    fn crates_keywords(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("crates_keywords", f);
+        slf
+    }
+
+    fn default_versions(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("default_versions", f);
+        slf
+    }
+
+    fn deleted_crates(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("deleted_crates", f);
+        slf
+    }
+
+    fn dependencies(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("dependencies", f);
+        slf
+    }
+
+    fn keywords(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("keywords", f);
+        slf
+    }
+
+    fn metadata(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("metadata", f);
+        slf
+    }
+
+    fn reserved_crate_names(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("reserved_crate_names", f);
+        slf
+    }
+
+    fn teams(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("teams", f);
+        slf
+    }
+
+    fn users(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("users", f);
+        slf
+    }
+
+    fn version_downloads(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("version_downloads", f);
+        slf
+    }
+
+    fn versions(mut slf: PyRefMut<Self>, f: Py<PyAny>) -> PyRefMut<Self> {
+        slf.callbacks.insert("versions", f);
+        slf
+    }
+
+    /// Load the dump at the given path, which may be a db-dump.tar.gz or any
+    /// other dump accepted by the Rust Loader.
+    ///
+    /// An exception raised by a callback stops the load and is re-raised.
+    fn load(&self, py: Python<'_>, path: PathBuf) -> PyResult<()> {
+        // Loader callbacks cannot return errors, so the first one is held
+        // here and the remaining rows are skipped.
+        let exception = RefCell::new(None);
+
+        let result = if self.arrow {
+            let tables: Vec<&str> = self.callbacks.keys().copied().collect();
+            parquet::record_batches(path, &tables, |table, batch| {
+                let result = batch
+                    .to_pyarrow(py)
+                    .and_then(|batch| self.callbacks[table].call1(py, (batch,)));
+                match result {
+                    Ok(_) => Ok(()),
+                    Err(e) => {
+                        *exception.borrow_mut() = Some(e);
+                        Err(io::Error::other("exception in callback").into())
+                    }
+                }
+            })
+        } else {
+            let mut loader = db_dump::Loader::new();
+            macro_rules! register {
+                ($($table:ident)*) => {
+                    $(
+                        if let Some(f) = self.callbacks.get(stringify!($table)) {
+                            loader.$table(|row| call(py, &exception, f, row));
+                        }
+                    )*
+                };
+            }
+            register! {
+                categories crate_downloads crate_owners crates crates_categories
+                crates_keywords default_versions deleted_crates dependencies
+                keywords metadata reserved_crate_names teams users
+                version_downloads versions
+            }
+            loader.load(path)
+        };
+
+        if let Some(e) = exception.into_inner() {
+            return Err(e);
+        }
+        result.map_err(error)
+    }
+}
+
+fn call(py: Python, exception: &RefCell<Option<PyErr>>, f: &Py<PyAny>, row: impl IntoDict) {
+    if exception.borrow().is_some() {
+        return;
+    }
+    let result = row.into_dict(py).and_then(|dict| f.call1(py, (dict,)));
+    if let Err(e) = result {
+        *exception.borrow_mut() = Some(e);
+    }
+}
+
+trait IntoDict {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>>;
+}
+
+impl IntoDict for db_dump::categories::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("id", self.id.0)?;
+        dict.set_item("category", self.category)?;
+        dict.set_item("slug", self.slug)?;
+        dict.set_item("description", self.description)?;
+        dict.set_item("crates_cnt", self.crates_cnt)?;
+        dict.set_item("created_at", self.created_at)?;
+        dict.set_item("path", self.path)?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::crate_downloads::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("crate_id", self.crate_id.0)?;
+        dict.set_item("downloads", self.downloads)?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::crate_owners::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("crate_id", self.crate_id.0)?;
+        dict.set_item("owner_id", self.owner_id.id())?;
+        dict.set_item("created_at", self.created_at)?;
+        dict.set_item("created_by", self.created_by.map(|user_id| user_id.0))?;
+        dict.set_item("owner_kind", self.owner_id.kind())?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::crates::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("id", self.id.0)?;
+        dict.set_item("name", self.name)?;
+        dict.set_item("updated_at", self.updated_at)?;
+        dict.set_item("created_at", self.created_at)?;
+        dict.set_item("description", self.description)?;
+        dict.set_item("homepage", self.homepage)?;
+        dict.set_item("documentation", self.documentation)?;
+        dict.set_item("readme", self.readme)?;
+        dict.set_item("repository", self.repository)?;
+        dict.set_item("max_upload_size", self.max_upload_size)?;
+        dict.set_item("max_features", self.max_features)?;
+        dict.set_item("trustpub_only", self.trustpub_only)?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::crates_categories::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("crate_id", self.crate_id.0)?;
+        dict.set_item("category_id", self.category_id.0)?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::crates_keywords::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("crate_id", self.crate_id.0)?;
+        dict.set_item("keyword_id", self.keyword_id.0)?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::default_versions::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("crate_id", self.crate_id.0)?;
+        dict.set_item("version_id", self.version_id.0)?;
+        dict.set_item("num_versions", self.num_versions)?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::deleted_crates::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("id", self.id.0)?;
+        dict.set_item("name", self.name)?;
+        dict.set_item("created_at", self.created_at)?;
+        dict.set_item("deleted_at", self.deleted_at)?;
+        dict.set_item("deleted_by", self.deleted_by.map(|user_id| user_id.0))?;
+        dict.set_item("message", self.message)?;
+        dict.set_item("available_at", self.available_at)?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::dependencies::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let kind = self.kind.as_u8();
+        let dict = PyDict::new(py);
+        dict.set_item("id", self.id)?;
+        dict.set_item("version_id", self.version_id.0)?;
+        dict.set_item("crate_id", self.crate_id.0)?;
+        dict.set_item("req", self.req.to_string())?;
+        dict.set_item("optional", self.optional)?;
+        dict.set_item("default_features", self.default_features)?;
+        dict.set_item("features", strings(py, &self.features)?)?;
+        dict.set_item("target", &*self.target)?;
+        dict.set_item("kind", kind)?;
+        dict.set_item("explicit_name", self.explicit_name)?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::keywords::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("id", self.id.0)?;
+        dict.set_item("keyword", self.keyword)?;
+        dict.set_item("crates_cnt", self.crates_cnt)?;
+        dict.set_item("created_at", self.created_at)?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::metadata::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("total_downloads", self.total_downloads)?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::reserved_crate_names::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("name", self.name)?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::teams::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("id", self.id.0)?;
+        dict.set_item("login", self.login)?;
+        dict.set_item("github_id", self.github_id)?;
+        dict.set_item("name", self.name)?;
+        dict.set_item("avatar", self.avatar)?;
+        dict.set_item("org_id", self.org_id)?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::users::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("id", self.id.0)?;
+        dict.set_item("gh_login", self.gh_login)?;
+        dict.set_item("name", self.name)?;
+        dict.set_item("gh_avatar", self.gh_avatar)?;
+        dict.set_item("gh_id", self.gh_id)?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::version_downloads::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("version_id", self.version_id.0)?;
+        dict.set_item("downloads", self.downloads)?;
+        dict.set_item("date", self.date.naive_utc())?;
+        Ok(dict)
+    }
+}
+
+impl IntoDict for db_dump::versions::Row {
+    fn into_dict(self, py: Python) -> PyResult<Bound<PyDict>> {
+        let features = PyDict::new(py);
+        for (feature, values) in &self.features {
+            features.set_item(&**feature, strings(py, values)?)?;
+        }
+        let dict = PyDict::new(py);
+        dict.set_item("id", self.id.0)?;
+        dict.set_item("crate_id", self.crate_id.0)?;
+        dict.set_item("num", self.num.to_string())?;
+        dict.set_item("major", self.num.major)?;
+        dict.set_item("minor", self.num.minor)?;
+        dict.set_item("patch", self.num.patch)?;
+        dict.set_item("updated_at", self.updated_at)?;
+        dict.set_item("created_at", self.created_at)?;
+        dict.set_item("downloads", self.downloads)?;
+        dict.set_item("features", features)?;
+        dict.set_item("yanked", self.yanked)?;
+        dict.set_item("license", &*self.license)?;
+        dict.set_item("crate_size", self.crate_size)?;
+        dict.set_item("published_by", self.published_by.map(|user_id| user_id.0))?;
+        dict.set_item(
+            "checksum",
+            self.checksum
+                .as_ref()
+                .map(|checksum| PyBytes::new(py, checksum)),
+        )?;
+        dict.set_item("links", self.links)?;
+        dict.set_item(
+            "rust_version",
+            self.rust_version.as_ref().map(ToString::to_string),
+        )?;
+        dict.set_item("has_lib", self.has_lib)?;
+        dict.set_item("bin_names", self.bin_names)?;
+        dict.set_item("edition", self.edition)?;
+        dict.set_item("description", self.description)?;
+        dict.set_item("homepage", self.homepage)?;
+        dict.set_item("documentation", self.documentation)?;
+        dict.set_item("repository", self.repository)?;
+        dict.set_item("categories", strings(py, &self.categories)?)?;
+        dict.set_item("keywords", strings(py, &self.keywords)?)?;
+        Ok(dict)
+    }
+}
+
+fn strings<'py>(py: Python<'py>, values: &[impl AsRef<str>]) -> PyResult<Bound<'py, PyList>> {
+    PyList::new(py, values.iter().map(AsRef::as_ref))
+}
+
+#[pymodule]
+#[pyo3(name = "db_dump")]
+fn db_dump_module(m: &Bound<PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(load_all, m)?)?;
+    m.add_class::<Loader>()?;
+    m.add("Error", m.py().get_type::<Error>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IntoDict;
+    use db_dump::testing::DumpBuilder;
+    use pyo3::prelude::*;
+    use pyo3::types::{PyBytes, PyDict};
+    use std::sync::Arc;
+
+    fn item<'py>(dict: &Bound<'py, PyDict>, key: &str) -> Bound<'py, PyAny> {
+        dict.get_item(key).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_into_dict() {
+        let mut builder = DumpBuilder::new();
+        let alice = builder.user("alice").id;
+        let serde = builder.krate("serde").id;
+        let version = builder.version(serde, "1.0.0-rc.1");
+        version.checksum = Some([0xab; 32]);
+        version
+            .features
+            .insert(Arc::from("default"), vec![Arc::from("std")]);
+        version.keywords = vec![Arc::from("serde")];
+        version.yanked = true;
+        builder.owner(serde, alice);
+        let db = builder.build();
+        let version = db.versions[0].clone();
+
+        Python::initialize();
+        Python::attach(|py| {
+            let dict = version.clone().into_dict(py).unwrap();
+            assert!(item(&dict, "id").eq(version.id.0).unwrap());
+            assert_eq!(
+                item(&dict, "num").extract::<String>().unwrap(),
+                "1.0.0-rc.1"
+            );
+            assert_eq!(item(&dict, "minor").extract::<u64>().unwrap(), 0);
+            assert!(item(&dict, "created_at").eq(version.created_at).unwrap());
+            assert!(item(&dict, "yanked").extract::<bool>().unwrap());
+            let features = item(&dict, "features");
+            let features = features.cast::<PyDict>().unwrap();
+            assert_eq!(
+                item(features, "default").extract::<Vec<String>>().unwrap(),
+                ["std"],
+            );
+            let checksum = item(&dict, "checksum");
+            assert_eq!(checksum.cast::<PyBytes>().unwrap().as_bytes(), [0xab; 32]);
+            assert!(item(&dict, "links").is_none());
+            assert_eq!(
+                item(&dict, "keywords").extract::<Vec<String>>().unwrap(),
+                ["serde"],
+            );
+
+            let owner = db.crate_owners[0].clone().into_dict(py).unwrap();
+            assert!(item(&owner, "owner_id").eq(alice.0).unwrap());
+            assert_eq!(item(&owner, "owner_kind").extract::<u8>().unwrap(), 0);
+        });
+    }
+}
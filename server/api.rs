@@ -0,0 +1,606 @@
+// Responses of the endpoints listed in main.rs, computed from an Index of the
+// dump.
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, TimeDelta, Utc};
+use db_dump::crate_owners::OwnerId;
+use db_dump::crates::CrateId;
+use db_dump::versions::VersionId;
+use db_dump::Index;
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap as Map};
+use tiny_http::Method;
+
+// Number of days of download counts served by the downloads endpoint and
+// summed in recent_downloads, as on crates.io.
+const RECENT_DAYS: i64 = 90;
+
+// The downloads endpoint breaks down this many of the most recently published
+// versions individually, and sums the others into extra_downloads.
+const DOWNLOADS_TOP_VERSIONS: usize = 5;
+
+fn error(detail: &str) -> Value {
+    json!({ "errors": [{ "detail": detail }] })
+}
+
+pub struct Api<'a> {
+    index: Index<'a>,
+    crates_by_name: Map<String, &'a db_dump::crates::Row>,
+    crate_downloads: Map<CrateId, u64>,
+    recent_downloads: Map<VersionId, Vec<(NaiveDate, u64)>>,
+}
+
+impl<'a> Api<'a> {
+    pub fn new(index: Index<'a>) -> Self {
+        let db = index.dump();
+
+        let crates_by_name = db
+            .crates
+            .iter()
+            .map(|row| (canonical_name(&row.name), row))
+            .collect();
+
+        let crate_downloads = db
+            .crate_downloads
+            .iter()
+            .map(|row| (row.crate_id, row.downloads))
+            .collect();
+
+        let mut recent_downloads = Map::<VersionId, Vec<(NaiveDate, u64)>>::new();
+        if let Some(last) = db.version_downloads.iter().map(|row| row.date).max() {
+            let first = last.naive_utc() - TimeDelta::days(RECENT_DAYS - 1);
+            for row in &db.version_downloads {
+                let date = row.date.naive_utc();
+                if date >= first {
+                    let entry = recent_downloads.entry(row.version_id).or_default();
+                    entry.push((date, row.downloads));
+                }
+            }
+        }
+
+        Api {
+            index,
+            crates_by_name,
+            crate_downloads,
+            recent_downloads,
+        }
+    }
+
+    // Status code and JSON body of the response to a request.
+    pub fn handle(&self, method: &Method, url: &str) -> (u16, Value) {
+        if *method != Method::Get {
+            return (405, error("method not allowed"));
+        }
+
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let query: Map<&str, &str> = query
+            .split('&')
+            .filter_map(|param| param.split_once('='))
+            .collect();
+
+        let Some(rest) = path.strip_prefix("/api/v1/crates/") else {
+            return (404, error("Not Found"));
+        };
+        let (name, endpoint) = match rest.split_once('/') {
+            Some((name, endpoint)) => (name, Some(endpoint)),
+            None => (rest, None),
+        };
+        let name = percent_decode(name);
+        let Some(&krate) = self.crates_by_name.get(&canonical_name(&name)) else {
+            let detail = format!("crate `{}` does not exist", name);
+            return (404, error(&detail));
+        };
+
+        let body = match endpoint {
+            None => self.krate(krate),
+            Some("versions") => self.versions(krate),
+            Some("reverse_dependencies") => {
+                let page = query.get("page").and_then(|page| page.parse().ok());
+                let per_page = query.get("per_page").and_then(|n| n.parse().ok());
+                let page = page.unwrap_or(1).max(1);
+                let per_page = per_page.unwrap_or(10).clamp(1, 100);
+                self.reverse_dependencies(krate, page, per_page)
+            }
+            Some("owners") => self.owners(krate),
+            Some("downloads") => self.downloads(krate),
+            Some(_) => return (404, error("Not Found")),
+        };
+        (200, body)
+    }
+
+    // GET /api/v1/crates/{name}
+    fn krate(&self, krate: &db_dump::crates::Row) -> Value {
+        let versions = self.sorted_versions(krate.id);
+        let keywords: Vec<Value> = self
+            .index
+            .keywords_of(krate.id)
+            .map(|row| {
+                json!({
+                    "id": row.keyword,
+                    "keyword": row.keyword,
+                    "created_at": datetime(row.created_at),
+                    "crates_cnt": row.crates_cnt,
+                })
+            })
+            .collect();
+        let categories: Vec<Value> = self
+            .index
+            .categories_of(krate.id)
+            .map(|row| {
+                json!({
+                    "id": row.slug,
+                    "category": row.category,
+                    "slug": row.slug,
+                    "description": row.description,
+                    "created_at": datetime(row.created_at),
+                    "crates_cnt": row.crates_cnt,
+                })
+            })
+            .collect();
+        json!({
+            "crate": self.crate_summary(krate, &versions),
+            "versions": versions
+                .iter()
+                .map(|row| self.version(krate, row))
+                .collect::<Vec<_>>(),
+            "keywords": keywords,
+            "categories": categories,
+        })
+    }
+
+    // GET /api/v1/crates/{name}/versions
+    fn versions(&self, krate: &db_dump::crates::Row) -> Value {
+        let versions = self.sorted_versions(krate.id);
+        json!({
+            "versions": versions
+                .iter()
+                .map(|row| self.version(krate, row))
+                .collect::<Vec<_>>(),
+            "meta": {
+                "total": versions.len(),
+                "next_page": null,
+            },
+        })
+    }
+
+    // GET /api/v1/crates/{name}/reverse_dependencies
+    //
+    // Dependents as listed by Index::reverse_dependencies, ordered by their
+    // total downloads like on crates.io.
+    fn reverse_dependencies(
+        &self,
+        krate: &db_dump::crates::Row,
+        page: usize,
+        per_page: usize,
+    ) -> Value {
+        let mut dependents = self.index.reverse_dependencies(krate.id);
+        dependents.sort_by_key(|dependent| {
+            (
+                Reverse(self.crate_downloads(dependent.krate.id)),
+                &dependent.krate.name,
+            )
+        });
+
+        let total = dependents.len();
+        let page = dependents.iter().skip((page - 1) * per_page).take(per_page);
+        let mut dependencies = Vec::new();
+        let mut versions = Vec::new();
+        for dependent in page {
+            let dependency = dependent.dependency;
+            dependencies.push(json!({
+                "id": dependency.id,
+                "version_id": dependency.version_id.0,
+                "crate_id": krate.name,
+                "req": dependency.req.to_string(),
+                "optional": dependency.optional,
+                "default_features": dependency.default_features,
+                "features": dependency.features,
+                "target": (!dependency.target.is_empty()).then_some(&dependency.target),
+                "kind": dependency.kind.as_str(),
+                "downloads": self.crate_downloads(dependent.krate.id),
+            }));
+            versions.push(self.version(dependent.krate, dependent.version));
+        }
+        json!({
+            "dependencies": dependencies,
+            "versions": versions,
+            "meta": { "total": total },
+        })
+    }
+
+    // GET /api/v1/crates/{name}/owners
+    fn owners(&self, krate: &db_dump::crates::Row) -> Value {
+        let mut users = Vec::new();
+        for owner in self.index.owners_of(krate.id) {
+            match owner.owner_id {
+                OwnerId::User(user_id) => {
+                    if let Some(row) = self.index.user_by_id(user_id) {
+                        let mut user = user(row);
+                        user["kind"] = json!("user");
+                        users.push(user);
+                    }
+                }
+                OwnerId::Team(team_id) => {
+                    if let Some(team) = self.index.team_by_id(team_id) {
+                        // Team logins are like github:org:team.
+                        let org = team.login.split(':').nth(1).unwrap_or_default();
+                        users.push(json!({
+                            "id": team.id.0,
+                            "login": team.login,
+                            "name": team.name,
+                            "avatar": team.avatar,
+                            "url": format!("https://github.com/{}", org),
+                            "kind": "team",
+                        }));
+                    }
+                }
+            }
+        }
+        json!({ "users": users })
+    }
+
+    // GET /api/v1/crates/{name}/downloads
+    fn downloads(&self, krate: &db_dump::crates::Row) -> Value {
+        let mut versions: Vec<_> = self.index.versions_of(krate.id).collect();
+        versions.sort_by_key(|row| Reverse(row.created_at));
+
+        let mut version_downloads = Vec::new();
+        let mut extra_downloads = BTreeMap::<NaiveDate, u64>::new();
+        for (i, version) in versions.iter().enumerate() {
+            let recent = self.recent_downloads.get(&version.id);
+            for &(date, downloads) in recent.into_iter().flatten() {
+                if i < DOWNLOADS_TOP_VERSIONS {
+                    version_downloads.push(json!({
+                        "version": version.id.0,
+                        "downloads": downloads,
+                        "date": date.to_string(),
+                    }));
+                } else {
+                    *extra_downloads.entry(date).or_default() += downloads;
+                }
+            }
+        }
+        let extra_downloads: Vec<Value> = extra_downloads
+            .into_iter()
+            .map(|(date, downloads)| {
+                json!({
+                    "date": date.to_string(),
+                    "downloads": downloads,
+                })
+            })
+            .collect();
+        json!({
+            "version_downloads": version_downloads,
+            "meta": { "extra_downloads": extra_downloads },
+        })
+    }
+
+    fn crate_summary(
+        &self,
+        krate: &db_dump::crates::Row,
+        versions: &[&db_dump::versions::Row],
+    ) -> Value {
+        let name = &krate.name;
+        let default_version = self.index.default_version(krate.id);
+        let unyanked = || versions.iter().filter(|row| !row.yanked);
+        let max_version = unyanked().next().map(|row| &row.num);
+        let max_stable_version = unyanked().find(|row| row.num.pre.is_empty());
+        let newest_version = unyanked().max_by_key(|row| row.created_at);
+        let recent_downloads: u64 = versions
+            .iter()
+            .filter_map(|row| self.recent_downloads.get(&row.id))
+            .flatten()
+            .map(|(_date, downloads)| downloads)
+            .sum();
+        json!({
+            "id": name,
+            "name": name,
+            "updated_at": datetime(krate.updated_at),
+            "versions": versions.iter().map(|row| row.id.0).collect::<Vec<_>>(),
+            "keywords": self
+                .index
+                .keywords_of(krate.id)
+                .map(|row| &row.keyword)
+                .collect::<Vec<_>>(),
+            "categories": self
+                .index
+                .categories_of(krate.id)
+                .map(|row| &row.slug)
+                .collect::<Vec<_>>(),
+            "badges": [],
+            "created_at": datetime(krate.created_at),
+            "downloads": self.crate_downloads(krate.id),
+            "recent_downloads": recent_downloads,
+            "default_version": default_version.map(|row| row.num.to_string()),
+            "num_versions": versions.len(),
+            "yanked": default_version.is_some_and(|row| row.yanked),
+            "max_version": max_version.map_or_else(|| "0.0.0".to_owned(), ToString::to_string),
+            "newest_version": newest_version
+                .map_or_else(|| "0.0.0".to_owned(), |row| row.num.to_string()),
+            "max_stable_version": max_stable_version.map(|row| row.num.to_string()),
+            "description": krate.description,
+            "homepage": krate.homepage,
+            "documentation": krate.documentation,
+            "repository": krate.repository,
+            "links": {
+                "version_downloads": format!("/api/v1/crates/{}/downloads", name),
+                "versions": null,
+                "owners": format!("/api/v1/crates/{}/owners", name),
+                "owner_team": format!("/api/v1/crates/{}/owner_team", name),
+                "owner_user": format!("/api/v1/crates/{}/owner_user", name),
+                "reverse_dependencies": format!("/api/v1/crates/{}/reverse_dependencies", name),
+            },
+            "exact_match": false,
+        })
+    }
+
+    fn version(&self, krate: &db_dump::crates::Row, row: &db_dump::versions::Row) -> Value {
+        let path = format!("/api/v1/crates/{}/{}", krate.name, row.num);
+        let published_by = row
+            .published_by
+            .and_then(|user_id| self.index.user_by_id(user_id))
+            .map(user);
+        json!({
+            "id": row.id.0,
+            "crate": krate.name,
+            "num": row.num.to_string(),
+            "dl_path": format!("{}/download", path),
+            "readme_path": format!("{}/readme", path),
+            "updated_at": datetime(row.updated_at),
+            "created_at": datetime(row.created_at),
+            "downloads": row.downloads,
+            "features": row.features,
+            "yanked": row.yanked,
+            "yank_message": null,
+            "lib_links": row.links,
+            "license": row.license,
+            "links": {
+                "dependencies": format!("{}/dependencies", path),
+                "version_downloads": format!("{}/downloads", path),
+                "authors": format!("{}/authors", path),
+            },
+            "crate_size": row.crate_size,
+            "published_by": published_by,
+            "audit_actions": [],
+            "checksum": row.checksum_hex(),
+            "rust_version": row.rust_version.as_ref().map(ToString::to_string),
+            "has_lib": row.has_lib,
+            "bin_names": row.bin_names,
+            "edition": row.edition.map(|edition| edition.to_string()),
+            "description": row.description,
+            "homepage": row.homepage,
+            "documentation": row.documentation,
+            "repository": row.repository,
+        })
+    }
+
+    // Versions of the crate from highest to lowest, as crates.io lists them.
+    fn sorted_versions(&self, crate_id: CrateId) -> Vec<&db_dump::versions::Row> {
+        let mut versions: Vec<_> = self.index.versions_of(crate_id).collect();
+        versions.sort_by(|a, b| b.num.cmp_precedence(&a.num).then(b.id.cmp(&a.id)));
+        versions
+    }
+
+    fn crate_downloads(&self, crate_id: CrateId) -> u64 {
+        self.crate_downloads.get(&crate_id).copied().unwrap_or(0)
+    }
+}
+
+// Crate names that differ only in case or in `-` versus `_` refer to the same
+// crate, as on crates.io.
+fn canonical_name(name: &str) -> String {
+    name.to_ascii_lowercase().replace('-', "_")
+}
+
+// Decodes %XX escapes in a path segment. Malformed escapes are left as they
+// are, and invalid UTF-8 is replaced.
+fn percent_decode(segment: &str) -> String {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match tail.get(..2) {
+            Some(&[hi, lo]) if byte == b'%' && hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => {
+                bytes.push(hex_digit(hi) << 4 | hex_digit(lo));
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn hex_digit(digit: u8) -> u8 {
+    match digit {
+        b'0'..=b'9' => digit - b'0',
+        _ => (digit | 0x20) - b'a' + 10,
+    }
+}
+
+fn datetime(datetime: DateTime<Utc>) -> String {
+    // Like 2017-01-06T14:23:11.042074+00:00, as served by crates.io.
+    datetime.to_rfc3339_opts(SecondsFormat::Micros, false)
+}
+
+fn user(row: &db_dump::users::Row) -> Value {
+    json!({
+        "id": row.id.0,
+        "login": row.gh_login,
+        "name": row.name,
+        "avatar": row.gh_avatar,
+        "url": format!("https://github.com/{}", row.gh_login),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Api;
+    use chrono::{DateTime, TimeDelta};
+    use db_dump::dependencies::DependencyKind;
+    use db_dump::testing::DumpBuilder;
+    use db_dump::{Date, DbDump};
+    use serde_json::{json, Value};
+    use tiny_http::Method;
+
+    fn fixture() -> DbDump {
+        let mut builder = DumpBuilder::new();
+        let start = DateTime::from_timestamp(1_672_531_200, 0).unwrap();
+        let serde = builder.krate("serde").id;
+        let mut versions = Vec::new();
+        let nums = ["0.8.0", "0.9.0", "1.0.0", "1.0.1-alpha.1", "0.9.1", "1.0.1"];
+        for (day, num) in (0..).zip(nums) {
+            builder.timestamp(start + TimeDelta::days(day));
+            let version = builder.version(serde, num);
+            version.yanked = num == "1.0.1";
+            versions.push(version.id);
+        }
+        let [v080, _, v100, ..] = versions[..] else {
+            unreachable!()
+        };
+        builder.download_series(v100, Date::from_ymd(2024, 1, 1), &[1; 100]);
+        builder.downloads(v080, Date::from_ymd(2024, 1, 1), 5);
+        builder.downloads(v080, Date::from_ymd(2024, 4, 9), 7);
+
+        for (name, downloads) in [("a", 10), ("b", 30), ("c", 20)] {
+            let krate = builder.krate(name).id;
+            let version = builder.version(krate, "1.0.0").id;
+            if name == "b" {
+                builder.dependency(version, serde, "^1.0").kind = DependencyKind::Dev;
+            }
+            builder.dependency(version, serde, "^1");
+            builder.downloads(version, Date::from_ymd(2024, 1, 1), downloads);
+        }
+
+        // Only the old version of d depends on serde.
+        let d = builder.krate("d").id;
+        let d_old = builder.version(d, "0.1.0").id;
+        builder.dependency(d_old, serde, "^0.9");
+        builder.version(d, "0.2.0");
+
+        builder.build()
+    }
+
+    fn get(api: &Api, url: &str) -> Value {
+        let (status, body) = api.handle(&Method::Get, url);
+        assert_eq!(status, 200, "{}", body);
+        body
+    }
+
+    fn nums(versions: &Value) -> Vec<&str> {
+        versions
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|version| version["num"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_crate() {
+        let db = fixture();
+        let api = Api::new(db.index());
+        let body = get(&api, "/api/v1/crates/serde");
+
+        // Precedence order, in which a prerelease sorts below its release.
+        assert_eq!(
+            nums(&body["versions"]),
+            ["1.0.1", "1.0.1-alpha.1", "1.0.0", "0.9.1", "0.9.0", "0.8.0"],
+        );
+        let krate = &body["crate"];
+        assert_eq!(krate["max_version"], "1.0.1-alpha.1");
+        assert_eq!(krate["max_stable_version"], "1.0.0");
+        assert_eq!(krate["newest_version"], "0.9.1");
+        assert_eq!(krate["default_version"], "1.0.0");
+        assert_eq!(krate["yanked"], false);
+        assert_eq!(krate["num_versions"], 6);
+        assert_eq!(krate["downloads"], 112);
+        assert_eq!(krate["recent_downloads"], 97);
+
+        let body = get(&api, "/api/v1/crates/serde/versions");
+        assert_eq!(nums(&body["versions"])[..2], ["1.0.1", "1.0.1-alpha.1"]);
+        assert_eq!(body["meta"]["total"], 6);
+    }
+
+    #[test]
+    fn test_reverse_dependencies() {
+        let db = fixture();
+        let api = Api::new(db.index());
+
+        let body = get(
+            &api,
+            "/api/v1/crates/serde/reverse_dependencies?page=1&per_page=2",
+        );
+        let crates: Vec<&Value> = body["versions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|version| &version["crate"])
+            .collect();
+        assert_eq!(crates, ["b", "c"]);
+        assert_eq!(body["dependencies"][0]["kind"], "normal");
+        assert_eq!(body["dependencies"][0]["req"], "^1");
+        assert_eq!(body["dependencies"][0]["crate_id"], "serde");
+        assert_eq!(body["dependencies"][0]["downloads"], 30);
+        assert_eq!(body["meta"]["total"], 3);
+
+        let body = get(
+            &api,
+            "/api/v1/crates/serde/reverse_dependencies?page=2&per_page=2",
+        );
+        assert_eq!(body["versions"][0]["crate"], "a");
+        assert_eq!(body["dependencies"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_downloads() {
+        let db = fixture();
+        let api = Api::new(db.index());
+        let body = get(&api, "/api/v1/crates/serde/downloads");
+
+        // The 90 days up to the last date in the dump, for the 5 most recently
+        // published versions, and the rest summed into extra_downloads.
+        let version_downloads = body["version_downloads"].as_array().unwrap();
+        assert_eq!(version_downloads.len(), 90);
+        assert_eq!(version_downloads[0]["date"], "2024-01-11");
+        assert_eq!(version_downloads[89]["date"], "2024-04-09");
+        assert!(version_downloads
+            .iter()
+            .all(|row| row["version"] == 3 && row["downloads"] == 1));
+        assert_eq!(
+            body["meta"]["extra_downloads"],
+            json!([{ "date": "2024-04-09", "downloads": 7 }]),
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let db = fixture();
+        let api = Api::new(db.index());
+        assert_eq!(api.handle(&Method::Get, "/api/v1/crates/nope").0, 404);
+        assert_eq!(api.handle(&Method::Get, "/api/v1/crates/serde/nope").0, 404);
+        assert_eq!(api.handle(&Method::Get, "/api/v1/summary").0, 404);
+        assert_eq!(api.handle(&Method::Post, "/api/v1/crates/serde").0, 405);
+    }
+
+    #[test]
+    fn test_crate_name() {
+        let mut builder = DumpBuilder::new();
+        let serde_json = builder.krate("serde_json").id;
+        builder.version(serde_json, "1.0.0");
+        let db = builder.build();
+        let api = Api::new(db.index());
+
+        for name in ["serde_json", "Serde_Json", "serde-json", "SERDE%2Djson"] {
+            let body = get(&api, &format!("/api/v1/crates/{}", name));
+            assert_eq!(body["crate"]["name"], "serde_json", "{}", name);
+        }
+        let (status, body) = api.handle(&Method::Get, "/api/v1/crates/serde%20json");
+        assert_eq!(status, 404);
+        assert_eq!(
+            body["errors"][0]["detail"],
+            "crate `serde json` does not exist"
+        );
+    }
+}
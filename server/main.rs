@@ -0,0 +1,68 @@
+// Usage:
+//
+//     cargo run --release -- path/to/db-dump.tar.gz
+//
+// Loads the dump and serves the following endpoints of the crates.io API,
+// with responses shaped like those of crates.io, for use in place of the live
+// service in offline tests:
+//
+//     GET /api/v1/crates/{name}
+//     GET /api/v1/crates/{name}/versions
+//     GET /api/v1/crates/{name}/reverse_dependencies?page=1&per_page=10
+//     GET /api/v1/crates/{name}/owners
+//     GET /api/v1/crates/{name}/downloads
+//
+// As on crates.io, {name} may be percent-encoded and is matched ignoring case
+// and the difference between `-` and `_`.
+//
+// Data that the dump does not contain, such as badges, yank messages and
+// audit actions, is empty. Recent downloads and the downloads endpoint cover
+// the 90 days up to the last date of version_downloads in the dump, rather
+// than up to today.
+//
+// The server listens on 127.0.0.1:8888, or the address given by the --listen
+// flag.
+
+#![allow(clippy::uninlined_format_args)]
+
+mod api;
+
+use crate::api::Api;
+use anyhow::{format_err, Result};
+use clap::Parser;
+use serde_json::Value;
+use std::io::{self, Write};
+use tiny_http::{Header, Request, Response, Server};
+
+#[derive(Parser, Debug)]
+#[command(name = "db-dump-server", author, version)]
+struct Opt {
+    #[arg(short, long, default_value = "127.0.0.1:8888")]
+    listen: String,
+
+    dump: std::path::PathBuf,
+}
+
+fn main() -> Result<()> {
+    let opt = Opt::parse();
+
+    let db = db_dump::load_all(&opt.dump)?;
+    let api = Api::new(db.index());
+
+    let server = Server::http(&opt.listen).map_err(|e| format_err!("{}: {}", opt.listen, e))?;
+    let _ = writeln!(io::stderr(), "listening on http://{}", opt.listen);
+
+    for request in server.incoming_requests() {
+        let (status, body) = api.handle(request.method(), request.url());
+        respond(request, status, &body);
+    }
+    Ok(())
+}
+
+fn respond(request: Request, status: u16, body: &Value) {
+    let content_type = Header::from_bytes("Content-Type", "application/json; charset=utf-8");
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(content_type.unwrap());
+    let _ = request.respond(response);
+}
@@ -0,0 +1,43 @@
+// Usage:
+//
+//     cargo run --release -- path/to/db-dump.tar.gz
+//
+// Converts the dump into a SQLite database with one table per csv file, plus
+// child tables for the list-valued columns of versions and dependencies. See
+// the documentation of the db_dump::sqlite module for the schema.
+//
+// The output file will be named db-dump.sqlite3, or can be specified
+// explicitly using the --out flag. It must not already exist.
+
+#![allow(clippy::uninlined_format_args)]
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+// Only to enable the "bundled" feature, so that the tool does not depend on a
+// system SQLite.
+use rusqlite as _;
+
+#[derive(Parser, Debug)]
+#[command(name = "db-dump-sqlite", author, version)]
+struct Opt {
+    #[arg(short, long, default_value = "db-dump.sqlite3")]
+    out: PathBuf,
+
+    dump: PathBuf,
+}
+
+fn main() -> Result<()> {
+    let opt = Opt::parse();
+
+    if opt.out.exists() {
+        bail!("{} already exists", opt.out.display());
+    }
+
+    db_dump::sqlite::export(&opt.dump, &opt.out)?;
+
+    let _ = writeln!(io::stderr(), "wrote {}", opt.out.display());
+    Ok(())
+}
@@ -0,0 +1,206 @@
+use crate::crate_owners::OwnerId;
+use crate::hex::Hex;
+use crate::teams::TeamId;
+use crate::users::UserId;
+use crate::DbDump;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::BTreeMap as Map;
+
+impl DbDump {
+    /// Replace personal data about users and teams with pseudonyms derived
+    /// from the given secret key, keeping the join structure intact.
+    ///
+    /// - User and team ids are renumbered, in every table that refers to
+    ///   them: users, teams, crate_owners (owner_id and created_by),
+    ///   versions.published_by, and deleted_crates.deleted_by. Rows of users
+    ///   and teams are reordered by their new id.
+    /// - Logins become pseudonyms like `user-3f9a04c1d2e87b65` and
+    ///   `github:org-0d1e2f3a4b5c6d7e:team-8899aabbccddeeff`.
+    /// - GitHub ids and org ids are renumbered too.
+    /// - Names and avatars are dropped.
+    ///
+    /// Pseudonymous logins are a keyed hash (HMAC-SHA256) of the crates.io
+    /// user or team id, so the same user has the same pseudonym in every dump
+    /// anonymized with the same key, and it cannot be reversed or recomputed
+    /// without the key. The renumbered ids, on the other hand, depend on
+    /// which users are present and are only consistent within one dump.
+    ///
+    /// Free text that may mention people, like crate descriptions and
+    /// repository URLs, is left as is.
+    ///
+    /// *This method is available only if db-dump is built with the
+    /// `"anonymize"` feature.*
+    pub fn anonymize(&mut self, key: &[u8]) {
+        let pseudonyms = Pseudonyms::new(key);
+
+        let mut user_ids = Vec::new();
+        user_ids.extend(self.users.iter().map(|row| row.id));
+        for row in &self.crate_owners {
+            if let OwnerId::User(user_id) = row.owner_id {
+                user_ids.push(user_id);
+            }
+            user_ids.extend(row.created_by);
+        }
+        user_ids.extend(self.versions.iter().filter_map(|row| row.published_by));
+        user_ids.extend(self.deleted_crates.iter().filter_map(|row| row.deleted_by));
+        let users = renumber(user_ids, |user_id| {
+            pseudonyms.digest("user", &user_id.0.to_le_bytes())
+        });
+
+        let mut team_ids = Vec::new();
+        team_ids.extend(self.teams.iter().map(|row| row.id));
+        for row in &self.crate_owners {
+            if let OwnerId::Team(team_id) = row.owner_id {
+                team_ids.push(team_id);
+            }
+        }
+        let teams = renumber(team_ids, |team_id| {
+            pseudonyms.digest("team", &team_id.0.to_le_bytes())
+        });
+        let orgs = renumber(self.teams.iter().filter_map(|row| row.org_id), |org_id| {
+            pseudonyms.digest("org", &org_id.to_le_bytes())
+        });
+
+        let user = |user_id: UserId| UserId(users[&user_id]);
+        let team = |team_id: TeamId| TeamId(teams[&team_id]);
+
+        for row in &mut self.users {
+            row.gh_login = pseudonyms.name("user", "user", &row.id.0.to_le_bytes());
+            row.id = user(row.id);
+            row.name = None;
+            row.gh_avatar = String::new();
+            row.gh_id = i32::try_from(row.id.0).unwrap_or(i32::MAX);
+        }
+        self.users.sort();
+
+        for row in &mut self.teams {
+            let org = row.login.split(':').nth(1).unwrap_or_default();
+            row.login = format!(
+                "github:{}:{}",
+                pseudonyms.name("org", "org-name", org.as_bytes()),
+                pseudonyms.name("team", "team", &row.id.0.to_le_bytes()),
+            );
+            row.id = team(row.id);
+            row.github_id = row.id.0;
+            row.name = String::new();
+            row.avatar = String::new();
+            row.org_id = row.org_id.map(|org_id| orgs[&org_id]);
+        }
+        self.teams.sort();
+
+        for row in &mut self.crate_owners {
+            row.owner_id = match row.owner_id {
+                OwnerId::User(user_id) => OwnerId::User(user(user_id)),
+                OwnerId::Team(team_id) => OwnerId::Team(team(team_id)),
+            };
+            row.created_by = row.created_by.map(user);
+        }
+        for row in &mut self.versions {
+            row.published_by = row.published_by.map(user);
+        }
+        for row in &mut self.deleted_crates {
+            row.deleted_by = row.deleted_by.map(user);
+        }
+    }
+}
+
+struct Pseudonyms {
+    mac: Hmac<Sha256>,
+}
+
+impl Pseudonyms {
+    fn new(key: &[u8]) -> Self {
+        Pseudonyms {
+            // HMAC accepts keys of any length.
+            mac: Hmac::new_from_slice(key).unwrap(),
+        }
+    }
+
+    // Keyed hash of a value. The domain separates hashes of equal values
+    // used for different purposes, such as a user id and a team id.
+    fn digest(&self, domain: &str, value: &[u8]) -> [u8; 32] {
+        let mut mac = self.mac.clone();
+        mac.update(domain.as_bytes());
+        mac.update(&[0]);
+        mac.update(value);
+        mac.finalize().into_bytes().into()
+    }
+
+    fn name(&self, prefix: &str, domain: &str, value: &[u8]) -> String {
+        format!("{}-{}", prefix, Hex(&self.digest(domain, value)[..8]))
+    }
+}
+
+// Assigns new ids 1..=n to the distinct keys, in order of their keyed digest,
+// so that the new ids reveal nothing about the order of the original ones.
+fn renumber<K>(keys: impl IntoIterator<Item = K>, digest: impl Fn(K) -> [u8; 32]) -> Map<K, u32>
+where
+    K: Ord + Copy,
+{
+    let mut keys: Vec<([u8; 32], K)> = keys.into_iter().map(|key| (digest(key), key)).collect();
+    keys.sort_unstable();
+    keys.dedup();
+    keys.into_iter()
+        .enumerate()
+        .map(|(i, (_digest, key))| (key, i as u32 + 1))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crate_owners::OwnerId;
+    use crate::testing::DumpBuilder;
+
+    #[test]
+    fn test_anonymize() {
+        let mut builder = DumpBuilder::new();
+        let alice = builder.user("alice");
+        alice.name = Some("Alice Liddell".to_owned());
+        let alice = alice.id;
+        let bob = builder.user("bob").id;
+        let team = builder.team("github:wonderland:owners").id;
+        let tea = builder.krate("tea").id;
+        builder.version(tea, "1.0.0").published_by = Some(bob);
+        builder.owner(tea, alice);
+        builder.owner(tea, team).created_by = Some(alice);
+        let cake = builder.krate("cake").id;
+        builder.version(cake, "1.0.0").published_by = Some(alice);
+        builder.owner(cake, bob);
+        builder.deleted_crate("hatter").deleted_by = Some(bob);
+        let original = builder.build();
+
+        let mut db = original.clone();
+        db.anonymize(b"secret");
+        assert!(db.validate().is_empty());
+
+        let text = format!("{:?}{:?}", db.users, db.teams);
+        for personal in ["alice", "Alice", "bob", "wonderland", "avatars"] {
+            assert!(!text.contains(personal), "{}", personal);
+        }
+        assert!(db.users.iter().all(|row| row.gh_login.starts_with("user-")));
+        assert!(db.teams[0].login.starts_with("github:org-"));
+
+        // Whoever published tea is the sole owner of cake, and whoever owns
+        // tea added the team and published cake.
+        let index = db.index();
+        let publisher = db.versions[0].published_by.unwrap();
+        let owners: Vec<OwnerId> = index.owners_of(cake).map(|row| row.owner_id).collect();
+        assert!(matches!(owners[..], [OwnerId::User(user_id)] if user_id == publisher));
+        let tea_owners: Vec<_> = index.owners_of(tea).collect();
+        let OwnerId::User(owner) = tea_owners[0].owner_id else {
+            panic!();
+        };
+        assert_eq!(tea_owners[1].created_by, Some(owner));
+        assert_eq!(db.versions[1].published_by, Some(owner));
+        assert_eq!(db.deleted_crates[0].deleted_by, Some(publisher));
+
+        let mut again = original.clone();
+        again.anonymize(b"secret");
+        assert_eq!(format!("{:?}", again.users), format!("{:?}", db.users));
+
+        let mut other = original;
+        other.anonymize(b"other key");
+        assert_ne!(other.users[0].gh_login, db.users[0].gh_login);
+    }
+}
@@ -1,4 +1,5 @@
 use serde::de::{Deserializer, Visitor};
+use serde::ser::Serializer;
 use std::fmt;
 
 struct BoolVisitor;
@@ -30,3 +31,11 @@ where
 {
     deserializer.deserialize_str(BoolVisitor)
 }
+
+#[allow(clippy::trivially_copy_pass_by_ref)] // signature required by serialize_with
+pub(crate) fn ser<S>(value: &bool, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(if *value { "t" } else { "f" })
+}
@@ -0,0 +1,1004 @@
+use crate::crate_owners::OwnerId;
+use crate::dependencies::DependencyKind;
+use crate::error::{err, Error, Result};
+use crate::Date;
+use crate::DbDump;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use memmap::Mmap;
+use semver::{BuildMetadata, Comparator, Op, Prerelease, Version, VersionReq};
+use std::collections::{BTreeMap as Map, HashMap};
+use std::fs::{self, File, Metadata};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use tempfile::NamedTempFile;
+
+const MAGIC: [u8; 8] = *b"db-dump\0";
+
+// Bump whenever the encoding of any row changes, including when a field is
+// added to a table. Caches written with a different format version are
+// rejected rather than misread.
+const FORMAT_VERSION: u32 = 1;
+
+/// Identifies the archive a cache was built from, by the archive's size and
+/// modification time. For a directory of JSON Lines, by the total size and
+/// latest modification time of the `.jsonl` files in it.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) struct Source {
+    len: u64,
+    secs: u64,
+    nanos: u32,
+}
+
+impl Source {
+    pub(crate) fn of(path: &Path) -> Result<Option<Self>> {
+        let metadata = fs::metadata(path)?;
+        if !metadata.is_dir() {
+            return Ok(Source::of_file(&metadata));
+        }
+
+        // Rewriting the files in a directory of JSON Lines does not
+        // necessarily change the modification time of the directory, so
+        // combine those of the files.
+        let mut combined: Option<Source> = None;
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            if entry.path().extension().is_none_or(|ext| ext != "jsonl") {
+                continue;
+            }
+            let Some(file) = Source::of_file(&entry.metadata()?) else {
+                return Ok(None);
+            };
+            combined = Some(match combined {
+                None => file,
+                Some(combined) => {
+                    let latest = if (file.secs, file.nanos) > (combined.secs, combined.nanos) {
+                        file
+                    } else {
+                        combined
+                    };
+                    Source {
+                        len: combined.len + file.len,
+                        ..latest
+                    }
+                }
+            });
+        }
+        Ok(combined)
+    }
+
+    fn of_file(metadata: &Metadata) -> Option<Self> {
+        let modified = metadata.modified().ok()?;
+        let since_epoch = modified.duration_since(UNIX_EPOCH).ok()?;
+        Some(Source {
+            len: metadata.len(),
+            secs: since_epoch.as_secs(),
+            nanos: since_epoch.subsec_nanos(),
+        })
+    }
+}
+
+impl DbDump {
+    /// Write the whole dump to a compact binary file, which
+    /// [`DbDump::load_cache`] reads back far faster than the original CSV can
+    /// be parsed.
+    ///
+    /// The format is versioned and private to this library. A cache written
+    /// by a different version of db-dump may fail to load, in which case it
+    /// needs to be regenerated from the original archive.
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    ///
+    /// fn main() -> db_dump::Result<()> {
+    ///     let cache = Path::new("./db-dump.cache");
+    ///     let db = if cache.exists() {
+    ///         db_dump::DbDump::load_cache(cache)?
+    ///     } else {
+    ///         let db = db_dump::load_all("./db-dump.tar.gz")?;
+    ///         db.save_cache(cache)?;
+    ///         db
+    ///     };
+    ///     println!("{} crates", db.crates.len());
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// See [`Loader::cache`](crate::Loader::cache) for a cache that is
+    /// invalidated automatically when the archive changes.
+    pub fn save_cache(&self, path: impl AsRef<Path>) -> Result<()> {
+        write(path.as_ref(), self, None)
+    }
+
+    /// Read a dump previously written by [`DbDump::save_cache`].
+    pub fn load_cache(path: impl AsRef<Path>) -> Result<DbDump> {
+        let (_source, db) = read(path.as_ref())?;
+        Ok(db)
+    }
+}
+
+pub(crate) fn write(path: &Path, db: &DbDump, source: Option<Source>) -> Result<()> {
+    // Write to a uniquely named temporary file alongside the destination and
+    // rename it into place, so that an interrupted write never leaves a
+    // truncated cache, and concurrent writers do not clobber each other. The
+    // temporary file is deleted if anything fails.
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut tmp = NamedTempFile::new_in(dir)?;
+    write_to(tmp.as_file_mut(), db, source)?;
+    tmp.persist(path).map_err(|error| error.error)?;
+    Ok(())
+}
+
+fn write_to(file: &mut File, db: &DbDump, source: Option<Source>) -> io::Result<()> {
+    let mut encoder = Encoder {
+        w: BufWriter::new(file),
+        strings: HashMap::new(),
+    };
+    encoder.w.write_all(&MAGIC)?;
+    encoder.w.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    match source {
+        None => encoder.w.write_all(&[0])?,
+        Some(source) => {
+            encoder.w.write_all(&[1])?;
+            encoder.w.write_all(&source.len.to_le_bytes())?;
+            encoder.w.write_all(&source.secs.to_le_bytes())?;
+            encoder.w.write_all(&source.nanos.to_le_bytes())?;
+        }
+    }
+    db.encode(&mut encoder)?;
+    encoder
+        .w
+        .into_inner()
+        .map_err(io::IntoInnerError::into_error)?;
+    Ok(())
+}
+
+pub(crate) fn read(path: &Path) -> Result<(Option<Source>, DbDump)> {
+    let file = File::open(path)?;
+    let mmap = unsafe { Mmap::map(&file) }?;
+    let mut decoder = Decoder {
+        bytes: &mmap,
+        strings: Vec::new(),
+    };
+    let source = decoder.header()?;
+    let db = DbDump::decode(&mut decoder)?;
+    if !decoder.bytes.is_empty() {
+        return Err(corrupt());
+    }
+    Ok((source, db))
+}
+
+/// Read just the source recorded in the header of a cache file.
+pub(crate) fn source(path: &Path) -> Result<Option<Source>> {
+    let file = File::open(path)?;
+    let mmap = unsafe { Mmap::map(&file) }?;
+    let mut decoder = Decoder {
+        bytes: &mmap,
+        strings: Vec::new(),
+    };
+    decoder.header()
+}
+
+fn corrupt() -> Error {
+    err(format_args!("corrupt db dump cache"))
+}
+
+struct Encoder<W> {
+    w: W,
+    // Strings already written, by their position in the file. Shared strings
+    // are written once and referenced by position afterward, which also means
+    // the loaded dump comes back interned.
+    strings: HashMap<Arc<str>, u64>,
+}
+
+impl<W: Write> Encoder<W> {
+    fn varint(&mut self, mut n: u64) -> io::Result<()> {
+        let mut buf = [0u8; 10];
+        let mut i = 0;
+        while n >= 0x80 {
+            buf[i] = n as u8 | 0x80;
+            n >>= 7;
+            i += 1;
+        }
+        buf[i] = n as u8;
+        self.w.write_all(&buf[..=i])
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    fn zigzag(&mut self, n: i64) -> io::Result<()> {
+        self.varint(((n << 1) ^ (n >> 63)) as u64)
+    }
+
+    fn str(&mut self, string: &str) -> io::Result<()> {
+        self.varint(string.len() as u64)?;
+        self.w.write_all(string.as_bytes())
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    strings: Vec<Arc<str>>,
+}
+
+impl<'a> Decoder<'a> {
+    fn header(&mut self) -> Result<Option<Source>> {
+        if self.take(MAGIC.len())? != MAGIC {
+            return Err(err(format_args!("not a db dump cache")));
+        }
+        let version = u32::from_le_bytes(self.array()?);
+        if version != FORMAT_VERSION {
+            return Err(err(format_args!(
+                "unsupported db dump cache format version {}, expected {}",
+                version, FORMAT_VERSION,
+            )));
+        }
+        Ok(match self.u8()? {
+            0 => None,
+            1 => Some(Source {
+                len: u64::from_le_bytes(self.array()?),
+                secs: u64::from_le_bytes(self.array()?),
+                nanos: u32::from_le_bytes(self.array()?),
+            }),
+            _ => return Err(corrupt()),
+        })
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.bytes.len() {
+            return Err(corrupt());
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        let (&byte, rest) = self.bytes.split_first().ok_or_else(corrupt)?;
+        self.bytes = rest;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut n = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift == 63 && byte > 1 {
+                return Err(corrupt());
+            }
+            n |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+            shift += 7;
+        }
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    fn zigzag(&mut self) -> Result<i64> {
+        let n = self.varint()?;
+        Ok((n >> 1) as i64 ^ -((n & 1) as i64))
+    }
+
+    fn len(&mut self) -> Result<usize> {
+        usize::try_from(self.varint()?).map_err(|_| corrupt())
+    }
+
+    fn str(&mut self) -> Result<&'a str> {
+        let len = self.len()?;
+        str::from_utf8(self.take(len)?).map_err(|_| corrupt())
+    }
+}
+
+trait Encode {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()>;
+}
+
+trait Decode: Sized {
+    fn decode(d: &mut Decoder) -> Result<Self>;
+}
+
+impl Encode for bool {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        e.w.write_all(&[u8::from(*self)])
+    }
+}
+
+impl Decode for bool {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        match d.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(corrupt()),
+        }
+    }
+}
+
+macro_rules! unsigned {
+    ($($int:ty)*) => {
+        $(
+            impl Encode for $int {
+                fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+                    e.varint(u64::from(*self))
+                }
+            }
+
+            impl Decode for $int {
+                fn decode(d: &mut Decoder) -> Result<Self> {
+                    <$int>::try_from(d.varint()?).map_err(|_| corrupt())
+                }
+            }
+        )*
+    };
+}
+
+unsigned!(u16 u32 u64);
+
+impl Encode for i32 {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        e.zigzag(i64::from(*self))
+    }
+}
+
+impl Decode for i32 {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        i32::try_from(d.zigzag()?).map_err(|_| corrupt())
+    }
+}
+
+impl Encode for String {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        e.str(self)
+    }
+}
+
+impl Decode for String {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        d.str().map(str::to_owned)
+    }
+}
+
+// 0 followed by the string's contents for the first occurrence of a string,
+// or 1 + the index of an earlier occurrence.
+impl Encode for Arc<str> {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        if let Some(&i) = e.strings.get(self) {
+            e.varint(i + 1)
+        } else {
+            let i = e.strings.len() as u64;
+            e.strings.insert(Arc::clone(self), i);
+            e.varint(0)?;
+            e.str(self)
+        }
+    }
+}
+
+impl Decode for Arc<str> {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        match d.len()? {
+            0 => {
+                let string = Arc::<str>::from(d.str()?);
+                d.strings.push(Arc::clone(&string));
+                Ok(string)
+            }
+            i => d.strings.get(i - 1).cloned().ok_or_else(corrupt),
+        }
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        match self {
+            None => e.w.write_all(&[0]),
+            Some(value) => {
+                e.w.write_all(&[1])?;
+                value.encode(e)
+            }
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        match d.u8()? {
+            0 => Ok(None),
+            1 => T::decode(d).map(Some),
+            _ => Err(corrupt()),
+        }
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        e.varint(self.len() as u64)?;
+        for element in self {
+            element.encode(e)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let len = d.len()?;
+        // Every element occupies at least one byte, so a length larger than
+        // the rest of the input indicates corruption rather than a reason to
+        // attempt a huge allocation.
+        if len > d.bytes.len() {
+            return Err(corrupt());
+        }
+        let mut vec = Vec::with_capacity(len);
+        for _ in 0..len {
+            vec.push(T::decode(d)?);
+        }
+        Ok(vec)
+    }
+}
+
+impl Encode for Map<Arc<str>, Vec<Arc<str>>> {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        e.varint(self.len() as u64)?;
+        for (key, values) in self {
+            key.encode(e)?;
+            values.encode(e)?;
+        }
+        Ok(())
+    }
+}
+
+impl Decode for Map<Arc<str>, Vec<Arc<str>>> {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let len = d.len()?;
+        let mut map = Map::new();
+        for _ in 0..len {
+            let key = Decode::decode(d)?;
+            let values = Decode::decode(d)?;
+            map.insert(key, values);
+        }
+        Ok(map)
+    }
+}
+
+impl Encode for [u8; 32] {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        e.w.write_all(self)
+    }
+}
+
+impl Decode for [u8; 32] {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        d.array()
+    }
+}
+
+impl Encode for DateTime<Utc> {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        e.zigzag(self.timestamp())?;
+        e.varint(u64::from(self.timestamp_subsec_nanos()))
+    }
+}
+
+impl Decode for DateTime<Utc> {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let secs = d.zigzag()?;
+        let nanos = u32::decode(d)?;
+        DateTime::from_timestamp(secs, nanos).ok_or_else(corrupt)
+    }
+}
+
+impl Encode for Date<Utc> {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        e.zigzag(i64::from(self.naive_utc().num_days_from_ce()))
+    }
+}
+
+impl Decode for Date<Utc> {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let day = i32::try_from(d.zigzag()?).map_err(|_| corrupt())?;
+        NaiveDate::from_num_days_from_ce_opt(day)
+            .map(Date::from)
+            .ok_or_else(corrupt)
+    }
+}
+
+impl Encode for Version {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        e.varint(self.major)?;
+        e.varint(self.minor)?;
+        e.varint(self.patch)?;
+        e.str(&self.pre)?;
+        e.str(&self.build)
+    }
+}
+
+impl Decode for Version {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(Version {
+            major: d.varint()?,
+            minor: d.varint()?,
+            patch: d.varint()?,
+            pre: prerelease(d)?,
+            build: match d.str()? {
+                "" => BuildMetadata::EMPTY,
+                build => BuildMetadata::new(build).map_err(|_| corrupt())?,
+            },
+        })
+    }
+}
+
+fn prerelease(d: &mut Decoder) -> Result<Prerelease> {
+    match d.str()? {
+        "" => Ok(Prerelease::EMPTY),
+        pre => Prerelease::new(pre).map_err(|_| corrupt()),
+    }
+}
+
+impl Encode for VersionReq {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        e.varint(self.comparators.len() as u64)?;
+        for comparator in &self.comparators {
+            e.w.write_all(&[match comparator.op {
+                Op::Exact => 0,
+                Op::Greater => 1,
+                Op::GreaterEq => 2,
+                Op::Less => 3,
+                Op::LessEq => 4,
+                Op::Tilde => 5,
+                Op::Caret => 6,
+                Op::Wildcard => 7,
+                _ => {
+                    return Err(io::Error::other(format!(
+                        "unsupported version requirement: {}",
+                        self,
+                    )));
+                }
+            }])?;
+            e.varint(comparator.major)?;
+            comparator.minor.encode(e)?;
+            comparator.patch.encode(e)?;
+            e.str(&comparator.pre)?;
+        }
+        Ok(())
+    }
+}
+
+impl Decode for VersionReq {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let len = d.len()?;
+        let mut comparators = Vec::with_capacity(len.min(d.bytes.len()));
+        for _ in 0..len {
+            comparators.push(Comparator {
+                op: match d.u8()? {
+                    0 => Op::Exact,
+                    1 => Op::Greater,
+                    2 => Op::GreaterEq,
+                    3 => Op::Less,
+                    4 => Op::LessEq,
+                    5 => Op::Tilde,
+                    6 => Op::Caret,
+                    7 => Op::Wildcard,
+                    _ => return Err(corrupt()),
+                },
+                major: d.varint()?,
+                minor: Decode::decode(d)?,
+                patch: Decode::decode(d)?,
+                pre: prerelease(d)?,
+            });
+        }
+        Ok(VersionReq { comparators })
+    }
+}
+
+impl Encode for OwnerId {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        e.w.write_all(&[self.kind()])?;
+        self.id().encode(e)
+    }
+}
+
+impl Decode for OwnerId {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let kind = d.u8()?;
+        OwnerId::new(u32::decode(d)?, kind).ok_or_else(corrupt)
+    }
+}
+
+impl Encode for DependencyKind {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        e.w.write_all(&[self.as_u8()])
+    }
+}
+
+impl Decode for DependencyKind {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        DependencyKind::from_u8(d.u8()?).ok_or_else(corrupt)
+    }
+}
+
+macro_rules! id {
+    ($($id:path)*) => {
+        $(
+            impl Encode for $id {
+                fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+                    self.0.encode(e)
+                }
+            }
+
+            impl Decode for $id {
+                fn decode(d: &mut Decoder) -> Result<Self> {
+                    u32::decode(d).map($id)
+                }
+            }
+        )*
+    };
+}
+
+id! {
+    crate::categories::CategoryId
+    crate::crates::CrateId
+    crate::deleted_crates::DeletedCrateId
+    crate::keywords::KeywordId
+    crate::teams::TeamId
+    crate::users::UserId
+    crate::versions::VersionId
+}
+
+// Decoding constructs each row with a struct literal, so adding a field to a
+// row without listing it here is a compile error.
+macro_rules! row {
+    ($($table:ident { $($field:ident,)* })*) => {
+        $(
+            impl Encode for crate::$table::Row {
+                fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+                    $(
+                        self.$field.encode(e)?;
+                    )*
+                    Ok(())
+                }
+            }
+
+            impl Decode for crate::$table::Row {
+                fn decode(d: &mut Decoder) -> Result<Self> {
+                    Ok(crate::$table::Row {
+                        $(
+                            $field: Decode::decode(d)?,
+                        )*
+                    })
+                }
+            }
+        )*
+    };
+}
+
+row! {
+    categories {
+        id,
+        category,
+        slug,
+        description,
+        crates_cnt,
+        created_at,
+        path,
+    }
+    crate_downloads {
+        crate_id,
+        downloads,
+    }
+    crate_owners {
+        crate_id,
+        owner_id,
+        created_at,
+        created_by,
+    }
+    crates {
+        id,
+        name,
+        updated_at,
+        created_at,
+        description,
+        homepage,
+        documentation,
+        readme,
+        repository,
+        max_upload_size,
+        max_features,
+        trustpub_only,
+    }
+    crates_categories {
+        crate_id,
+        category_id,
+    }
+    crates_keywords {
+        crate_id,
+        keyword_id,
+    }
+    default_versions {
+        crate_id,
+        version_id,
+        num_versions,
+    }
+    deleted_crates {
+        id,
+        name,
+        created_at,
+        deleted_at,
+        deleted_by,
+        message,
+        available_at,
+    }
+    dependencies {
+        id,
+        version_id,
+        crate_id,
+        req,
+        optional,
+        default_features,
+        features,
+        target,
+        kind,
+        explicit_name,
+    }
+    keywords {
+        id,
+        keyword,
+        crates_cnt,
+        created_at,
+    }
+    metadata {
+        total_downloads,
+    }
+    reserved_crate_names {
+        name,
+    }
+    teams {
+        id,
+        login,
+        github_id,
+        name,
+        avatar,
+        org_id,
+    }
+    users {
+        id,
+        gh_login,
+        name,
+        gh_avatar,
+        gh_id,
+    }
+    versions {
+        id,
+        crate_id,
+        num,
+        updated_at,
+        created_at,
+        downloads,
+        features,
+        yanked,
+        license,
+        crate_size,
+        published_by,
+        checksum,
+        links,
+        rust_version,
+        has_lib,
+        bin_names,
+        edition,
+        description,
+        homepage,
+        documentation,
+        repository,
+        categories,
+        keywords,
+    }
+    version_downloads {
+        version_id,
+        downloads,
+        date,
+    }
+}
+
+impl Encode for crate::version_downloads::Table {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+        e.varint(self.len() as u64)?;
+        for row in self {
+            row.encode(e)?;
+        }
+        Ok(())
+    }
+}
+
+impl Decode for crate::version_downloads::Table {
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let len = d.len()?;
+        if len > d.bytes.len() {
+            return Err(corrupt());
+        }
+        let mut table = crate::version_downloads::Table::with_capacity(len);
+        for _ in 0..len {
+            table.push(Decode::decode(d)?);
+        }
+        Ok(table)
+    }
+}
+
+macro_rules! dump {
+    ($($table:ident)*) => {
+        impl Encode for DbDump {
+            fn encode<W: Write>(&self, e: &mut Encoder<W>) -> io::Result<()> {
+                $(
+                    self.$table.encode(e)?;
+                )*
+                Ok(())
+            }
+        }
+
+        impl Decode for DbDump {
+            fn decode(d: &mut Decoder) -> Result<Self> {
+                Ok(DbDump {
+                    $(
+                        $table: Decode::decode(d)?,
+                    )*
+                })
+            }
+        }
+    };
+}
+
+dump! {
+    categories
+    crate_downloads
+    crate_owners
+    crates
+    crates_categories
+    crates_keywords
+    default_versions
+    deleted_crates
+    dependencies
+    keywords
+    metadata
+    reserved_crate_names
+    teams
+    users
+    version_downloads
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decode, Decoder, Encode, Encoder, Source};
+    use crate::crate_owners::OwnerId;
+    use crate::crates::CrateId;
+    use crate::dependencies::DependencyKind;
+    use crate::users::UserId;
+    use crate::versions::VersionId;
+    use crate::{Date, DbDump};
+    use chrono::DateTime;
+    use semver::{Version, VersionReq};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_round_trip() {
+        let timestamp = DateTime::from_timestamp(1_700_000_000, 123_456_000).unwrap();
+        let mit = Arc::<str>::from("MIT OR Apache-2.0");
+
+        let mut db = DbDump::default();
+        db.crate_owners.push(crate::crate_owners::Row {
+            crate_id: CrateId(1),
+            owner_id: OwnerId::User(UserId(7)),
+            created_at: timestamp,
+            created_by: None,
+        });
+        for (id, num) in [(1, "1.0.0"), (2, "1.1.0-alpha.1+build")] {
+            db.versions.push(crate::versions::Row {
+                id: VersionId(id),
+                crate_id: CrateId(1),
+                num: Version::parse(num).unwrap(),
+                updated_at: timestamp,
+                created_at: timestamp,
+                downloads: 1000,
+                features: [(Arc::from("default"), vec![Arc::from("std")])]
+                    .into_iter()
+                    .collect(),
+                yanked: false,
+                license: Arc::clone(&mit),
+                crate_size: Some(4096),
+                published_by: Some(UserId(7)),
+                checksum: Some([0xab; 32]),
+                links: None,
+                rust_version: Some(Version::new(1, 60, 0)),
+                has_lib: true,
+                bin_names: vec!["demo".to_owned()],
+                edition: Some(2021),
+                description: Some("demo".to_owned()),
+                homepage: None,
+                documentation: None,
+                repository: None,
+                categories: Vec::new(),
+                keywords: vec![Arc::from("demo")],
+            });
+        }
+        db.dependencies.push(crate::dependencies::Row {
+            id: 1,
+            version_id: VersionId(2),
+            crate_id: CrateId(3),
+            req: VersionReq::parse(">=1.2, <2.0.0-rc.1").unwrap(),
+            optional: true,
+            default_features: false,
+            features: vec![Arc::from("derive")],
+            target: Arc::from("cfg(unix)"),
+            kind: DependencyKind::Dev,
+            explicit_name: Some("renamed".to_owned()),
+        });
+        for (version_id, day, downloads) in [(2, 2, 5), (1, 1, 3), (1, 3, 4)] {
+            db.version_downloads.push(crate::version_downloads::Row {
+                version_id: VersionId(version_id),
+                downloads,
+                date: Date::from_ymd(2024, 6, day),
+            });
+        }
+        db.metadata.total_downloads = 12;
+
+        let mut encoder = Encoder {
+            w: Vec::new(),
+            strings: HashMap::new(),
+        };
+        db.encode(&mut encoder).unwrap();
+        let mut decoder = Decoder {
+            bytes: &encoder.w,
+            strings: Vec::new(),
+        };
+        let loaded = DbDump::decode(&mut decoder).unwrap();
+        assert!(decoder.bytes.is_empty());
+
+        assert_eq!(
+            format!("{:?}", loaded.crate_owners),
+            format!("{:?}", db.crate_owners),
+        );
+        assert_eq!(
+            format!("{:?}", loaded.versions),
+            format!("{:?}", db.versions),
+        );
+        assert_eq!(
+            format!("{:?}", loaded.dependencies),
+            format!("{:?}", db.dependencies),
+        );
+        assert_eq!(
+            format!("{:?}", loaded.version_downloads),
+            format!("{:?}", db.version_downloads),
+        );
+        assert_eq!(loaded.metadata.total_downloads, 12);
+        assert!(Arc::ptr_eq(
+            &loaded.versions[0].license,
+            &loaded.versions[1].license,
+        ));
+    }
+
+    #[test]
+    fn test_source_of_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Source::of(dir.path()).unwrap(), None);
+
+        fs::write(dir.path().join("crates.jsonl"), "{}\n").unwrap();
+        fs::write(dir.path().join("users.jsonl"), "{}\n{}\n").unwrap();
+        fs::write(dir.path().join("README"), "not a table").unwrap();
+        let source = Source::of(dir.path()).unwrap().unwrap();
+        assert_eq!(source.len, 9);
+        assert_eq!(Source::of(dir.path()).unwrap(), Some(source));
+
+        fs::write(dir.path().join("crates.jsonl"), "{}\n{}\n").unwrap();
+        assert_ne!(Source::of(dir.path()).unwrap(), Some(source));
+    }
+}
@@ -0,0 +1,410 @@
+// The columns of each table in the Parquet export and in polars DataFrames,
+// and the values of a row in those columns. Both are the columns of the csv
+// file, in the same order, except that versions has `major`, `minor` and
+// `patch` in place of `num_no_build`.
+
+use crate::versions::FeaturesMap;
+use crate::Date;
+use chrono::{DateTime, Datelike, Utc};
+
+/// The tables of a dump, in the order they are listed in [`DbDump`].
+///
+/// [`DbDump`]: crate::DbDump
+#[cfg_attr(not(feature = "parquet"), allow(dead_code))]
+pub const TABLES: [&str; 16] = [
+    "categories",
+    "crate_downloads",
+    "crate_owners",
+    "crates",
+    "crates_categories",
+    "crates_keywords",
+    "default_versions",
+    "deleted_crates",
+    "dependencies",
+    "keywords",
+    "metadata",
+    "reserved_crate_names",
+    "teams",
+    "users",
+    "version_downloads",
+    "versions",
+];
+
+// Days from 0001-01-01 to 1970-01-01.
+const UNIX_EPOCH_FROM_CE: i32 = 719_163;
+
+#[derive(Copy, Clone)]
+pub(crate) enum Kind {
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    Int32,
+    Bool,
+    Str,
+    Checksum,
+    DateTime,
+    Date,
+    List,
+    Features,
+}
+
+// Name, kind and whether the column is nullable, for each column of the
+// table, or empty if there is no table by that name.
+pub(crate) fn columns(table: &str) -> &'static [(&'static str, Kind, bool)] {
+    match table {
+        "categories" => &[
+            ("id", Kind::UInt32, false),
+            ("category", Kind::Str, false),
+            ("slug", Kind::Str, false),
+            ("description", Kind::Str, false),
+            ("crates_cnt", Kind::UInt32, false),
+            ("created_at", Kind::DateTime, false),
+            ("path", Kind::Str, false),
+        ],
+        "crate_downloads" => &[
+            ("crate_id", Kind::UInt32, false),
+            ("downloads", Kind::UInt64, false),
+        ],
+        "crate_owners" => &[
+            ("crate_id", Kind::UInt32, false),
+            ("created_at", Kind::DateTime, false),
+            ("created_by", Kind::UInt32, true),
+            ("owner_id", Kind::UInt32, false),
+            ("owner_kind", Kind::UInt8, false),
+        ],
+        "crates" => &[
+            ("id", Kind::UInt32, false),
+            ("name", Kind::Str, false),
+            ("updated_at", Kind::DateTime, false),
+            ("created_at", Kind::DateTime, false),
+            ("description", Kind::Str, false),
+            ("homepage", Kind::Str, true),
+            ("documentation", Kind::Str, true),
+            ("readme", Kind::Str, true),
+            ("repository", Kind::Str, true),
+            ("max_upload_size", Kind::UInt64, true),
+            ("max_features", Kind::UInt16, true),
+            ("trustpub_only", Kind::Bool, false),
+        ],
+        "crates_categories" => &[
+            ("crate_id", Kind::UInt32, false),
+            ("category_id", Kind::UInt32, false),
+        ],
+        "crates_keywords" => &[
+            ("crate_id", Kind::UInt32, false),
+            ("keyword_id", Kind::UInt32, false),
+        ],
+        "default_versions" => &[
+            ("crate_id", Kind::UInt32, false),
+            ("version_id", Kind::UInt32, false),
+            ("num_versions", Kind::UInt32, true),
+        ],
+        "deleted_crates" => &[
+            ("id", Kind::UInt32, false),
+            ("name", Kind::Str, false),
+            ("created_at", Kind::DateTime, false),
+            ("deleted_at", Kind::DateTime, false),
+            ("deleted_by", Kind::UInt32, true),
+            ("message", Kind::Str, false),
+            ("available_at", Kind::DateTime, false),
+        ],
+        "dependencies" => &[
+            ("id", Kind::UInt32, false),
+            ("version_id", Kind::UInt32, false),
+            ("crate_id", Kind::UInt32, false),
+            ("req", Kind::Str, false),
+            ("optional", Kind::Bool, false),
+            ("default_features", Kind::Bool, false),
+            ("features", Kind::List, false),
+            ("target", Kind::Str, false),
+            ("kind", Kind::UInt8, false),
+            ("explicit_name", Kind::Str, true),
+        ],
+        "keywords" => &[
+            ("id", Kind::UInt32, false),
+            ("keyword", Kind::Str, false),
+            ("crates_cnt", Kind::UInt32, false),
+            ("created_at", Kind::DateTime, false),
+        ],
+        "metadata" => &[("total_downloads", Kind::UInt64, false)],
+        "reserved_crate_names" => &[("name", Kind::Str, false)],
+        "teams" => &[
+            ("id", Kind::UInt32, false),
+            ("login", Kind::Str, false),
+            ("github_id", Kind::UInt32, false),
+            ("name", Kind::Str, false),
+            ("avatar", Kind::Str, false),
+            ("org_id", Kind::UInt32, true),
+        ],
+        "users" => &[
+            ("id", Kind::UInt32, false),
+            ("gh_login", Kind::Str, false),
+            ("name", Kind::Str, true),
+            ("gh_avatar", Kind::Str, false),
+            ("gh_id", Kind::Int32, false),
+        ],
+        "version_downloads" => &[
+            ("version_id", Kind::UInt32, false),
+            ("downloads", Kind::UInt64, false),
+            ("date", Kind::Date, false),
+        ],
+        "versions" => &[
+            ("id", Kind::UInt32, false),
+            ("crate_id", Kind::UInt32, false),
+            ("num", Kind::Str, false),
+            ("major", Kind::UInt64, false),
+            ("minor", Kind::UInt64, false),
+            ("patch", Kind::UInt64, false),
+            ("updated_at", Kind::DateTime, false),
+            ("created_at", Kind::DateTime, false),
+            ("downloads", Kind::UInt64, false),
+            ("features", Kind::Features, false),
+            ("yanked", Kind::Bool, false),
+            ("license", Kind::Str, false),
+            ("crate_size", Kind::UInt64, true),
+            ("published_by", Kind::UInt32, true),
+            ("checksum", Kind::Checksum, true),
+            ("links", Kind::Str, true),
+            ("rust_version", Kind::Str, true),
+            ("has_lib", Kind::Bool, false),
+            ("bin_names", Kind::List, false),
+            ("edition", Kind::UInt16, true),
+            ("description", Kind::Str, true),
+            ("homepage", Kind::Str, true),
+            ("documentation", Kind::Str, true),
+            ("repository", Kind::Str, true),
+            ("categories", Kind::List, false),
+            ("keywords", Kind::List, false),
+        ],
+        _ => &[],
+    }
+}
+
+pub(crate) fn days_from_epoch(date: Date<Utc>) -> i32 {
+    date.num_days_from_ce() - UNIX_EPOCH_FROM_CE
+}
+
+// Builders of the columns of one table, filled in left to right by appending
+// a value to each in the order of `columns`.
+pub(crate) trait Columns {
+    fn u8(&mut self, value: u8);
+    fn u16(&mut self, value: impl Into<Option<u16>>);
+    fn u32(&mut self, value: impl Into<Option<u32>>);
+    fn u64(&mut self, value: impl Into<Option<u64>>);
+    fn i32(&mut self, value: i32);
+    fn bool(&mut self, value: bool);
+    fn opt_str(&mut self, value: Option<&str>);
+    fn datetime(&mut self, value: DateTime<Utc>);
+    fn date(&mut self, value: Date<Utc>);
+    fn checksum(&mut self, value: Option<&[u8; 32]>);
+    fn strings(&mut self, values: &[impl AsRef<str>]);
+    fn features(&mut self, features: &FeaturesMap);
+
+    fn str(&mut self, value: &str) {
+        self.opt_str(Some(value));
+    }
+}
+
+pub(crate) trait Append {
+    fn append(&self, c: &mut impl Columns);
+}
+
+impl Append for crate::categories::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u32(self.id.0);
+        c.str(&self.category);
+        c.str(&self.slug);
+        c.str(&self.description);
+        c.u32(self.crates_cnt);
+        c.datetime(self.created_at);
+        c.str(&self.path);
+    }
+}
+
+impl Append for crate::crate_downloads::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u32(self.crate_id.0);
+        c.u64(self.downloads);
+    }
+}
+
+impl Append for crate::crate_owners::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u32(self.crate_id.0);
+        c.datetime(self.created_at);
+        c.u32(self.created_by.map(|user_id| user_id.0));
+        c.u32(self.owner_id.id());
+        c.u8(self.owner_id.kind());
+    }
+}
+
+impl Append for crate::crates::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u32(self.id.0);
+        c.str(&self.name);
+        c.datetime(self.updated_at);
+        c.datetime(self.created_at);
+        c.str(&self.description);
+        c.opt_str(self.homepage.as_deref());
+        c.opt_str(self.documentation.as_deref());
+        c.opt_str(self.readme.as_deref());
+        c.opt_str(self.repository.as_deref());
+        c.u64(self.max_upload_size);
+        c.u16(self.max_features);
+        c.bool(self.trustpub_only);
+    }
+}
+
+impl Append for crate::crates_categories::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u32(self.crate_id.0);
+        c.u32(self.category_id.0);
+    }
+}
+
+impl Append for crate::crates_keywords::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u32(self.crate_id.0);
+        c.u32(self.keyword_id.0);
+    }
+}
+
+impl Append for crate::default_versions::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u32(self.crate_id.0);
+        c.u32(self.version_id.0);
+        c.u32(self.num_versions);
+    }
+}
+
+impl Append for crate::deleted_crates::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u32(self.id.0);
+        c.str(&self.name);
+        c.datetime(self.created_at);
+        c.datetime(self.deleted_at);
+        c.u32(self.deleted_by.map(|user_id| user_id.0));
+        c.str(&self.message);
+        c.datetime(self.available_at);
+    }
+}
+
+impl Append for crate::dependencies::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u32(self.id);
+        c.u32(self.version_id.0);
+        c.u32(self.crate_id.0);
+        c.str(&self.req.to_string());
+        c.bool(self.optional);
+        c.bool(self.default_features);
+        c.strings(&self.features);
+        c.str(&self.target);
+        c.u8(self.kind.as_u8());
+        c.opt_str(self.explicit_name.as_deref());
+    }
+}
+
+impl Append for crate::keywords::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u32(self.id.0);
+        c.str(&self.keyword);
+        c.u32(self.crates_cnt);
+        c.datetime(self.created_at);
+    }
+}
+
+impl Append for crate::metadata::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u64(self.total_downloads);
+    }
+}
+
+impl Append for crate::reserved_crate_names::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.str(&self.name);
+    }
+}
+
+impl Append for crate::teams::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u32(self.id.0);
+        c.str(&self.login);
+        c.u32(self.github_id);
+        c.str(&self.name);
+        c.str(&self.avatar);
+        c.u32(self.org_id);
+    }
+}
+
+impl Append for crate::users::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u32(self.id.0);
+        c.str(&self.gh_login);
+        c.opt_str(self.name.as_deref());
+        c.str(&self.gh_avatar);
+        c.i32(self.gh_id);
+    }
+}
+
+impl Append for crate::version_downloads::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u32(self.version_id.0);
+        c.u64(self.downloads);
+        c.date(self.date);
+    }
+}
+
+impl Append for crate::versions::Row {
+    fn append(&self, c: &mut impl Columns) {
+        c.u32(self.id.0);
+        c.u32(self.crate_id.0);
+        c.str(&self.num.to_string());
+        c.u64(self.num.major);
+        c.u64(self.num.minor);
+        c.u64(self.num.patch);
+        c.datetime(self.updated_at);
+        c.datetime(self.created_at);
+        c.u64(self.downloads);
+        c.features(&self.features);
+        c.bool(self.yanked);
+        c.str(&self.license);
+        c.u64(self.crate_size);
+        c.u32(self.published_by.map(|user_id| user_id.0));
+        c.checksum(self.checksum.as_ref());
+        c.opt_str(self.links.as_deref());
+        c.opt_str(
+            self.rust_version
+                .as_ref()
+                .map(ToString::to_string)
+                .as_deref(),
+        );
+        c.bool(self.has_lib);
+        c.strings(&self.bin_names);
+        c.u16(self.edition);
+        c.opt_str(self.description.as_deref());
+        c.opt_str(self.homepage.as_deref());
+        c.opt_str(self.documentation.as_deref());
+        c.opt_str(self.repository.as_deref());
+        c.strings(&self.categories);
+        c.strings(&self.keywords);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TABLES;
+
+    #[test]
+    fn test_csv_columns() {
+        for table in TABLES {
+            let names: Vec<&str> = super::columns(table).iter().map(|c| c.0).collect();
+            let mut header = crate::write::header(table).to_vec();
+            if table == "versions" {
+                let i = header.iter().position(|&c| c == "num_no_build").unwrap();
+                header.splice(i..=i, ["major", "minor", "patch"]);
+            }
+            assert_eq!(names, header, "{}", table);
+        }
+    }
+}
@@ -1,10 +1,10 @@
 //! <b style="font-variant:small-caps">crate_downloads.csv</b>
 
 use crate::crates::CrateId;
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
 /// One row of **crate_downloads.csv**.
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 #[non_exhaustive]
 pub struct Row {
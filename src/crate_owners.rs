@@ -1,15 +1,16 @@
 //! <b style="font-variant:small-caps">crate_owners.csv</b>
 
 use crate::crates::CrateId;
-use crate::error::{err, Result};
-use crate::load::FromRecord;
 use crate::teams::TeamId;
 use crate::users::UserId;
 use chrono::{DateTime, Utc};
-use csv::StringRecord;
-use serde_derive::Deserialize;
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use serde_derive::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+/// Serializes as `{"user": id}` or `{"team": id}`.
+#[derive(Serialize, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
 pub enum OwnerId {
     /// owner_kind=0
     User(UserId),
@@ -27,43 +28,110 @@ pub struct Row {
     pub created_by: Option<UserId>,
 }
 
-impl FromRecord for Row {
-    fn from_record(record: &StringRecord, headers: &StringRecord) -> Result<Self> {
-        de(record, headers)
+impl<'de> Deserialize<'de> for Row {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Row {
+            crate_id: CrateId,
+            #[serde(deserialize_with = "crate::datetime::de")]
+            created_at: DateTime<Utc>,
+            created_by: Option<UserId>,
+            owner_id: u32,
+            owner_kind: u8,
+        }
+
+        let Row {
+            crate_id,
+            created_at,
+            created_by,
+            owner_id,
+            owner_kind,
+        } = Row::deserialize(deserializer)?;
+
+        let Some(owner_id) = OwnerId::new(owner_id, owner_kind) else {
+            return Err(serde::de::Error::custom(format_args!(
+                "unrecognized crate_owners.csv owner_kind: {}",
+                owner_kind,
+            )));
+        };
+
+        Ok(Self {
+            crate_id,
+            owner_id,
+            created_at,
+            created_by,
+        })
     }
 }
 
-fn de(record: &StringRecord, headers: &StringRecord) -> Result<Row> {
-    #[derive(Deserialize)]
-    #[serde(deny_unknown_fields)]
-    struct Record {
-        crate_id: CrateId,
-        #[serde(deserialize_with = "crate::datetime::de")]
-        created_at: DateTime<Utc>,
-        created_by: Option<UserId>,
-        owner_id: u32,
-        owner_kind: u8,
+impl Serialize for Row {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct Row {
+            crate_id: CrateId,
+            #[serde(serialize_with = "crate::datetime::ser")]
+            created_at: DateTime<Utc>,
+            created_by: Option<UserId>,
+            owner_id: u32,
+            owner_kind: u8,
+        }
+
+        Row {
+            crate_id: self.crate_id,
+            created_at: self.created_at,
+            created_by: self.created_by,
+            owner_id: self.owner_id.id(),
+            owner_kind: self.owner_id.kind(),
+        }
+        .serialize(serializer)
     }
+}
 
-    let record: Record = record.deserialize(Some(headers)).map_err(err)?;
+impl OwnerId {
+    /// The owner from the owner_id and owner_kind columns of
+    /// crate_owners.csv, or None if the kind is neither 0 nor 1.
+    pub fn new(id: u32, kind: u8) -> Option<Self> {
+        match kind {
+            0 => Some(OwnerId::User(UserId(id))),
+            1 => Some(OwnerId::Team(TeamId(id))),
+            _ => None,
+        }
+    }
 
-    let owner_id = match record.owner_kind {
-        0 => OwnerId::User(UserId(record.owner_id)),
-        1 => OwnerId::Team(TeamId(record.owner_id)),
-        other => {
-            return Err(err(format_args!(
-                "unrecognized crate_owners.csv owner_kind: {}",
-                other,
-            )))
+    /// The owner_id column: the id of the user or team.
+    pub fn id(self) -> u32 {
+        match self {
+            OwnerId::User(user_id) => user_id.0,
+            OwnerId::Team(team_id) => team_id.0,
         }
-    };
-
-    Ok(Row {
-        crate_id: record.crate_id,
-        created_at: record.created_at,
-        created_by: record.created_by,
-        owner_id,
-    })
+    }
+
+    /// The owner_kind column: 0 for a user, 1 for a team.
+    pub fn kind(self) -> u8 {
+        match self {
+            OwnerId::User(_) => 0,
+            OwnerId::Team(_) => 1,
+        }
+    }
+}
+
+impl From<UserId> for OwnerId {
+    fn from(user_id: UserId) -> Self {
+        OwnerId::User(user_id)
+    }
+}
+
+impl From<TeamId> for OwnerId {
+    fn from(team_id: TeamId) -> Self {
+        OwnerId::Team(team_id)
+    }
 }
 
 impl PartialEq<UserId> for OwnerId {
@@ -95,3 +163,41 @@ impl PartialEq<OwnerId> for TeamId {
         other == self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{OwnerId, Row};
+    use crate::crates::CrateId;
+    use crate::teams::TeamId;
+    use crate::users::UserId;
+    use chrono::DateTime;
+
+    #[test]
+    fn test_round_trip() {
+        let row = Row {
+            crate_id: CrateId(1),
+            owner_id: OwnerId::Team(TeamId(2)),
+            created_at: DateTime::from_timestamp_micros(1_700_000_000_000_001).unwrap(),
+            created_by: Some(UserId(3)),
+        };
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.serialize(&row).unwrap();
+        let csv = writer.into_inner().unwrap();
+        assert_eq!(
+            String::from_utf8(csv.clone()).unwrap(),
+            "crate_id,created_at,created_by,owner_id,owner_kind\n\
+             1,2023-11-14 22:13:20.000001,3,2,1\n",
+        );
+        let mut reader = csv::Reader::from_reader(&*csv);
+        let rows: Vec<Row> = reader.deserialize().collect::<Result<_, _>>().unwrap();
+        assert_eq!(format!("{:?}", rows), format!("{:?}", [&row]));
+
+        let json = serde_json::to_string(&row).unwrap();
+        let from_json: Row = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{:?}", from_json), format!("{:?}", row));
+
+        let owner_id = serde_json::to_string(&OwnerId::User(UserId(4))).unwrap();
+        assert_eq!(owner_id, r#"{"user":4}"#);
+    }
+}
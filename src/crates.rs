@@ -13,16 +13,22 @@ use std::hash::{Hash, Hasher};
 pub struct CrateId(pub u32);
 
 /// One row of **crates.csv**.
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 #[non_exhaustive]
 pub struct Row {
     /// PRIMARY KEY
     pub id: CrateId,
     pub name: String,
-    #[serde(deserialize_with = "crate::datetime::de")]
+    #[serde(
+        serialize_with = "crate::datetime::ser",
+        deserialize_with = "crate::datetime::de"
+    )]
     pub updated_at: DateTime<Utc>,
-    #[serde(deserialize_with = "crate::datetime::de")]
+    #[serde(
+        serialize_with = "crate::datetime::ser",
+        deserialize_with = "crate::datetime::de"
+    )]
     pub created_at: DateTime<Utc>,
     pub description: String,
     pub homepage: Option<String>,
@@ -31,7 +37,11 @@ pub struct Row {
     pub repository: Option<String>,
     pub max_upload_size: Option<u64>,
     pub max_features: Option<u16>,
-    #[serde(default, deserialize_with = "crate::bool::de")]
+    #[serde(
+        default,
+        serialize_with = "crate::bool::ser",
+        deserialize_with = "crate::bool::de"
+    )]
     pub trustpub_only: bool,
 }
 
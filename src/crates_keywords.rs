@@ -2,10 +2,10 @@
 
 use crate::crates::CrateId;
 use crate::keywords::KeywordId;
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
 /// One row of **crates_keywords.csv**.
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 #[non_exhaustive]
 pub struct Row {
@@ -1,5 +1,6 @@
 use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
 use serde::de::{Deserializer, Unexpected, Visitor};
+use serde::ser::Serializer;
 use std::fmt;
 
 // The timestamps in the db dump CSV do not mention a time zone, but in reality
@@ -98,11 +99,20 @@ where
     deserializer.deserialize_str(CratesioDateTimeVisitor)
 }
 
+// Microsecond precision, which is what the db dump contains.
+pub(crate) fn ser<S>(datetime: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_str(&datetime.format("%Y-%m-%d %H:%M:%S%.6f"))
+}
+
 #[cfg(test)]
 mod tests {
     use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
     use serde::de::value::Error;
     use serde::de::IntoDeserializer;
+    use serde_derive::Serialize;
 
     #[test]
     fn test_de() {
@@ -134,4 +144,22 @@ mod tests {
             )),
         );
     }
+
+    #[test]
+    fn test_ser() {
+        #[derive(Serialize)]
+        struct DateTime(#[serde(serialize_with = "super::ser")] chrono::DateTime<Utc>);
+
+        let datetime = Utc.from_utc_datetime(&NaiveDateTime::new(
+            NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+            NaiveTime::from_hms_micro_opt(12, 11, 10, 990000).unwrap(),
+        ));
+        let json = serde_json::to_string(&DateTime(datetime)).unwrap();
+        assert_eq!(json, "\"2020-01-01 12:11:10.990000\"");
+        let deserializer = IntoDeserializer::<Error>::into_deserializer;
+        assert_eq!(
+            super::de(deserializer(&json[1..json.len() - 1])).unwrap(),
+            datetime,
+        );
+    }
 }
@@ -11,19 +11,28 @@ use serde_derive::{Deserialize, Serialize};
 pub struct DeletedCrateId(pub u32);
 
 /// One row of **deleted_crates.csv**.
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 #[non_exhaustive]
 pub struct Row {
     /// PRIMARY KEY
     pub id: DeletedCrateId,
     pub name: String,
-    #[serde(deserialize_with = "crate::datetime::de")]
+    #[serde(
+        serialize_with = "crate::datetime::ser",
+        deserialize_with = "crate::datetime::de"
+    )]
     pub created_at: DateTime<Utc>,
-    #[serde(deserialize_with = "crate::datetime::de")]
+    #[serde(
+        serialize_with = "crate::datetime::ser",
+        deserialize_with = "crate::datetime::de"
+    )]
     pub deleted_at: DateTime<Utc>,
     pub deleted_by: Option<UserId>,
     pub message: String,
-    #[serde(deserialize_with = "crate::datetime::de")]
+    #[serde(
+        serialize_with = "crate::datetime::ser",
+        deserialize_with = "crate::datetime::de"
+    )]
     pub available_at: DateTime<Utc>,
 }
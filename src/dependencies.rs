@@ -1,14 +1,17 @@
 //! <b style="font-variant:small-caps">dependencies.csv</b>
 
 use crate::crates::CrateId;
+use crate::intern::{Intern, Interner};
 use crate::versions::VersionId;
 use semver::VersionReq;
 use serde::de::{Deserialize, Deserializer, Unexpected, Visitor};
-use serde_derive::Deserialize;
+use serde::ser::{Serialize, Serializer};
+use serde_derive::{Deserialize, Serialize};
 use std::fmt;
+use std::sync::Arc;
 
 /// One row of **dependencies.csv**.
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 #[non_exhaustive]
 pub struct Row {
@@ -18,18 +21,31 @@ pub struct Row {
     pub crate_id: CrateId,
     #[serde(deserialize_with = "version_req")]
     pub req: VersionReq,
-    #[serde(deserialize_with = "crate::bool::de")]
+    #[serde(
+        serialize_with = "crate::bool::ser",
+        deserialize_with = "crate::bool::de"
+    )]
     pub optional: bool,
-    #[serde(deserialize_with = "crate::bool::de")]
+    #[serde(
+        serialize_with = "crate::bool::ser",
+        deserialize_with = "crate::bool::de"
+    )]
     pub default_features: bool,
-    #[serde(deserialize_with = "features_set")]
-    pub features: Vec<String>,
-    pub target: String,
+    #[serde(serialize_with = "crate::set::ser", deserialize_with = "features_set")]
+    pub features: Vec<Arc<str>>,
+    pub target: Arc<str>,
     pub kind: DependencyKind,
     #[serde(default)]
     pub explicit_name: Option<String>,
 }
 
+impl Intern for Row {
+    fn intern(&mut self, interner: &mut Interner) {
+        interner.intern_all(&mut self.features);
+        interner.intern(&mut self.target);
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum DependencyKind {
     /// kind=0
@@ -40,6 +56,38 @@ pub enum DependencyKind {
     Dev,
 }
 
+impl DependencyKind {
+    /// The kind column of dependencies.csv, or None if it is not 0, 1 or 2.
+    pub fn from_u8(kind: u8) -> Option<Self> {
+        match kind {
+            0 => Some(DependencyKind::Normal),
+            1 => Some(DependencyKind::Build),
+            2 => Some(DependencyKind::Dev),
+            _ => None,
+        }
+    }
+
+    /// The kind column of dependencies.csv: 0 for normal, 1 for build, 2
+    /// for dev.
+    pub fn as_u8(self) -> u8 {
+        match self {
+            DependencyKind::Normal => 0,
+            DependencyKind::Build => 1,
+            DependencyKind::Dev => 2,
+        }
+    }
+
+    /// "normal", "build" or "dev", as the kind is spelled in the crates.io
+    /// API and in the registry index.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DependencyKind::Normal => "normal",
+            DependencyKind::Build => "build",
+            DependencyKind::Dev => "dev",
+        }
+    }
+}
+
 struct DependencyKindVisitor;
 
 impl<'de> Visitor<'de> for DependencyKindVisitor {
@@ -49,19 +97,14 @@ impl<'de> Visitor<'de> for DependencyKindVisitor {
         formatter.write_str("dependency kind (0, 1, 2)")
     }
 
-    fn visit_u8<E>(self, kind: u8) -> Result<Self::Value, E>
+    fn visit_u64<E>(self, kind: u64) -> Result<Self::Value, E>
     where
         E: serde::de::Error,
     {
-        match kind {
-            0 => Ok(DependencyKind::Normal),
-            1 => Ok(DependencyKind::Build),
-            2 => Ok(DependencyKind::Dev),
-            _ => Err(serde::de::Error::invalid_value(
-                Unexpected::Unsigned(kind as u64),
-                &self,
-            )),
-        }
+        u8::try_from(kind)
+            .ok()
+            .and_then(DependencyKind::from_u8)
+            .ok_or_else(|| serde::de::Error::invalid_value(Unexpected::Unsigned(kind), &self))
     }
 }
 
@@ -74,6 +117,15 @@ impl<'de> Deserialize<'de> for DependencyKind {
     }
 }
 
+impl Serialize for DependencyKind {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u8(self.as_u8())
+    }
+}
+
 fn compat(string: &str) -> Option<VersionReq> {
     let deprecated = match string {
         "^0-.11.0" => "^0.11.0",
@@ -121,9 +173,45 @@ where
     deserializer.deserialize_str(VersionReqVisitor)
 }
 
-fn features_set<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+fn features_set<'de, D>(deserializer: D) -> Result<Vec<Arc<str>>, D::Error>
 where
     D: Deserializer<'de>,
 {
     crate::set::de(deserializer, "features set")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{DependencyKind, Row};
+    use crate::crates::CrateId;
+    use crate::versions::VersionId;
+    use semver::VersionReq;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_round_trip() {
+        let row = Row {
+            id: 1,
+            version_id: VersionId(2),
+            crate_id: CrateId(3),
+            req: VersionReq::parse(">=1.2, <2.0.0-rc.1").unwrap(),
+            optional: false,
+            default_features: true,
+            features: vec![Arc::from("derive"), Arc::from("std")],
+            target: Arc::from("cfg(unix)"),
+            kind: DependencyKind::Build,
+            explicit_name: None,
+        };
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.serialize(&row).unwrap();
+        let csv = writer.into_inner().unwrap();
+        let mut reader = csv::Reader::from_reader(&*csv);
+        let rows: Vec<Row> = reader.deserialize().collect::<Result<_, _>>().unwrap();
+        assert_eq!(format!("{:?}", rows), format!("{:?}", [&row]));
+
+        let json = serde_json::to_string(&row).unwrap();
+        let from_json: Row = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{:?}", from_json), format!("{:?}", row));
+    }
+}
@@ -21,6 +21,12 @@ pub(crate) enum ErrorKind {
     Csv(csv::Error),
     Io(io::Error),
     Json(serde_json::Error),
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+    #[cfg(feature = "parquet")]
+    Parquet(parquet::errors::ParquetError),
+    #[cfg(feature = "polars")]
+    Polars(polars::error::PolarsError),
 }
 
 impl std::error::Error for Error {
@@ -30,6 +36,12 @@ impl std::error::Error for Error {
             ErrorKind::Io(e) => e.source(),
             ErrorKind::Csv(e) => e.source(),
             ErrorKind::Json(e) => e.source(),
+            #[cfg(feature = "sqlite")]
+            ErrorKind::Sqlite(e) => e.source(),
+            #[cfg(feature = "parquet")]
+            ErrorKind::Parquet(e) => e.source(),
+            #[cfg(feature = "polars")]
+            ErrorKind::Polars(e) => e.source(),
         }
     }
 }
@@ -44,6 +56,12 @@ impl Display for Error {
             ErrorKind::Io(e) => write!(f, "{}", e),
             ErrorKind::Csv(e) => write!(f, "{}", e),
             ErrorKind::Json(e) => write!(f, "{}", e),
+            #[cfg(feature = "sqlite")]
+            ErrorKind::Sqlite(e) => write!(f, "{}", e),
+            #[cfg(feature = "parquet")]
+            ErrorKind::Parquet(e) => write!(f, "{}", e),
+            #[cfg(feature = "polars")]
+            ErrorKind::Polars(e) => write!(f, "{}", e),
         }
     }
 }
@@ -91,3 +109,24 @@ impl From<serde_json::Error> for ErrorKind {
         ErrorKind::Json(e)
     }
 }
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        err(ErrorKind::Sqlite(e))
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for Error {
+    fn from(e: parquet::errors::ParquetError) -> Self {
+        err(ErrorKind::Parquet(e))
+    }
+}
+
+#[cfg(feature = "polars")]
+impl From<polars::error::PolarsError> for Error {
+    fn from(e: polars::error::PolarsError) -> Self {
+        err(ErrorKind::Polars(e))
+    }
+}
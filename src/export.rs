@@ -0,0 +1,60 @@
+use crate::error::Result;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+// The files of an export, written into a temporary directory inside the
+// destination and moved into place only once all of them are complete. If
+// the export fails, dropping the Staging removes whatever was written, so
+// the destination is left as it was and the export can be rerun.
+pub(crate) struct Staging {
+    dir: PathBuf,
+    tempdir: TempDir,
+    files: Vec<String>,
+}
+
+impl Staging {
+    // Creates the destination directory if it does not exist.
+    pub(crate) fn new(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let tempdir = tempfile::Builder::new()
+            .prefix(".export-")
+            .tempdir_in(dir)?;
+        Ok(Staging {
+            dir: dir.to_owned(),
+            tempdir,
+            files: Vec::new(),
+        })
+    }
+
+    // Fails if a file by this name already exists in the destination.
+    pub(crate) fn create(&mut self, file_name: String) -> Result<File> {
+        self.check(&file_name)?;
+        let file = File::create_new(self.tempdir.path().join(&file_name))?;
+        self.files.push(file_name);
+        Ok(file)
+    }
+
+    pub(crate) fn persist(self) -> Result<()> {
+        for file_name in &self.files {
+            self.check(file_name)?;
+        }
+        for file_name in &self.files {
+            fs::rename(
+                self.tempdir.path().join(file_name),
+                self.dir.join(file_name),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn check(&self, file_name: &str) -> Result<()> {
+        let path = self.dir.join(file_name);
+        if path.try_exists()? {
+            let message = format!("{} already exists", path.display());
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, message).into());
+        }
+        Ok(())
+    }
+}
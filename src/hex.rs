@@ -0,0 +1,13 @@
+use std::fmt::{self, Display};
+
+// Lowercase hex, as checksums are written in the csv and the registry index.
+pub(crate) struct Hex<'a>(pub &'a [u8]);
+
+impl Display for Hex<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0 {
+            write!(formatter, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
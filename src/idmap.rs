@@ -0,0 +1,775 @@
+//! Maps and sets keyed by the integer ids of the crates.io tables.
+//!
+//! Crates.io assigns ids sequentially, so the ids present in any one table are
+//! mostly dense. [`IdMap`] and [`IdSet`] exploit this by storing entries in a
+//! `Vec` indexed directly by the id's underlying `u32`, which is both faster and
+//! far smaller than a `BTreeMap` or `HashMap` over millions of versions.
+//!
+//! ```no_run
+//! use db_dump::crates::CrateId;
+//! use db_dump::versions::VersionId;
+//! use db_dump::IdMap;
+//!
+//! fn main() -> db_dump::Result<()> {
+//!     let mut versions = IdMap::<VersionId, CrateId>::new();
+//!     let mut version_downloads = Vec::new();
+//!     db_dump::Loader::new()
+//!         .versions(|row| {
+//!             versions.insert(row.id, row.crate_id);
+//!         })
+//!         .version_downloads(|row| version_downloads.push(row))
+//!         .load("./db-dump.tar.gz")?;
+//!
+//!     let mut downloads = IdMap::<CrateId, u64>::new();
+//!     for row in version_downloads {
+//!         let crate_id = versions[row.version_id];
+//!         *downloads.entry(crate_id).or_default() += row.downloads;
+//!     }
+//!
+//!     for (crate_id, downloads) in &downloads {
+//!         println!("{},{}", crate_id.0, downloads);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::fmt::{self, Debug};
+use std::iter::{Enumerate, FusedIterator};
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Index, IndexMut};
+use std::slice;
+use std::vec;
+
+/// An id type usable as the key of [`IdMap`] and [`IdSet`].
+///
+/// This trait is sealed and implemented for the primary key types of the
+/// crates.io tables.
+pub trait Id: Copy + private::Sealed {
+    #[doc(hidden)]
+    fn from_raw(raw: u32) -> Self;
+    #[doc(hidden)]
+    fn raw(self) -> u32;
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_id {
+    ($($id:ty),*) => {
+        $(
+            impl private::Sealed for $id {}
+
+            impl Id for $id {
+                #[inline]
+                fn from_raw(raw: u32) -> Self {
+                    Self(raw)
+                }
+
+                #[inline]
+                fn raw(self) -> u32 {
+                    self.0
+                }
+            }
+        )*
+    };
+}
+
+impl_id!(
+    crate::categories::CategoryId,
+    crate::crates::CrateId,
+    crate::deleted_crates::DeletedCrateId,
+    crate::keywords::KeywordId,
+    crate::teams::TeamId,
+    crate::users::UserId,
+    crate::versions::VersionId
+);
+
+/// Map from id to value, backed by a `Vec` indexed by the raw id.
+///
+/// Iteration is in order of increasing id.
+pub struct IdMap<K, V> {
+    slots: Vec<Option<V>>,
+    len: usize,
+    marker: PhantomData<fn(K) -> K>,
+}
+
+/// Set of ids, backed by a bitset indexed by the raw id.
+///
+/// Iteration is in order of increasing id.
+pub struct IdSet<K> {
+    words: Vec<u64>,
+    len: usize,
+    marker: PhantomData<fn(K) -> K>,
+}
+
+impl<K, V> IdMap<K, V>
+where
+    K: Id,
+{
+    pub const fn new() -> Self {
+        IdMap {
+            slots: Vec::new(),
+            len: 0,
+            marker: PhantomData,
+        }
+    }
+
+    /// Preallocate room for ids up to but not including `max_id`.
+    pub fn with_capacity(max_id: u32) -> Self {
+        let mut map = IdMap::new();
+        map.slots.resize_with(max_id as usize, || None);
+        map
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.len = 0;
+    }
+
+    pub fn contains_key(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get(&self, key: K) -> Option<&V> {
+        self.slots.get(key.raw() as usize)?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, key: K) -> Option<&mut V> {
+        self.slots.get_mut(key.raw() as usize)?.as_mut()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let previous = self.slot(key).replace(value);
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    pub fn remove(&mut self, key: K) -> Option<V> {
+        let removed = self.slots.get_mut(key.raw() as usize)?.take();
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        self.slot(key);
+        let slot = &mut self.slots[key.raw() as usize];
+        if slot.is_some() {
+            Entry::Occupied(OccupiedEntry {
+                key,
+                slot,
+                len: &mut self.len,
+            })
+        } else {
+            Entry::Vacant(VacantEntry {
+                key,
+                slot,
+                len: &mut self.len,
+            })
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            slots: self.slots.iter().enumerate(),
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            slots: self.slots.iter_mut().enumerate(),
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = K> + '_ {
+        self.iter().map(|(key, _value)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.iter().map(|(_key, value)| value)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> + '_ {
+        self.iter_mut().map(|(_key, value)| value)
+    }
+
+    fn slot(&mut self, key: K) -> &mut Option<V> {
+        let index = key.raw() as usize;
+        if index >= self.slots.len() {
+            self.slots.resize_with(index + 1, || None);
+        }
+        &mut self.slots[index]
+    }
+}
+
+/// A view into a single entry of an [`IdMap`], which may be vacant or
+/// occupied.
+pub enum Entry<'a, K, V> {
+    Vacant(VacantEntry<'a, K, V>),
+    Occupied(OccupiedEntry<'a, K, V>),
+}
+
+pub struct VacantEntry<'a, K, V> {
+    key: K,
+    slot: &'a mut Option<V>,
+    len: &'a mut usize,
+}
+
+pub struct OccupiedEntry<'a, K, V> {
+    key: K,
+    slot: &'a mut Option<V>,
+    len: &'a mut usize,
+}
+
+impl<'a, K, V> Entry<'a, K, V>
+where
+    K: Id,
+{
+    pub fn key(&self) -> K {
+        match self {
+            Entry::Vacant(entry) => entry.key,
+            Entry::Occupied(entry) => entry.key,
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Vacant(entry) => entry.insert(default),
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        match self {
+            Entry::Vacant(entry) => entry.insert(default()),
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    #[must_use]
+    pub fn and_modify(self, f: impl FnOnce(&mut V)) -> Self {
+        match self {
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+        }
+    }
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V>
+where
+    K: Id,
+{
+    pub fn key(&self) -> K {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        *self.len += 1;
+        self.slot.insert(value)
+    }
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V>
+where
+    K: Id,
+{
+    pub fn key(&self) -> K {
+        self.key
+    }
+
+    pub fn get(&self) -> &V {
+        self.slot.as_ref().unwrap()
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.slot.as_mut().unwrap()
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        self.slot.as_mut().unwrap()
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        *self.len -= 1;
+        self.slot.take().unwrap()
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    slots: Enumerate<slice::Iter<'a, Option<V>>>,
+    len: usize,
+    marker: PhantomData<fn(K) -> K>,
+}
+
+pub struct IterMut<'a, K, V> {
+    slots: Enumerate<slice::IterMut<'a, Option<V>>>,
+    len: usize,
+    marker: PhantomData<fn(K) -> K>,
+}
+
+pub struct IntoIter<K, V> {
+    slots: Enumerate<vec::IntoIter<Option<V>>>,
+    len: usize,
+    marker: PhantomData<fn(K) -> K>,
+}
+
+macro_rules! map_iterator {
+    ($name:ident $(<$life:lifetime>)?, $item:ty, |$slot:ident| $value:expr) => {
+        impl<$($life,)? K, V> Iterator for $name<$($life,)? K, V>
+        where
+            K: Id,
+        {
+            type Item = (K, $item);
+
+            fn next(&mut self) -> Option<Self::Item> {
+                for (index, $slot) in &mut self.slots {
+                    if let Some(value) = $value {
+                        self.len -= 1;
+                        return Some((K::from_raw(index as u32), value));
+                    }
+                }
+                None
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (self.len, Some(self.len))
+            }
+        }
+
+        impl<$($life,)? K, V> DoubleEndedIterator for $name<$($life,)? K, V>
+        where
+            K: Id,
+        {
+            fn next_back(&mut self) -> Option<Self::Item> {
+                while let Some((index, $slot)) = self.slots.next_back() {
+                    if let Some(value) = $value {
+                        self.len -= 1;
+                        return Some((K::from_raw(index as u32), value));
+                    }
+                }
+                None
+            }
+        }
+
+        impl<$($life,)? K, V> ExactSizeIterator for $name<$($life,)? K, V> where K: Id {}
+
+        impl<$($life,)? K, V> FusedIterator for $name<$($life,)? K, V> where K: Id {}
+    };
+}
+
+map_iterator!(Iter<'a>, &'a V, |slot| slot.as_ref());
+map_iterator!(IterMut<'a>, &'a mut V, |slot| slot.as_mut());
+map_iterator!(IntoIter, V, |slot| slot);
+
+impl<K, V> Default for IdMap<K, V>
+where
+    K: Id,
+{
+    fn default() -> Self {
+        IdMap::new()
+    }
+}
+
+impl<K, V> Clone for IdMap<K, V>
+where
+    V: Clone,
+{
+    fn clone(&self) -> Self {
+        IdMap {
+            slots: self.slots.clone(),
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V> Debug for IdMap<K, V>
+where
+    K: Id + Debug,
+    V: Debug,
+{
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V> Index<K> for IdMap<K, V>
+where
+    K: Id,
+{
+    type Output = V;
+
+    fn index(&self, key: K) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<K, V> Index<&K> for IdMap<K, V>
+where
+    K: Id,
+{
+    type Output = V;
+
+    fn index(&self, key: &K) -> &V {
+        &self[*key]
+    }
+}
+
+impl<K, V> IndexMut<K> for IdMap<K, V>
+where
+    K: Id,
+{
+    fn index_mut(&mut self, key: K) -> &mut V {
+        self.get_mut(key).expect("no entry found for key")
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for IdMap<K, V>
+where
+    K: Id,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = IdMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, V> Extend<(K, V)> for IdMap<K, V>
+where
+    K: Id,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V> IntoIterator for IdMap<K, V>
+where
+    K: Id,
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            slots: self.slots.into_iter().enumerate(),
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a IdMap<K, V>
+where
+    K: Id,
+{
+    type Item = (K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut IdMap<K, V>
+where
+    K: Id,
+{
+    type Item = (K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K> IdSet<K>
+where
+    K: Id,
+{
+    pub const fn new() -> Self {
+        IdSet {
+            words: Vec::new(),
+            len: 0,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.words.clear();
+        self.len = 0;
+    }
+
+    pub fn contains(&self, key: K) -> bool {
+        let (word, bit) = position(key);
+        self.words.get(word).is_some_and(|word| word & bit != 0)
+    }
+
+    /// Returns whether the id was newly inserted.
+    pub fn insert(&mut self, key: K) -> bool {
+        let (word, bit) = position(key);
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        let word = &mut self.words[word];
+        let inserted = *word & bit == 0;
+        *word |= bit;
+        self.len += inserted as usize;
+        inserted
+    }
+
+    /// Returns whether the id was present in the set.
+    pub fn remove(&mut self, key: K) -> bool {
+        let (word, bit) = position(key);
+        let Some(word) = self.words.get_mut(word) else {
+            return false;
+        };
+        let removed = *word & bit != 0;
+        *word &= !bit;
+        self.len -= removed as usize;
+        removed
+    }
+
+    pub fn iter(&self) -> SetIter<'_, K> {
+        SetIter {
+            words: self.words.iter().enumerate(),
+            front: None,
+            back: None,
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+}
+
+fn position<K: Id>(key: K) -> (usize, u64) {
+    let raw = key.raw();
+    ((raw / 64) as usize, 1 << (raw % 64))
+}
+
+pub struct SetIter<'a, K> {
+    words: Enumerate<slice::Iter<'a, u64>>,
+    front: Option<(usize, u64)>,
+    back: Option<(usize, u64)>,
+    len: usize,
+    marker: PhantomData<fn(K) -> K>,
+}
+
+impl<'a, K> Iterator for SetIter<'a, K>
+where
+    K: Id,
+{
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        loop {
+            if let Some((index, bits)) = &mut self.front {
+                if *bits != 0 {
+                    let bit = bits.trailing_zeros();
+                    *bits &= *bits - 1;
+                    self.len -= 1;
+                    return Some(K::from_raw(*index as u32 * 64 + bit));
+                }
+            }
+            if let Some((index, &bits)) = self.words.next() {
+                self.front = Some((index, bits));
+            } else {
+                // Drain whatever word the back half has started on.
+                self.front = self.back.take();
+                if self.front.is_none_or(|(_, bits)| bits == 0) {
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for SetIter<'a, K>
+where
+    K: Id,
+{
+    fn next_back(&mut self) -> Option<K> {
+        loop {
+            if let Some((index, bits)) = &mut self.back {
+                if *bits != 0 {
+                    let bit = 63 - bits.leading_zeros();
+                    *bits &= !(1 << bit);
+                    self.len -= 1;
+                    return Some(K::from_raw(*index as u32 * 64 + bit));
+                }
+            }
+            if let Some((index, &bits)) = self.words.next_back() {
+                self.back = Some((index, bits));
+            } else {
+                self.back = self.front.take();
+                if self.back.is_none_or(|(_, bits)| bits == 0) {
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl<'a, K> ExactSizeIterator for SetIter<'a, K> where K: Id {}
+
+impl<'a, K> FusedIterator for SetIter<'a, K> where K: Id {}
+
+impl<K> Default for IdSet<K>
+where
+    K: Id,
+{
+    fn default() -> Self {
+        IdSet::new()
+    }
+}
+
+impl<K> Clone for IdSet<K> {
+    fn clone(&self) -> Self {
+        IdSet {
+            words: self.words.clone(),
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K> Debug for IdSet<K>
+where
+    K: Id + Debug,
+{
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K> FromIterator<K> for IdSet<K>
+where
+    K: Id,
+{
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut set = IdSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<K> Extend<K> for IdSet<K>
+where
+    K: Id,
+{
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+impl<'a, K> IntoIterator for &'a IdSet<K>
+where
+    K: Id,
+{
+    type Item = K;
+    type IntoIter = SetIter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IdMap, IdSet};
+    use crate::versions::VersionId;
+
+    #[test]
+    fn test_map() {
+        let mut map = IdMap::new();
+        assert_eq!(map.insert(VersionId(3), "c"), None);
+        assert_eq!(map.insert(VersionId(1), "a"), None);
+        assert_eq!(map.insert(VersionId(3), "C"), Some("c"));
+        *map.entry(VersionId(2)).or_insert("b") = "B";
+        assert_eq!(map.len(), 3);
+        assert_eq!(map[VersionId(3)], "C");
+
+        let entries: Vec<_> = map.iter().map(|(k, v)| (k.0, *v)).collect();
+        assert_eq!(entries, [(1, "a"), (2, "B"), (3, "C")]);
+
+        assert_eq!(map.remove(VersionId(2)), Some("B"));
+        assert_eq!(map.remove(VersionId(99)), None);
+        let entries: Vec<_> = map.into_iter().rev().map(|(k, v)| (k.0, v)).collect();
+        assert_eq!(entries, [(3, "C"), (1, "a")]);
+    }
+
+    #[test]
+    fn test_set() {
+        let mut set: IdSet<VersionId> = [5, 64, 0, 200, 63].map(VersionId).into_iter().collect();
+        assert!(!set.insert(VersionId(64)));
+        assert!(set.contains(VersionId(63)));
+        assert!(!set.contains(VersionId(1000)));
+        assert_eq!(set.len(), 5);
+
+        let ids: Vec<u32> = set.iter().map(|id| id.0).collect();
+        assert_eq!(ids, [0, 5, 63, 64, 200]);
+        let ids: Vec<u32> = set.iter().rev().map(|id| id.0).collect();
+        assert_eq!(ids, [200, 64, 63, 5, 0]);
+
+        let mut iter = set.iter();
+        assert_eq!(iter.next(), Some(VersionId(0)));
+        assert_eq!(iter.next_back(), Some(VersionId(200)));
+        assert_eq!(iter.next_back(), Some(VersionId(64)));
+        assert_eq!(iter.next_back(), Some(VersionId(63)));
+        assert_eq!(iter.next(), Some(VersionId(5)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        assert!(set.remove(VersionId(5)));
+        assert!(!set.remove(VersionId(5)));
+        assert_eq!(set.len(), 4);
+    }
+}
@@ -0,0 +1,462 @@
+use crate::categories::CategoryId;
+use crate::crate_owners::OwnerId;
+use crate::crates::CrateId;
+use crate::idmap::{Id, IdMap};
+use crate::keywords::KeywordId;
+use crate::teams::TeamId;
+use crate::users::UserId;
+use crate::versions::VersionId;
+use crate::DbDump;
+use std::collections::hash_map::{Entry, HashMap as Map};
+use std::iter::FusedIterator;
+use std::slice;
+
+/// Relational lookups over a fully loaded [`DbDump`].
+///
+/// Building an index takes one pass over the tables involved. Afterward every
+/// lookup is a direct array or hash map access, rather than a scan of the
+/// underlying `Vec`.
+///
+/// ```no_run
+/// fn main() -> db_dump::Result<()> {
+///     let db = db_dump::load_all("./db-dump.tar.gz")?;
+///     let index = db.index();
+///
+///     let serde = index.crate_by_name("serde").unwrap();
+///     for version in index.versions_of(serde.id) {
+///         let deps = index.dependencies_of(version.id).len();
+///         println!("{} {} has {} dependencies", serde.name, version.num, deps);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub struct Index<'a> {
+    dump: &'a DbDump,
+    categories: IdMap<CategoryId, u32>,
+    crates: IdMap<CrateId, u32>,
+    crate_names: Map<&'a str, u32>,
+    default_versions: IdMap<CrateId, u32>,
+    keywords: IdMap<KeywordId, u32>,
+    teams: IdMap<TeamId, u32>,
+    users: IdMap<UserId, u32>,
+    user_logins: Map<&'a str, u32>,
+    versions: IdMap<VersionId, u32>,
+    versions_of: IdMap<CrateId, Vec<u32>>,
+    dependencies_of: IdMap<VersionId, Vec<u32>>,
+    dependents_of: IdMap<CrateId, Vec<u32>>,
+    owners_of: IdMap<CrateId, Vec<u32>>,
+    crates_owned_by: Map<OwnerId, Vec<u32>>,
+    categories_of: IdMap<CrateId, Vec<u32>>,
+    keywords_of: IdMap<CrateId, Vec<u32>>,
+}
+
+/// A crate that depends on another, as returned by
+/// [`Index::reverse_dependencies`].
+#[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
+pub struct Dependent<'a> {
+    pub krate: &'a crate::crates::Row,
+    /// The default version of the dependent crate.
+    pub version: &'a crate::versions::Row,
+    pub dependency: &'a crate::dependencies::Row,
+}
+
+/// Iterator over rows of one table, as returned by the lookups on [`Index`].
+pub struct Rows<'a, T> {
+    indices: slice::Iter<'a, u32>,
+    table: &'a [T],
+}
+
+impl DbDump {
+    /// Build an [`Index`] for performing lookups across the tables of this
+    /// dump.
+    pub fn index(&self) -> Index<'_> {
+        Index::new(self)
+    }
+}
+
+impl<'a> Index<'a> {
+    pub fn new(dump: &'a DbDump) -> Self {
+        let categories = primary_key(&dump.categories, |row| row.id);
+        let crates = primary_key(&dump.crates, |row| row.id);
+        let crate_names = unique_key(&dump.crates, |row| row.name.as_str());
+        let default_versions = primary_key(&dump.default_versions, |row| row.crate_id);
+        let keywords = primary_key(&dump.keywords, |row| row.id);
+        let teams = primary_key(&dump.teams, |row| row.id);
+        let users = primary_key(&dump.users, |row| row.id);
+        let user_logins = unique_key(&dump.users, |row| row.gh_login.as_str());
+        let versions = primary_key(&dump.versions, |row| row.id);
+
+        let versions_of = group_by(&dump.versions, |row| row.crate_id);
+        let dependencies_of = group_by(&dump.dependencies, |row| row.version_id);
+        let dependents_of = group_by(&dump.dependencies, |row| row.crate_id);
+        let owners_of = group_by(&dump.crate_owners, |row| row.crate_id);
+
+        let mut crates_owned_by = Map::<OwnerId, Vec<u32>>::new();
+        for row in &dump.crate_owners {
+            if let Some(&krate) = crates.get(row.crate_id) {
+                crates_owned_by.entry(row.owner_id).or_default().push(krate);
+            }
+        }
+
+        let mut categories_of = IdMap::<CrateId, Vec<u32>>::new();
+        for row in &dump.crates_categories {
+            if let Some(&category) = categories.get(row.category_id) {
+                categories_of
+                    .entry(row.crate_id)
+                    .or_default()
+                    .push(category);
+            }
+        }
+
+        let mut keywords_of = IdMap::<CrateId, Vec<u32>>::new();
+        for row in &dump.crates_keywords {
+            if let Some(&keyword) = keywords.get(row.keyword_id) {
+                keywords_of.entry(row.crate_id).or_default().push(keyword);
+            }
+        }
+
+        Index {
+            dump,
+            categories,
+            crates,
+            crate_names,
+            default_versions,
+            keywords,
+            teams,
+            users,
+            user_logins,
+            versions,
+            versions_of,
+            dependencies_of,
+            dependents_of,
+            owners_of,
+            crates_owned_by,
+            categories_of,
+            keywords_of,
+        }
+    }
+
+    /// The dump that this index refers into.
+    pub fn dump(&self) -> &'a DbDump {
+        self.dump
+    }
+
+    pub fn category_by_id(&self, id: CategoryId) -> Option<&'a crate::categories::Row> {
+        lookup(self.categories.get(id), &self.dump.categories)
+    }
+
+    pub fn crate_by_id(&self, id: CrateId) -> Option<&'a crate::crates::Row> {
+        lookup(self.crates.get(id), &self.dump.crates)
+    }
+
+    pub fn crate_by_name(&self, name: &str) -> Option<&'a crate::crates::Row> {
+        lookup(self.crate_names.get(name), &self.dump.crates)
+    }
+
+    pub fn keyword_by_id(&self, id: KeywordId) -> Option<&'a crate::keywords::Row> {
+        lookup(self.keywords.get(id), &self.dump.keywords)
+    }
+
+    pub fn team_by_id(&self, id: TeamId) -> Option<&'a crate::teams::Row> {
+        lookup(self.teams.get(id), &self.dump.teams)
+    }
+
+    pub fn user_by_id(&self, id: UserId) -> Option<&'a crate::users::Row> {
+        lookup(self.users.get(id), &self.dump.users)
+    }
+
+    pub fn user_by_login(&self, gh_login: &str) -> Option<&'a crate::users::Row> {
+        lookup(self.user_logins.get(gh_login), &self.dump.users)
+    }
+
+    pub fn version_by_id(&self, id: VersionId) -> Option<&'a crate::versions::Row> {
+        lookup(self.versions.get(id), &self.dump.versions)
+    }
+
+    /// Every version of the given crate, in the order they appear in
+    /// versions.csv.
+    pub fn versions_of(&self, crate_id: CrateId) -> Rows<'_, crate::versions::Row> {
+        Rows::new(self.versions_of.get(crate_id), &self.dump.versions)
+    }
+
+    /// Every dependency declared by the given version.
+    pub fn dependencies_of(&self, version_id: VersionId) -> Rows<'_, crate::dependencies::Row> {
+        Rows::new(
+            self.dependencies_of.get(version_id),
+            &self.dump.dependencies,
+        )
+    }
+
+    /// Every dependency on the given crate, declared by any version of any
+    /// crate.
+    pub fn dependents_of(&self, crate_id: CrateId) -> Rows<'_, crate::dependencies::Row> {
+        Rows::new(self.dependents_of.get(crate_id), &self.dump.dependencies)
+    }
+
+    /// The users and teams that own the given crate.
+    pub fn owners_of(&self, crate_id: CrateId) -> Rows<'_, crate::crate_owners::Row> {
+        Rows::new(self.owners_of.get(crate_id), &self.dump.crate_owners)
+    }
+
+    /// The crates owned by a given user or team.
+    pub fn crates_owned_by(&self, owner: impl Into<OwnerId>) -> Rows<'_, crate::crates::Row> {
+        Rows::new(self.crates_owned_by.get(&owner.into()), &self.dump.crates)
+    }
+
+    pub fn categories_of(&self, crate_id: CrateId) -> Rows<'_, crate::categories::Row> {
+        Rows::new(self.categories_of.get(crate_id), &self.dump.categories)
+    }
+
+    pub fn keywords_of(&self, crate_id: CrateId) -> Rows<'_, crate::keywords::Row> {
+        Rows::new(self.keywords_of.get(crate_id), &self.dump.keywords)
+    }
+
+    /// The version that crates.io shows for the given crate, according to
+    /// default_versions.csv.
+    ///
+    /// Dumps from before that table existed fall back to the way crates.io
+    /// picks it: the greatest version that is neither yanked nor a
+    /// prerelease, else the greatest one that is not yanked, else the greatest
+    /// one.
+    pub fn default_version(&self, crate_id: CrateId) -> Option<&'a crate::versions::Row> {
+        let dump = self.dump;
+        if !dump.default_versions.is_empty() {
+            let row = lookup(self.default_versions.get(crate_id), &dump.default_versions)?;
+            return self.version_by_id(row.version_id);
+        }
+        self.versions_of
+            .get(crate_id)
+            .into_iter()
+            .flatten()
+            .map(|&i| &dump.versions[i as usize])
+            .max_by_key(|version| (!version.yanked, version.num.pre.is_empty(), &version.num))
+    }
+
+    /// The crates whose default version depends on the given crate, in the
+    /// order their dependencies appear in dependencies.csv.
+    ///
+    /// Every kind of dependency counts, including dev-dependencies. A crate
+    /// that depends on the given crate more than once, for example as both a
+    /// normal and a dev-dependency, is listed once, by its normal dependency if
+    /// it has one, otherwise by its build-dependency.
+    pub fn reverse_dependencies(&self, crate_id: CrateId) -> Vec<Dependent<'a>> {
+        let rank = |dependency: &crate::dependencies::Row| (dependency.kind.as_u8(), dependency.id);
+        let dump = self.dump;
+        let mut dependents = Vec::new();
+        let mut positions = Map::new();
+        for &i in self.dependents_of.get(crate_id).into_iter().flatten() {
+            let dependency = &dump.dependencies[i as usize];
+            let Some(version) = self.version_by_id(dependency.version_id) else {
+                continue;
+            };
+            if self
+                .default_version(version.crate_id)
+                .is_none_or(|default| default.id != version.id)
+            {
+                continue;
+            }
+            let Some(krate) = self.crate_by_id(version.crate_id) else {
+                continue;
+            };
+            let dependent = Dependent {
+                krate,
+                version,
+                dependency,
+            };
+            match positions.entry(krate.id) {
+                Entry::Vacant(entry) => {
+                    entry.insert(dependents.len());
+                    dependents.push(dependent);
+                }
+                Entry::Occupied(entry) => {
+                    let existing = &mut dependents[*entry.get()];
+                    if rank(dependency) < rank(existing.dependency) {
+                        *existing = dependent;
+                    }
+                }
+            }
+        }
+        dependents
+    }
+}
+
+fn primary_key<T, K>(table: &[T], key: impl Fn(&T) -> K) -> IdMap<K, u32>
+where
+    K: Id,
+{
+    let mut map = IdMap::new();
+    for (i, row) in table.iter().enumerate() {
+        map.insert(key(row), i as u32);
+    }
+    map
+}
+
+fn unique_key<'a, T>(table: &'a [T], key: impl Fn(&'a T) -> &'a str) -> Map<&'a str, u32> {
+    let mut map = Map::with_capacity(table.len());
+    for (i, row) in table.iter().enumerate() {
+        map.insert(key(row), i as u32);
+    }
+    map
+}
+
+fn group_by<T, K>(table: &[T], key: impl Fn(&T) -> K) -> IdMap<K, Vec<u32>>
+where
+    K: Id,
+{
+    let mut map = IdMap::<K, Vec<u32>>::new();
+    for (i, row) in table.iter().enumerate() {
+        map.entry(key(row)).or_default().push(i as u32);
+    }
+    map
+}
+
+fn lookup<'a, T>(i: Option<&u32>, table: &'a [T]) -> Option<&'a T> {
+    i.map(|&i| &table[i as usize])
+}
+
+impl<'a, T> Rows<'a, T> {
+    fn new(indices: Option<&'a Vec<u32>>, table: &'a [T]) -> Self {
+        Rows {
+            indices: indices.map_or(&[][..], Vec::as_slice).iter(),
+            table,
+        }
+    }
+}
+
+impl<'a, T> Iterator for Rows<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let i = *self.indices.next()?;
+        Some(&self.table[i as usize])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Rows<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let i = *self.indices.next_back()?;
+        Some(&self.table[i as usize])
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Rows<'a, T> {}
+
+impl<'a, T> FusedIterator for Rows<'a, T> {}
+
+impl<'a, T> Clone for Rows<'a, T> {
+    fn clone(&self) -> Self {
+        Rows {
+            indices: self.indices.clone(),
+            table: self.table,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dependencies::DependencyKind;
+    use crate::testing::DumpBuilder;
+
+    #[test]
+    fn test_lookups() {
+        let mut builder = DumpBuilder::new();
+        let alice = builder.user("alice").id;
+        let serde = builder.krate("serde").id;
+        let serde_v1 = builder.version(serde, "1.0.0").id;
+        builder.owner(serde, alice);
+        let json = builder.krate("serde_json").id;
+        let json_v1 = builder.version(json, "1.0.0").id;
+        builder.dependency(json_v1, serde, "^1.0");
+        let db = builder.build();
+        let index = db.index();
+
+        assert_eq!(index.crate_by_name("serde").unwrap().id, serde);
+        assert!(index.crate_by_name("serde-json").is_none());
+        assert_eq!(index.crate_by_id(json).unwrap().name, "serde_json");
+        assert_eq!(index.version_by_id(serde_v1).unwrap().crate_id, serde);
+        assert_eq!(index.user_by_login("alice").unwrap().id, alice);
+        assert_eq!(index.versions_of(serde).len(), 1);
+        assert_eq!(index.dependencies_of(json_v1).len(), 1);
+        assert_eq!(
+            index.dependents_of(serde).next().unwrap().version_id,
+            json_v1
+        );
+        assert_eq!(index.dependents_of(json).len(), 0);
+        assert_eq!(index.crates_owned_by(alice).next().unwrap().id, serde);
+    }
+
+    #[test]
+    fn test_default_version() {
+        let mut builder = DumpBuilder::new();
+        let stable = builder.krate("stable").id;
+        builder.version(stable, "1.0.0");
+        builder.version(stable, "1.1.0").yanked = true;
+        builder.version(stable, "2.0.0-alpha.1");
+        let prerelease = builder.krate("prerelease").id;
+        builder.version(prerelease, "0.1.0-alpha.1");
+        builder.version(prerelease, "0.1.0-alpha.2");
+        let yanked = builder.krate("yanked").id;
+        builder.version(yanked, "0.1.0").yanked = true;
+        builder.version(yanked, "0.2.0").yanked = true;
+        let mut db = builder.build();
+
+        let defaults = |db: &crate::DbDump| {
+            let index = db.index();
+            [stable, prerelease, yanked].map(|crate_id| {
+                let version = index.default_version(crate_id).unwrap();
+                version.num.to_string()
+            })
+        };
+
+        // default_versions.csv is authoritative when present.
+        db.default_versions[0].version_id = db.versions[2].id;
+        assert_eq!(defaults(&db), ["2.0.0-alpha.1", "0.1.0-alpha.2", "0.2.0"]);
+
+        // Otherwise the same choice crates.io would make.
+        db.default_versions.clear();
+        assert_eq!(defaults(&db), ["1.0.0", "0.1.0-alpha.2", "0.2.0"]);
+    }
+
+    #[test]
+    fn test_reverse_dependencies() {
+        let mut builder = DumpBuilder::new();
+        let serde = builder.krate("serde").id;
+        builder.version(serde, "1.0.0");
+        let mut dependent = |name: &str, kinds: &[DependencyKind]| {
+            let crate_id = builder.krate(name).id;
+            let version_id = builder.version(crate_id, "1.0.0").id;
+            for &kind in kinds {
+                builder.dependency(version_id, serde, "^1.0").kind = kind;
+            }
+            crate_id
+        };
+        let dev_then_normal = dependent("a", &[DependencyKind::Dev, DependencyKind::Normal]);
+        let dev_only = dependent("b", &[DependencyKind::Dev]);
+        let dev_then_build = dependent("c", &[DependencyKind::Dev, DependencyKind::Build]);
+        // Only an older version of d depends on serde.
+        let old = dependent("d", &[DependencyKind::Normal]);
+        builder.version(old, "2.0.0");
+        let db = builder.build();
+        let index = db.index();
+
+        let dependents: Vec<_> = index
+            .reverse_dependencies(serde)
+            .into_iter()
+            .map(|dependent| (dependent.krate.id, dependent.dependency.kind))
+            .collect();
+        assert!(matches!(
+            dependents[..],
+            [
+                (a, DependencyKind::Normal),
+                (b, DependencyKind::Dev),
+                (c, DependencyKind::Build),
+            ] if a == dev_then_normal && b == dev_only && c == dev_then_build
+        ));
+    }
+}
@@ -0,0 +1,45 @@
+use std::collections::{BTreeMap as Map, HashSet as Set};
+use std::mem;
+use std::sync::Arc;
+
+// Deduplicates strings that repeat across many rows, such as license
+// expressions and dependency target cfgs, so that equal strings share a single
+// allocation.
+#[derive(Default)]
+pub(crate) struct Interner {
+    strings: Set<Arc<str>>,
+}
+
+pub(crate) trait Intern {
+    fn intern(&mut self, interner: &mut Interner);
+}
+
+impl Interner {
+    pub(crate) fn intern(&mut self, string: &mut Arc<str>) {
+        if let Some(existing) = self.strings.get(string) {
+            *string = Arc::clone(existing);
+        } else {
+            self.strings.insert(Arc::clone(string));
+        }
+    }
+
+    pub(crate) fn intern_all(&mut self, strings: &mut [Arc<str>]) {
+        for string in strings {
+            self.intern(string);
+        }
+    }
+
+    pub(crate) fn intern_map(&mut self, map: &mut Map<Arc<str>, Vec<Arc<str>>>) {
+        if map.is_empty() {
+            return;
+        }
+        *map = mem::take(map)
+            .into_iter()
+            .map(|(mut key, mut values)| {
+                self.intern(&mut key);
+                self.intern_all(&mut values);
+                (key, values)
+            })
+            .collect();
+    }
+}
@@ -0,0 +1,263 @@
+//! Export of a dump into JSON Lines files, one per table, and the reverse.
+//!
+//! Every table of the dump becomes a file `<table>.jsonl` in which each line
+//! is one row as a JSON object, keyed by the columns of the csv file. The
+//! values are the fields that [`Writer`] puts in the csv: ids, counts and
+//! sizes are numbers, missing optional values are null, and everything else
+//! is the text of the csv field, such as `"2024-01-31 02:00:17.123456"` for a
+//! datetime, `"t"` or `"f"` for a boolean, `"{serde,serialization}"` for a
+//! list, or the JSON object of the features of a version as a string. A
+//! checksum is lowercase hex, or empty if there is none.
+//!
+//! A directory of these files is a dump in its own right: passing it to
+//! [`Loader::load`] or [`load_all`] in place of a db-dump.tar.gz reads the
+//! rows back from JSON. Tables without a file are treated as empty, but the
+//! directory must contain at least one `.jsonl` file.
+//!
+//! ```no_run
+//! fn main() -> db_dump::Result<()> {
+//!     db_dump::jsonl::export("./db-dump.tar.gz", "./jsonl")?;
+//!     let db = db_dump::load_all("./jsonl")?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ```console
+//! $ jq -r 'select(.yanked == "t") | .num' jsonl/versions.jsonl
+//! ```
+//!
+//! [`Writer`]: crate::Writer
+//! [`Loader::load`]: crate::Loader::load
+//! [`load_all`]: crate::load_all
+
+use crate::error::{err, Error, Result};
+use crate::export::Staging;
+use crate::Loader;
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Convert the db-dump.tar.gz at the given path into one JSON Lines file per
+/// table in the given directory.
+///
+/// The directory is created if it does not exist. Returns an error if any of
+/// the files already exists. Rows are streamed from [`Loader`] into the
+/// files, so the dump is never held in memory. Every table gets a file, even
+/// if it is absent from the dump.
+///
+/// The files are written into a temporary directory inside the given one and
+/// moved into place once they are all complete. If the export fails, none of
+/// them are left behind.
+pub fn export(dump: impl AsRef<Path>, dir: impl AsRef<Path>) -> Result<()> {
+    let mut staging = Staging::new(dir.as_ref())?;
+    let mut create = |table: &str| -> Result<BufWriter<File>> {
+        let file = staging.create(format!("{}.jsonl", table))?;
+        Ok(BufWriter::new(file))
+    };
+    let mut categories = create("categories")?;
+    let mut crate_downloads = create("crate_downloads")?;
+    let mut crate_owners = create("crate_owners")?;
+    let mut crates = create("crates")?;
+    let mut crates_categories = create("crates_categories")?;
+    let mut crates_keywords = create("crates_keywords")?;
+    let mut default_versions = create("default_versions")?;
+    let mut deleted_crates = create("deleted_crates")?;
+    let mut dependencies = create("dependencies")?;
+    let mut keywords = create("keywords")?;
+    let mut metadata = create("metadata")?;
+    let mut reserved_crate_names = create("reserved_crate_names")?;
+    let mut teams = create("teams")?;
+    let mut users = create("users")?;
+    let mut version_downloads = create("version_downloads")?;
+    let mut versions = create("versions")?;
+
+    // Loader callbacks cannot return errors, so the first one is held here
+    // and the remaining rows are skipped.
+    let error = RefCell::new(None);
+
+    Loader::new()
+        .categories(|row| write(&error, &mut categories, &row))
+        .crate_downloads(|row| write(&error, &mut crate_downloads, &row))
+        .crate_owners(|row| write(&error, &mut crate_owners, &row))
+        .crates(|row| write(&error, &mut crates, &row))
+        .crates_categories(|row| write(&error, &mut crates_categories, &row))
+        .crates_keywords(|row| write(&error, &mut crates_keywords, &row))
+        .default_versions(|row| write(&error, &mut default_versions, &row))
+        .deleted_crates(|row| write(&error, &mut deleted_crates, &row))
+        .dependencies(|row| write(&error, &mut dependencies, &row))
+        .keywords(|row| write(&error, &mut keywords, &row))
+        .metadata(|row| write(&error, &mut metadata, &row))
+        .reserved_crate_names(|row| write(&error, &mut reserved_crate_names, &row))
+        .teams(|row| write(&error, &mut teams, &row))
+        .users(|row| write(&error, &mut users, &row))
+        .version_downloads(|row| write(&error, &mut version_downloads, &row))
+        .versions(|row| write(&error, &mut versions, &row))
+        .load(dump)?;
+
+    if let Some(error) = error.into_inner() {
+        return Err(error);
+    }
+    for mut out in [
+        categories,
+        crate_downloads,
+        crate_owners,
+        crates,
+        crates_categories,
+        crates_keywords,
+        default_versions,
+        deleted_crates,
+        dependencies,
+        keywords,
+        metadata,
+        reserved_crate_names,
+        teams,
+        users,
+        version_downloads,
+        versions,
+    ] {
+        out.flush()?;
+    }
+    staging.persist()
+}
+
+fn write(error: &RefCell<Option<Error>>, out: &mut BufWriter<File>, row: &impl Serialize) {
+    if error.borrow().is_some() {
+        return;
+    }
+    let result = serde_json::to_writer(&mut *out, row)
+        .map_err(err)
+        .and_then(|()| Ok(out.write_all(b"\n")?));
+    if let Err(e) = result {
+        *error.borrow_mut() = Some(e);
+    }
+}
+
+// Fails unless the directory contains at least one `.jsonl` file, so that a
+// mistyped path is not loaded as an empty dump.
+pub(crate) fn check(dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        if entry?.path().extension().is_some_and(|ext| ext == "jsonl") {
+            return Ok(());
+        }
+    }
+    Err(err(format_args!(
+        "{}: directory contains no .jsonl files",
+        dir.display(),
+    )))
+}
+
+// Reads the rows of `<table>.jsonl` in the given directory, if it exists.
+pub(crate) fn read<T>(dir: &Path, table: &str, mut f: impl FnMut(T)) -> Result<()>
+where
+    T: DeserializeOwned,
+{
+    let path = dir.join(table).with_extension("jsonl");
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let row = serde_json::from_str(&line)
+            .map_err(|e| err(format_args!("{}.jsonl:{}: {}", table, i + 1, e)))?;
+        f(row);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crate_owners::OwnerId;
+    use crate::testing::DumpBuilder;
+    use crate::Date;
+    use serde_json::Value;
+    use std::fs;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_round_trip() {
+        let mut builder = DumpBuilder::new();
+        let alice = builder.user("alice").id;
+        let team = builder.team("github:serde-rs:publish").id;
+        let serde = builder.krate("serde").id;
+        let serde_v1 = builder.version(serde, "1.0.0");
+        serde_v1.checksum = Some([0xab; 32]);
+        serde_v1
+            .features
+            .insert(Arc::from("default"), vec![Arc::from("std")]);
+        serde_v1.keywords = vec![Arc::from("serde"), Arc::from("serialization")];
+        serde_v1.yanked = true;
+        let serde_v1 = serde_v1.id;
+        builder.version(serde, "1.0.1");
+        builder.owner(serde, alice);
+        builder.owner(serde, team).created_by = Some(alice);
+        builder.keyword(serde, "serde");
+        builder.download_series(serde_v1, Date::from_ymd(2024, 1, 1), &[3, 4]);
+        let json = builder.krate("serde_json").id;
+        let json_v1 = builder.version(json, "1.0.0").id;
+        builder.dependency(json_v1, serde, "^1.0").features = vec![Arc::from("derive")];
+        let archive = builder.temp_archive().unwrap();
+
+        let dir = archive.path().with_extension("jsonl");
+        super::export(&archive, &dir).unwrap();
+
+        let versions = fs::read_to_string(dir.join("versions.jsonl")).unwrap();
+        let version: Value = serde_json::from_str(versions.lines().next().unwrap()).unwrap();
+        assert_eq!(version["created_at"], "2024-01-01 00:00:00.000000");
+        assert_eq!(version["yanked"], "t");
+        assert_eq!(version["features"], r#"{"default":["std"]}"#);
+        assert_eq!(version["keywords"], "{serde,serialization}");
+        assert_eq!(version["links"], Value::Null);
+        let dependencies = fs::read_to_string(dir.join("dependencies.jsonl")).unwrap();
+        let dependency: Value = serde_json::from_str(dependencies.trim_end()).unwrap();
+        assert_eq!(dependency["features"], "{derive}");
+        assert_eq!(dependency["kind"], 0);
+        let owners = fs::read_to_string(dir.join("crate_owners.jsonl")).unwrap();
+        let owner: Value = serde_json::from_str(owners.lines().nth(1).unwrap()).unwrap();
+        assert_eq!(owner["owner_id"], team.0);
+        assert_eq!(owner["owner_kind"], 1);
+
+        let original = crate::load_all(&archive).unwrap();
+        let db = crate::load_all(&dir).unwrap();
+        assert_eq!(
+            format!("{:?}", db.versions),
+            format!("{:?}", original.versions)
+        );
+        assert_eq!(
+            format!("{:?}", db.dependencies),
+            format!("{:?}", original.dependencies),
+        );
+        assert_eq!(db.crate_owners[1].owner_id, OwnerId::Team(team));
+        assert_eq!(db.version_downloads.len(), 2);
+        assert_eq!(
+            db.metadata.total_downloads,
+            original.metadata.total_downloads
+        );
+        assert!(db.validate().is_empty());
+
+        // The files already exist.
+        assert!(super::export(&archive, &dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_empty_dir() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path();
+        let Err(error) = crate::load_all(dir) else {
+            panic!("loaded an empty directory");
+        };
+        assert!(error.to_string().contains("no .jsonl files"), "{}", error);
+
+        fs::write(dir.join("crates.jsonl"), "").unwrap();
+        let db = crate::load_all(dir).unwrap();
+        assert!(db.crates.is_empty());
+    }
+}
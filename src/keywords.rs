@@ -13,7 +13,7 @@ use std::hash::{Hash, Hasher};
 pub struct KeywordId(pub u32);
 
 /// One row of **keywords.csv**.
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 #[non_exhaustive]
 pub struct Row {
@@ -22,7 +22,10 @@ pub struct Row {
     /// UNIQUE
     pub keyword: String,
     pub crates_cnt: u32,
-    #[serde(deserialize_with = "crate::datetime::de")]
+    #[serde(
+        serialize_with = "crate::datetime::ser",
+        deserialize_with = "crate::datetime::de"
+    )]
     pub created_at: DateTime<Utc>,
 }
 
@@ -4,7 +4,7 @@
 //! [crates-io]: https://img.shields.io/badge/crates.io-fc8d62?style=for-the-badge&labelColor=555555&logo=rust
 //! [docs-rs]: https://img.shields.io/badge/docs.rs-66c2a5?style=for-the-badge&labelColor=555555&logo=docs.rs
 
-#![doc(html_root_url = "https://docs.rs/db-dump/0.7.15")]
+#![doc(html_root_url = "https://docs.rs/db-dump/0.8.0")]
 #![cfg_attr(not(check_cfg), allow(unexpected_cfgs))]
 #![allow(
     clippy::cast_lossless,
@@ -26,13 +26,24 @@
 
 extern crate self as db_dump;
 
+#[cfg(feature = "anonymize")]
+mod anonymize;
 mod bool;
+mod cache;
+#[cfg(any(feature = "parquet", feature = "polars"))]
+mod columnar;
 mod date;
 mod datetime;
 mod error;
+mod export;
+mod hex;
 mod ignore;
+mod index;
+mod intern;
 mod load;
+mod refs;
 mod set;
+mod write;
 
 pub mod categories;
 pub mod crate_downloads;
@@ -43,23 +54,45 @@ pub mod crates_keywords;
 pub mod default_versions;
 pub mod deleted_crates;
 pub mod dependencies;
+pub mod idmap;
+pub mod jsonl;
 pub mod keywords;
 pub mod metadata;
+#[cfg(feature = "mirror")]
+pub mod mirror;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "polars")]
+pub mod polars;
+pub mod registry_index;
 pub mod reserved_crate_names;
+pub mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod subset;
+pub mod synthetic;
 pub mod teams;
+pub mod testing;
 pub mod users;
+pub mod validate;
 pub mod version_downloads;
 pub mod versions;
 
 pub use crate::date::Date;
 pub use crate::error::{Error, Result};
+pub use crate::idmap::{IdMap, IdSet};
+pub use crate::index::{Dependent, Index, Rows};
 pub use crate::load::{load_all, Loader};
+pub use crate::refs::{
+    CrateRef, DeletedCrateRef, DependencyRef, OwnerRef, TeamRef, UserRef, VersionRef,
+};
+pub use crate::write::Writer;
 
 /// A crates.io DB dump with *everything* deserialized into memory. Use
 /// [`Loader`] to load only parts of a dump, which is more efficient.
 ///
 /// One of these full dumps can be loaded via [`db_dump::load_all`].
-#[derive(Default)]
+#[derive(Clone, Default)]
 #[non_exhaustive]
 pub struct DbDump {
     /// <table style="width:initial"><tr>
@@ -205,7 +238,7 @@ pub struct DbDump {
     /// <td>downloads</td>
     /// <td>date</td>
     /// </tr></table>
-    pub version_downloads: Vec<version_downloads::Row>,
+    pub version_downloads: version_downloads::Table,
 
     /// <table style="width:initial"><tr>
     /// <th>versions.csv</th>
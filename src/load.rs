@@ -1,4 +1,5 @@
 use crate::error::{err, Result};
+use crate::intern::{Intern, Interner};
 use crate::DbDump;
 use csv::StringRecord;
 use flate2::read::GzDecoder;
@@ -8,7 +9,7 @@ use serde::de::DeserializeOwned;
 use std::borrow::Cow;
 use std::fs::File;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tar::Archive;
 
 /// Perform a streaming load of only relevant database tables.
@@ -59,6 +60,8 @@ pub struct Loader<'a> {
     users: Option<Callback<'a, crate::users::Row>>,
     version_downloads: Option<Callback<'a, crate::version_downloads::Row>>,
     versions: Option<Callback<'a, crate::versions::Row>>,
+    interner: Option<Interner>,
+    cache: Option<PathBuf>,
 }
 
 struct Callback<'a, T> {
@@ -169,8 +172,69 @@ impl<'a> Loader<'a> {
         self
     }
 
+    /// Share a single allocation among equal strings in the columns that
+    /// repeat the same few values across millions of rows.
+    ///
+    /// The affected fields are [`versions::Row`]'s `license`, `features`,
+    /// `categories` and `keywords`, and [`dependencies::Row`]'s `target` and
+    /// `features`. Interning costs a hash lookup per string during loading, in
+    /// exchange for a substantially smaller memory footprint if the rows are
+    /// being retained. Strings are not interned unless this is called, including
+    /// by [`load_all`].
+    ///
+    /// [`versions::Row`]: crate::versions::Row
+    /// [`dependencies::Row`]: crate::dependencies::Row
+    pub fn intern_strings(&mut self) -> &mut Self {
+        self.interner = Some(Interner::default());
+        self
+    }
+
+    /// Reuse a binary cache of the entire dump, stored at the given path and
+    /// keyed by the size and modification time of the archive being loaded,
+    /// or of the `.jsonl` files when loading a directory of JSON Lines.
+    ///
+    /// When the cache is missing or was built from a different archive, the
+    /// whole archive is parsed once and the cache is rewritten, which makes
+    /// that load slower and more memory-intensive than an uncached one. If the
+    /// cache cannot be written, the load still succeeds without it.
+    ///
+    /// Subsequent loads of the same archive skip CSV parsing entirely, but
+    /// still decode every table in the cache and hold the whole dump in memory
+    /// while feeding the rows to the callbacks, regardless of which tables
+    /// have a callback. A cache pays off for repeatedly loading most of a
+    /// dump; for loading one or two small tables, an uncached `Loader` that
+    /// skips the rest of the archive may use much less memory.
+    ///
+    /// ```no_run
+    /// fn main() -> db_dump::Result<()> {
+    ///     let mut crates = 0;
+    ///     db_dump::Loader::new()
+    ///         .crates(|_row| crates += 1)
+    ///         .cache("./db-dump.cache")
+    ///         .load("./db-dump.tar.gz")?;
+    ///
+    ///     println!("{} crates", crates);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn cache(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.cache = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// Load the db-dump.tar.gz at the given path, invoking the callbacks for
+    /// the rows of each table.
+    ///
+    /// The path may also be a directory of JSON Lines files written by
+    /// [`db_dump::jsonl::export`][crate::jsonl::export]. Loading a directory
+    /// without any `.jsonl` files is an error.
     pub fn load(&mut self, path: impl AsRef<Path>) -> Result<()> {
-        do_load(path.as_ref(), self)
+        let path = path.as_ref();
+        match self.cache.clone() {
+            Some(cache) => do_load_cached(path, &cache, self),
+            None if path.is_dir() => do_load_jsonl(path, self),
+            None => do_load(path, self),
+        }
     }
 }
 
@@ -222,6 +286,8 @@ fn do_load(path: &Path, loader: &mut Loader) -> Result<()> {
             users,
             version_downloads,
             versions,
+            interner: _,
+            cache: _,
         } = loader;
 
         if categories.as_ref().map_or(true, Callback::done)
@@ -273,6 +339,8 @@ fn do_load(path: &Path, loader: &mut Loader) -> Result<()> {
             users,
             version_downloads,
             versions,
+            interner,
+            cache: _,
         } = loader;
 
         let (path, result) = if path.ends_with("badges.csv") {
@@ -294,7 +362,7 @@ fn do_load(path: &Path, loader: &mut Loader) -> Result<()> {
         } else if path.ends_with("deleted_crates.csv") {
             ("deleted_crates", read(deleted_crates, entry))
         } else if path.ends_with("dependencies.csv") {
-            ("dependencies", read(dependencies, entry))
+            ("dependencies", read_interned(dependencies, entry, interner))
         } else if path.ends_with("keywords.csv") {
             ("keywords", read(keywords, entry))
         } else if path.ends_with("metadata.csv") {
@@ -310,7 +378,7 @@ fn do_load(path: &Path, loader: &mut Loader) -> Result<()> {
         } else if path.ends_with("version_downloads.csv") {
             ("version_downloads", read(version_downloads, entry))
         } else if path.ends_with("versions.csv") {
-            ("versions", read(versions, entry))
+            ("versions", read_interned(versions, entry, interner))
         } else {
             if cfg!(db_dump_panic_on_unrecognized_csv) {
                 panic!("unimplemented: {}", path.display());
@@ -329,6 +397,124 @@ fn do_load(path: &Path, loader: &mut Loader) -> Result<()> {
     Ok(())
 }
 
+fn do_load_jsonl(dir: &Path, loader: &mut Loader) -> Result<()> {
+    crate::jsonl::check(dir)?;
+
+    #[deny(unused_variables)]
+    let Loader {
+        categories,
+        crate_downloads,
+        crate_owners,
+        crates,
+        crates_categories,
+        crates_keywords,
+        default_versions,
+        deleted_crates,
+        dependencies,
+        keywords,
+        metadata,
+        reserved_crate_names,
+        teams,
+        users,
+        version_downloads,
+        versions,
+        interner,
+        cache: _,
+    } = loader;
+
+    read_jsonl(categories, dir, "categories", |_row| {})?;
+    read_jsonl(crate_downloads, dir, "crate_downloads", |_row| {})?;
+    read_jsonl(crate_owners, dir, "crate_owners", |_row| {})?;
+    read_jsonl(crates, dir, "crates", |_row| {})?;
+    read_jsonl(crates_categories, dir, "crates_categories", |_row| {})?;
+    read_jsonl(crates_keywords, dir, "crates_keywords", |_row| {})?;
+    read_jsonl(default_versions, dir, "default_versions", |_row| {})?;
+    read_jsonl(deleted_crates, dir, "deleted_crates", |_row| {})?;
+    read_jsonl(dependencies, dir, "dependencies", |row| {
+        if let Some(interner) = interner {
+            row.intern(interner);
+        }
+    })?;
+    read_jsonl(keywords, dir, "keywords", |_row| {})?;
+    read_jsonl(metadata, dir, "metadata", |_row| {})?;
+    read_jsonl(reserved_crate_names, dir, "reserved_crate_names", |_row| {})?;
+    read_jsonl(teams, dir, "teams", |_row| {})?;
+    read_jsonl(users, dir, "users", |_row| {})?;
+    read_jsonl(version_downloads, dir, "version_downloads", |_row| {})?;
+    read_jsonl(versions, dir, "versions", |row| {
+        if let Some(interner) = interner {
+            row.intern(interner);
+        }
+    })?;
+    Ok(())
+}
+
+fn do_load_cached(path: &Path, cache: &Path, loader: &mut Loader) -> Result<()> {
+    let source = crate::cache::Source::of(path)?;
+    let fresh = source.is_some() && crate::cache::source(cache).ok().flatten() == source;
+    let db = if let Some(Ok((_source, db))) = fresh.then(|| crate::cache::read(cache)) {
+        db
+    } else {
+        let db = do_load_all(path)?;
+        // The cache is only an optimization. Failing to write it, for example
+        // because its directory is read-only, still leaves a loaded dump.
+        let _ = crate::cache::write(cache, &db, source);
+        db
+    };
+    replay(db, loader);
+    Ok(())
+}
+
+fn replay(db: DbDump, loader: &mut Loader) {
+    #[deny(unused_variables)]
+    let Loader {
+        categories,
+        crate_downloads,
+        crate_owners,
+        crates,
+        crates_categories,
+        crates_keywords,
+        default_versions,
+        deleted_crates,
+        dependencies,
+        keywords,
+        metadata,
+        reserved_crate_names,
+        teams,
+        users,
+        version_downloads,
+        versions,
+        interner: _,
+        cache: _,
+    } = loader;
+
+    feed(categories, db.categories);
+    feed(crate_downloads, db.crate_downloads);
+    feed(crate_owners, db.crate_owners);
+    feed(crates, db.crates);
+    feed(crates_categories, db.crates_categories);
+    feed(crates_keywords, db.crates_keywords);
+    feed(default_versions, db.default_versions);
+    feed(deleted_crates, db.deleted_crates);
+    feed(dependencies, db.dependencies);
+    feed(keywords, db.keywords);
+    feed(metadata, [db.metadata]);
+    feed(reserved_crate_names, db.reserved_crate_names);
+    feed(teams, db.teams);
+    feed(users, db.users);
+    feed(version_downloads, &db.version_downloads);
+    feed(versions, db.versions);
+}
+
+fn feed<T>(loader: &mut Option<Callback<T>>, rows: impl IntoIterator<Item = T>) {
+    if let Some(loader) = loader {
+        for row in rows {
+            (loader.f)(row);
+        }
+        loader.done = true;
+    }
+}
+
 pub(crate) trait FromRecord: Sized {
     fn from_record(record: &StringRecord, headers: &StringRecord) -> Result<Self>;
 }
@@ -343,6 +529,31 @@ where
 }
 
 fn read<T>(loader: &mut Option<Callback<T>>, entry: impl Read) -> Result<()>
+where
+    T: FromRecord,
+{
+    read_with(loader, entry, |_record| {})
+}
+
+fn read_interned<T>(
+    loader: &mut Option<Callback<T>>,
+    entry: impl Read,
+    interner: &mut Option<Interner>,
+) -> Result<()>
+where
+    T: FromRecord + Intern,
+{
+    match interner {
+        Some(interner) => read_with(loader, entry, |record| record.intern(interner)),
+        None => read(loader, entry),
+    }
+}
+
+fn read_with<T>(
+    loader: &mut Option<Callback<T>>,
+    entry: impl Read,
+    mut postprocess: impl FnMut(&mut T),
+) -> Result<()>
 where
     T: FromRecord,
 {
@@ -351,7 +562,8 @@ where
         let headers = csv.headers().map_err(err)?.clone();
         let mut record = StringRecord::new();
         while csv.read_record(&mut record).map_err(err)? {
-            let record = T::from_record(&record, &headers)?;
+            let mut record = T::from_record(&record, &headers)?;
+            postprocess(&mut record);
             (loader.f)(record);
         }
         loader.done = true;
@@ -359,6 +571,25 @@ where
     Ok(())
 }
 
+fn read_jsonl<T>(
+    loader: &mut Option<Callback<T>>,
+    dir: &Path,
+    table: &str,
+    mut postprocess: impl FnMut(&mut T),
+) -> Result<()>
+where
+    T: DeserializeOwned,
+{
+    if let Some(loader) = loader {
+        crate::jsonl::read(dir, table, |mut row| {
+            postprocess(&mut row);
+            (loader.f)(row);
+        })?;
+        loader.done = true;
+    }
+    Ok(())
+}
+
 /// Deserialize *everything* in a crates.io DB dump into memory.
 ///
 /// This function is equivalent to the following [`Loader`]-based invocation:
@@ -418,7 +649,7 @@ fn do_load_all(path: &Path) -> Result<DbDump> {
     let mut reserved_crate_names = Vec::new();
     let mut teams = Vec::new();
     let mut users = Vec::new();
-    let mut version_downloads = Vec::new();
+    let mut version_downloads = crate::version_downloads::Table::new();
     let mut versions = Vec::new();
 
     let mut loader = Loader {
@@ -438,6 +669,8 @@ fn do_load_all(path: &Path) -> Result<DbDump> {
         users: Some(Callback::new(|row| users.push(row))),
         version_downloads: Some(Callback::new(|row| version_downloads.push(row))),
         versions: Some(Callback::new(|row| versions.push(row))),
+        interner: None,
+        cache: None,
     };
 
     loader.load(path)?;
@@ -1,9 +1,9 @@
 //! <b style="font-variant:small-caps">metadata.csv</b>
 
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
 /// One row of **metadata.csv**.
-#[derive(Deserialize, Clone, Default, Debug)]
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
 #[serde(deny_unknown_fields)]
 #[non_exhaustive]
 pub struct Row {
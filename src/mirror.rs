@@ -0,0 +1,243 @@
+//! Verification of a local mirror of .crate files against a dump.
+//!
+//! *This module is available only if db-dump is built with the `"mirror"`
+//! feature.*
+//!
+//! [`DbDump::verify_mirror`] walks a directory of files named like
+//! `serde-1.0.0.crate`, in any layout of subdirectories, and checks each one
+//! against the size and SHA-256 checksum recorded in versions.csv.
+//!
+//! ```no_run
+//! fn main() -> db_dump::Result<()> {
+//!     let mut db = db_dump::DbDump::default();
+//!     db_dump::Loader::new()
+//!         .crates(|row| db.crates.push(row))
+//!         .versions(|row| db.versions.push(row))
+//!         .load("./db-dump.tar.gz")?;
+//!
+//!     for problem in db.verify_mirror("/srv/mirror/crates")? {
+//!         println!("{}", problem);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::error::Result;
+use crate::hex::Hex;
+use crate::DbDump;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A disagreement between a mirror and a dump, found by
+/// [`DbDump::verify_mirror`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Problem {
+    /// A version in the dump has no .crate file in the mirror.
+    Missing { name: String, vers: String },
+    /// A .crate file does not correspond to any version in the dump.
+    Extra { path: PathBuf },
+    /// The size of the file differs from the crate_size in the dump. The file
+    /// is not hashed.
+    Size {
+        path: PathBuf,
+        expected: u64,
+        actual: u64,
+    },
+    /// The SHA-256 of the file differs from the checksum in the dump.
+    Checksum {
+        path: PathBuf,
+        expected: [u8; 32],
+        actual: [u8; 32],
+    },
+    /// The file is of a version that is yanked. Its contents are verified
+    /// like any other file, since cargo still downloads yanked versions that
+    /// are in a lockfile.
+    Yanked { path: PathBuf },
+}
+
+impl DbDump {
+    /// Verify every .crate file in the given directory and its
+    /// subdirectories against the versions in this dump.
+    ///
+    /// Files are matched to versions by name, like `serde-1.0.0.crate`. The
+    /// size of each file is compared first, and only files of the expected
+    /// size are hashed. Versions with neither a size nor a checksum in the
+    /// dump are only checked for presence. Problems are returned in order of
+    /// path, followed by the versions missing from the mirror.
+    ///
+    /// Only the crates and versions tables are needed.
+    pub fn verify_mirror(&self, dir: impl AsRef<Path>) -> Result<Vec<Problem>> {
+        let mut names = BTreeMap::new();
+        for krate in &self.crates {
+            names.insert(krate.id, &krate.name);
+        }
+        let mut versions = BTreeMap::new();
+        for version in &self.versions {
+            if let Some(name) = names.get(&version.crate_id) {
+                let file_name = format!("{}-{}.crate", name, version.num);
+                versions.insert(file_name, (*name, version, false));
+            }
+        }
+
+        let mut files = Vec::new();
+        let mut stack = vec![dir.as_ref().to_owned()];
+        while let Some(dir) = stack.pop() {
+            for child in fs::read_dir(dir)? {
+                let child = child?;
+                if child.file_type()?.is_dir() {
+                    stack.push(child.path());
+                } else if child.file_name().to_string_lossy().ends_with(".crate") {
+                    files.push(child.path());
+                }
+            }
+        }
+        files.sort();
+
+        let mut problems = Vec::new();
+        for path in files {
+            let file_name = path.file_name().unwrap().to_string_lossy();
+            let Some((_name, version, found)) = versions.get_mut(&*file_name) else {
+                problems.push(Problem::Extra { path });
+                continue;
+            };
+            *found = true;
+            if version.yanked {
+                problems.push(Problem::Yanked { path: path.clone() });
+            }
+            let actual = fs::metadata(&path)?.len();
+            if let Some(expected) = version.crate_size {
+                if actual != expected {
+                    problems.push(Problem::Size {
+                        path,
+                        expected,
+                        actual,
+                    });
+                    continue;
+                }
+            }
+            if let Some(expected) = version.checksum {
+                let mut sha256 = Sha256::new();
+                io::copy(&mut File::open(&path)?, &mut sha256)?;
+                let actual: [u8; 32] = sha256.finalize().into();
+                if actual != expected {
+                    problems.push(Problem::Checksum {
+                        path,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+
+        for (name, version, found) in versions.into_values() {
+            if !found {
+                problems.push(Problem::Missing {
+                    name: name.clone(),
+                    vers: version.num.to_string(),
+                });
+            }
+        }
+        Ok(problems)
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::Missing { name, vers } => {
+                write!(formatter, "{} {}: missing from mirror", name, vers)
+            }
+            Problem::Extra { path } => {
+                write!(formatter, "{}: not in dump", path.display())
+            }
+            Problem::Size {
+                path,
+                expected,
+                actual,
+            } => write!(
+                formatter,
+                "{}: size {} differs from {} in dump",
+                path.display(),
+                actual,
+                expected,
+            ),
+            Problem::Checksum {
+                path,
+                expected,
+                actual,
+            } => write!(
+                formatter,
+                "{}: checksum {} differs from {} in dump",
+                path.display(),
+                Hex(actual),
+                Hex(expected),
+            ),
+            Problem::Yanked { path } => write!(formatter, "{}: yanked", path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hex::Hex;
+    use crate::testing::DumpBuilder;
+    use sha2::{Digest, Sha256};
+    use std::fs;
+
+    #[test]
+    fn test_verify_mirror() {
+        let mut builder = DumpBuilder::new();
+        let serde = builder.krate("serde").id;
+        let mut add = |num: &str, contents: &[u8], yanked: bool| {
+            let version = builder.version(serde, num);
+            version.checksum = Some(Sha256::digest(contents).into());
+            version.crate_size = Some(contents.len() as u64);
+            version.yanked = yanked;
+        };
+        add("1.0.0", b"good", false);
+        add("1.0.1", b"same size", false);
+        add("1.0.2", b"short", false);
+        add("1.0.3", b"missing", false);
+        add("1.0.4", b"yanked", true);
+        let db = builder.build();
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path();
+        fs::create_dir_all(dir.join("se/rd")).unwrap();
+        fs::write(dir.join("se/rd/serde-1.0.0.crate"), b"good").unwrap();
+        fs::write(dir.join("serde-1.0.1.crate"), b"SAME SIZE").unwrap();
+        fs::write(dir.join("serde-1.0.2.crate"), b"longer").unwrap();
+        fs::write(dir.join("serde-1.0.4.crate"), b"yanked").unwrap();
+        fs::write(dir.join("serde-9.9.9.crate"), b"extra").unwrap();
+        fs::write(dir.join("README"), b"not a crate").unwrap();
+
+        let problems = db.verify_mirror(dir).unwrap();
+        let problems: Vec<String> = problems
+            .iter()
+            .map(|problem| {
+                let problem = problem.to_string();
+                problem.replace(&format!("{}/", dir.display()), "")
+            })
+            .collect();
+        let checksum = |contents: &[u8]| -> String { Hex(&Sha256::digest(contents)).to_string() };
+        assert_eq!(
+            problems,
+            [
+                format!(
+                    "serde-1.0.1.crate: checksum {} differs from {} in dump",
+                    checksum(b"SAME SIZE"),
+                    checksum(b"same size"),
+                ),
+                "serde-1.0.2.crate: size 6 differs from 5 in dump".to_owned(),
+                "serde-1.0.4.crate: yanked".to_owned(),
+                "serde-9.9.9.crate: not in dump".to_owned(),
+                "serde 1.0.3: missing from mirror".to_owned(),
+            ],
+        );
+    }
+}
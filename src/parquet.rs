@@ -0,0 +1,488 @@
+//! Export of a dump into Parquet files, one per table, via Apache Arrow.
+//!
+//! *This module is available only if db-dump is built with the `"parquet"`
+//! feature.*
+//!
+//! Every table of the dump becomes a file `<table>.parquet` with the columns
+//! of the csv file, in the same order, and the Arrow [`schema`] of that table.
+//! The schemas do not depend on the contents of the dump, so files from
+//! different dumps can be read together.
+//!
+//! - Datetimes are `Timestamp(Microsecond, "UTC")` and dates are `Date32`.
+//! - Ids, counts and sizes are unsigned integers of the same width as in
+//!   the Rust types, and booleans are `Boolean`.
+//! - Checksums are `FixedSizeBinary(32)`.
+//! - The version number of versions.csv is kept as a string in `num`,
+//!   followed by the columns `major`, `minor` and `patch` as `UInt64` in
+//!   place of `num_no_build`.
+//!   Requirements and rust_version are strings.
+//! - Features of versions.csv are a `List` of `Struct` with fields
+//!   `feature` (`Utf8`) and `values` (`List<Utf8>`). The other list-valued
+//!   columns, like keywords or the features of dependencies.csv, are
+//!   `List<Utf8>`.
+//! - In crate_owners, `owner_kind` is 0 for a user and 1 for a team, and
+//!   dependency `kind` is 0 for normal, 1 for build and 2 for dev, as in the
+//!   csv files.
+//!
+//! ```no_run
+//! fn main() -> db_dump::Result<()> {
+//!     db_dump::parquet::export("./db-dump.tar.gz", "./parquet")?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ```python
+//! import polars as pl
+//! crates = pl.read_parquet("parquet/crates.parquet")
+//! ```
+
+use crate::columnar::{self, Append, Columns, Kind};
+use crate::error::{err, Error, Result};
+use crate::export::Staging;
+use crate::versions::FeaturesMap;
+use crate::{Date, Loader};
+use ::parquet::arrow::ArrowWriter;
+use ::parquet::basic::Compression;
+use ::parquet::file::properties::WriterProperties;
+use arrow_array::builder::{
+    make_builder, ArrayBuilder, BooleanBuilder, Date32Builder, FixedSizeBinaryBuilder,
+    Int32Builder, ListBuilder, StringBuilder, StructBuilder, TimestampMicrosecondBuilder,
+    UInt16Builder, UInt32Builder, UInt64Builder, UInt8Builder,
+};
+use arrow_array::RecordBatch;
+use arrow_schema::{DataType, Field, Fields, Schema, SchemaRef, TimeUnit};
+use chrono::{DateTime, Utc};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+pub use crate::columnar::TABLES;
+
+// Rows are handed out in batches of this many, which bounds the memory held in
+// Arrow builders. When exporting, the Parquet writer itself buffers up to one
+// row group of encoded data per table.
+const BATCH_SIZE: usize = 8192;
+
+/// The Arrow schema of the given table, or None if there is no table by that
+/// name.
+pub fn schema(table: &str) -> Option<SchemaRef> {
+    let columns = columnar::columns(table);
+    if columns.is_empty() {
+        return None;
+    }
+    let fields: Vec<Field> = columns
+        .iter()
+        .map(|&(name, kind, nullable)| {
+            let data_type = match kind {
+                Kind::UInt8 => DataType::UInt8,
+                Kind::UInt16 => DataType::UInt16,
+                Kind::UInt32 => DataType::UInt32,
+                Kind::UInt64 => DataType::UInt64,
+                Kind::Int32 => DataType::Int32,
+                Kind::Bool => DataType::Boolean,
+                Kind::Str => DataType::Utf8,
+                Kind::Checksum => DataType::FixedSizeBinary(32),
+                Kind::DateTime => timestamp(),
+                Kind::Date => DataType::Date32,
+                Kind::List => strings(),
+                Kind::Features => features(),
+            };
+            Field::new(name, data_type, nullable)
+        })
+        .collect();
+    Some(Arc::new(Schema::new(fields)))
+}
+
+fn timestamp() -> DataType {
+    DataType::Timestamp(TimeUnit::Microsecond, Some(Arc::from("UTC")))
+}
+
+fn strings() -> DataType {
+    DataType::List(Arc::new(Field::new_list_field(DataType::Utf8, false)))
+}
+
+fn features() -> DataType {
+    let entry = Fields::from(vec![
+        Field::new("feature", DataType::Utf8, false),
+        Field::new("values", strings(), false),
+    ]);
+    DataType::List(Arc::new(Field::new_list_field(
+        DataType::Struct(entry),
+        false,
+    )))
+}
+
+/// Convert the db-dump.tar.gz at the given path into one Parquet file per
+/// table in the given directory.
+///
+/// The directory is created if it does not exist. Returns an error if any of
+/// the files already exists. Rows are streamed from [`Loader`] into the files
+/// in batches, so the dump is never held in memory. Every table gets a file,
+/// even if it is absent from the dump.
+///
+/// The files are written into a temporary directory inside the given one and
+/// moved into place once they are all complete. If the export fails, none of
+/// them are left behind.
+pub fn export(dump: impl AsRef<Path>, dir: impl AsRef<Path>) -> Result<()> {
+    let mut staging = Staging::new(dir.as_ref())?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writers = BTreeMap::new();
+    for table in TABLES {
+        let file = staging.create(format!("{}.parquet", table))?;
+        let writer = ArrowWriter::try_new(file, schema(table).unwrap(), Some(properties.clone()))?;
+        writers.insert(table, writer);
+    }
+    record_batches(dump, &TABLES, |table, batch| {
+        writers.get_mut(table).unwrap().write(&batch)?;
+        Ok(())
+    })?;
+    for writer in writers.into_values() {
+        writer.close()?;
+    }
+    staging.persist()
+}
+
+/// Stream the rows of the given tables of a db-dump.tar.gz as Arrow record
+/// batches.
+///
+/// Each batch has the [`schema`] of its table and holds up to 8192 rows.
+/// Batches of different tables may be interleaved, and tables that are not
+/// listed are not parsed at all. If the callback returns an error, the
+/// remaining rows are skipped and the error is returned.
+///
+/// ```no_run
+/// fn main() -> db_dump::Result<()> {
+///     let mut rows = 0;
+///     db_dump::parquet::record_batches("./db-dump.tar.gz", &["crates"], |_table, batch| {
+///         rows += batch.num_rows();
+///         Ok(())
+///     })?;
+///     println!("{} crates", rows);
+///     Ok(())
+/// }
+/// ```
+pub fn record_batches(
+    dump: impl AsRef<Path>,
+    tables: &[&str],
+    mut f: impl FnMut(&'static str, RecordBatch) -> Result<()>,
+) -> Result<()> {
+    let mut selected: [Option<Table>; TABLES.len()] = Default::default();
+    for table in tables {
+        let Some(i) = TABLES.iter().position(|name| name == table) else {
+            return Err(err(format_args!("no table named {}", table)));
+        };
+        selected[i] = Some(Table::new(TABLES[i]));
+    }
+    let sink = Sink {
+        f: RefCell::new(&mut f),
+        error: RefCell::new(None),
+    };
+
+    let mut loader = Loader::new();
+    macro_rules! tables {
+        ($($table:ident)*) => {
+            let [$($table),*] = &mut selected;
+            $(
+                if let Some(table) = $table {
+                    loader.$table(|row| table.append(&sink, &row));
+                }
+            )*
+        };
+    }
+    tables! {
+        categories
+        crate_downloads
+        crate_owners
+        crates
+        crates_categories
+        crates_keywords
+        default_versions
+        deleted_crates
+        dependencies
+        keywords
+        metadata
+        reserved_crate_names
+        teams
+        users
+        version_downloads
+        versions
+    }
+    loader.load(dump)?;
+    drop(loader);
+
+    for table in selected.iter_mut().flatten() {
+        if !table.builders[0].is_empty() && sink.error.borrow().is_none() {
+            sink.send(table);
+        }
+    }
+    match sink.error.into_inner() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+// Receives the finished batches of every table.
+struct Sink<'a> {
+    f: RefCell<&'a mut dyn FnMut(&'static str, RecordBatch) -> Result<()>>,
+    // Loader callbacks cannot return errors, so the first one is held here
+    // and the remaining rows are skipped.
+    error: RefCell<Option<Error>>,
+}
+
+impl Sink<'_> {
+    fn send(&self, table: &mut Table) {
+        let result = table
+            .finish()
+            .map_err(Error::from)
+            .and_then(|batch| (self.f.borrow_mut())(table.name, batch));
+        if let Err(e) = result {
+            *self.error.borrow_mut() = Some(e);
+        }
+    }
+}
+
+struct Table {
+    name: &'static str,
+    schema: SchemaRef,
+    builders: Vec<Box<dyn ArrayBuilder>>,
+}
+
+impl Table {
+    fn new(name: &'static str) -> Self {
+        let schema = schema(name).unwrap();
+        let builders = schema
+            .fields()
+            .iter()
+            .map(|field| make_builder(field.data_type(), BATCH_SIZE))
+            .collect();
+        Table {
+            name,
+            schema,
+            builders,
+        }
+    }
+
+    fn append(&mut self, sink: &Sink, row: &impl Append) {
+        if sink.error.borrow().is_some() {
+            return;
+        }
+        row.append(&mut Builders {
+            builders: &mut self.builders,
+            next: 0,
+        });
+        if self.builders[0].len() >= BATCH_SIZE {
+            sink.send(self);
+        }
+    }
+
+    fn finish(&mut self) -> ::parquet::errors::Result<RecordBatch> {
+        let columns = self.builders.iter_mut().map(ArrayBuilder::finish).collect();
+        Ok(RecordBatch::try_new(Arc::clone(&self.schema), columns)?)
+    }
+}
+
+struct Builders<'a> {
+    builders: &'a mut [Box<dyn ArrayBuilder>],
+    next: usize,
+}
+
+impl Builders<'_> {
+    fn next<B: ArrayBuilder>(&mut self) -> &mut B {
+        let builder = &mut self.builders[self.next];
+        self.next += 1;
+        builder.as_any_mut().downcast_mut().unwrap()
+    }
+}
+
+impl Columns for Builders<'_> {
+    fn u8(&mut self, value: u8) {
+        self.next::<UInt8Builder>().append_value(value);
+    }
+
+    fn u16(&mut self, value: impl Into<Option<u16>>) {
+        self.next::<UInt16Builder>().append_option(value.into());
+    }
+
+    fn u32(&mut self, value: impl Into<Option<u32>>) {
+        self.next::<UInt32Builder>().append_option(value.into());
+    }
+
+    fn u64(&mut self, value: impl Into<Option<u64>>) {
+        self.next::<UInt64Builder>().append_option(value.into());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.next::<Int32Builder>().append_value(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.next::<BooleanBuilder>().append_value(value);
+    }
+
+    fn opt_str(&mut self, value: Option<&str>) {
+        self.next::<StringBuilder>().append_option(value);
+    }
+
+    fn datetime(&mut self, value: DateTime<Utc>) {
+        self.next::<TimestampMicrosecondBuilder>()
+            .append_value(value.timestamp_micros());
+    }
+
+    fn date(&mut self, value: Date<Utc>) {
+        self.next::<Date32Builder>()
+            .append_value(columnar::days_from_epoch(value));
+    }
+
+    fn checksum(&mut self, value: Option<&[u8; 32]>) {
+        let builder = self.next::<FixedSizeBinaryBuilder>();
+        match value {
+            // Cannot fail, the length matches the schema.
+            Some(checksum) => builder.append_value(checksum).unwrap(),
+            None => builder.append_null(),
+        }
+    }
+
+    fn strings(&mut self, values: &[impl AsRef<str>]) {
+        append_strings(self.next(), values);
+    }
+
+    fn features(&mut self, features: &FeaturesMap) {
+        let list = self.next::<ListBuilder<Box<dyn ArrayBuilder>>>();
+        let entries: &mut StructBuilder = list.values().as_any_mut().downcast_mut().unwrap();
+        for (feature, values) in features {
+            entries
+                .field_builder::<StringBuilder>(0)
+                .unwrap()
+                .append_value(feature);
+            append_strings(entries.field_builder(1).unwrap(), values);
+            entries.append(true);
+        }
+        list.append(true);
+    }
+}
+
+fn append_strings(list: &mut ListBuilder<Box<dyn ArrayBuilder>>, values: &[impl AsRef<str>]) {
+    let strings: &mut StringBuilder = list.values().as_any_mut().downcast_mut().unwrap();
+    for value in values {
+        strings.append_value(value);
+    }
+    list.append(true);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::DumpBuilder;
+    use crate::Date;
+    use ::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Date32Type, UInt64Type};
+    use arrow_array::RecordBatch;
+    use std::fs::{self, File};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    fn read(dir: &Path, table: &str) -> RecordBatch {
+        let file = File::open(dir.join(table).with_extension("parquet")).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(*batches[0].schema(), *super::schema(table).unwrap());
+        batches.remove(0)
+    }
+
+    #[test]
+    fn test_export() {
+        let mut builder = DumpBuilder::new();
+        let serde = builder.krate("serde").id;
+        let serde_v1 = builder.version(serde, "1.2.3");
+        serde_v1.checksum = Some([0xab; 32]);
+        serde_v1
+            .features
+            .insert(Arc::from("default"), vec![Arc::from("std")]);
+        serde_v1.keywords = vec![Arc::from("serde")];
+        let serde_v1 = serde_v1.id;
+        builder.download_series(serde_v1, Date::from_ymd(2024, 1, 1), &[3, 4]);
+        let json = builder.krate("serde_json").id;
+        let json_v1 = builder.version(json, "1.0.0-rc.1").id;
+        builder.dependency(json_v1, serde, "^1.0").features = vec![Arc::from("derive")];
+        let archive = builder.temp_archive().unwrap();
+
+        let dir = archive.path().with_extension("parquet");
+        super::export(&archive, &dir).unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), super::TABLES.len());
+
+        let versions = read(&dir, "versions");
+        assert_eq!(versions.num_rows(), 2);
+        let num = versions.column_by_name("num").unwrap().as_string::<i32>();
+        assert_eq!(num.value(1), "1.0.0-rc.1");
+        let minor = versions.column_by_name("minor").unwrap();
+        assert_eq!(minor.as_primitive::<UInt64Type>().value(0), 2);
+        let features = versions
+            .column_by_name("features")
+            .unwrap()
+            .as_list::<i32>();
+        let entries = features.value(0);
+        let entries = entries.as_struct();
+        assert_eq!(entries.column(0).as_string::<i32>().value(0), "default");
+        let values = entries.column(1).as_list::<i32>().value(0);
+        assert_eq!(values.as_string::<i32>().value(0), "std");
+        let checksum = versions.column_by_name("checksum").unwrap();
+        assert_eq!(checksum.as_fixed_size_binary().value(0), [0xab; 32]);
+
+        let downloads = read(&dir, "version_downloads");
+        let date = downloads.column_by_name("date").unwrap();
+        let date = date.as_primitive::<Date32Type>().value_as_date(1).unwrap();
+        assert_eq!(date.to_string(), "2024-01-02");
+
+        let dependencies = read(&dir, "dependencies");
+        let features = dependencies.column_by_name("features").unwrap();
+        let features = features.as_list::<i32>().value(0);
+        assert_eq!(features.as_string::<i32>().value(0), "derive");
+
+        // The files already exist.
+        assert!(super::export(&archive, &dir).is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), super::TABLES.len());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_failure() {
+        let archive = DumpBuilder::new().temp_archive().unwrap();
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path();
+
+        // Nothing is left behind by an export that fails partway, so it can
+        // be rerun.
+        let missing = dir.join("missing.tar.gz");
+        assert!(super::export(missing, dir).is_err());
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 0);
+        super::export(&archive, dir).unwrap();
+        assert_eq!(fs::read_dir(dir).unwrap().count(), super::TABLES.len());
+    }
+
+    #[test]
+    fn test_record_batches() {
+        let mut builder = DumpBuilder::new();
+        let serde = builder.krate("serde").id;
+        builder.version(serde, "1.0.0");
+        builder.version(serde, "1.0.1");
+        let archive = builder.temp_archive().unwrap();
+
+        let mut batches = Vec::new();
+        super::record_batches(&archive, &["versions"], |table, batch| {
+            batches.push((table, batch.num_rows()));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(batches, [("versions", 2)]);
+
+        let result = super::record_batches(&archive, &["version"], |_, _| Ok(()));
+        assert_eq!(result.unwrap_err().to_string(), "no table named version");
+    }
+}
@@ -0,0 +1,448 @@
+//! Loading of tables of a dump into polars DataFrames.
+//!
+//! *This module is available only if db-dump is built with the `"polars"`
+//! feature.*
+//!
+//! Rows are streamed from [`db_dump::Loader`] directly into the columns of the
+//! DataFrames, so a table like version_downloads never exists in memory as a
+//! `Vec` of rows. The columns are those of the csv file, in the same order,
+//! with these dtypes:
+//!
+//! - Datetimes are `Datetime(Microseconds)` without a time zone, like in the
+//!   csv, but the values are in UTC. Dates are `Date`.
+//! - Ids, counts and sizes are unsigned integers of the same width as in
+//!   the Rust types, and booleans are `Boolean`.
+//! - Checksums are `Binary`, 32 bytes long.
+//! - The version number of versions.csv is kept as a string in `num`,
+//!   followed by the columns `major`, `minor` and `patch` as `UInt64` in
+//!   place of `num_no_build`.
+//!   Requirements and rust_version are strings.
+//! - Features of versions.csv are a `List` of `Struct` with fields
+//!   `feature` (`String`) and `values` (`List(String)`). The other
+//!   list-valued columns, like keywords or the features of dependencies.csv,
+//!   are `List(String)`.
+//! - In crate_owners, `owner_kind` is 0 for a user and 1 for a team, and
+//!   dependency `kind` is 0 for normal, 1 for build and 2 for dev, as in the
+//!   csv files.
+//!
+//! These are the same as the Arrow types of the Parquet export in
+//! `db_dump::parquet`, except for the time zone of datetimes and for
+//! checksums.
+//!
+//! ```no_run
+//! fn main() -> db_dump::Result<()> {
+//!     let frames = db_dump::polars::Loader::new()
+//!         .crates()
+//!         .version_downloads()
+//!         .load("./db-dump.tar.gz")?;
+//!
+//!     let version_downloads = frames.version_downloads.unwrap();
+//!     println!("{}", version_downloads.head(Some(10)));
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`db_dump::Loader`]: crate::Loader
+
+use crate::columnar::{self, Append, Columns, Kind};
+use crate::error::Result;
+use crate::versions::FeaturesMap;
+use crate::Date;
+use ::polars::chunked_array::builder::AnonymousOwnedListBuilder;
+use ::polars::prelude::{
+    BinaryChunkedBuilder, BooleanChunkedBuilder, ChunkedBuilder, Column as PolarsColumn, DataFrame,
+    DataType, Field, Int32Type, Int64Type, IntoSeries, ListBuilderTrait, ListStringChunkedBuilder,
+    PlSmallStr, PolarsResult, PrimitiveChunkedBuilder, Series, StringChunkedBuilder, StructChunked,
+    TimeUnit, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use chrono::{DateTime, Utc};
+use std::cell::RefCell;
+use std::path::Path;
+
+macro_rules! tables {
+    ($($table:ident)*) => {
+        /// Selection of the tables to load into DataFrames.
+        ///
+        /// Unlike [`db_dump::Loader`][crate::Loader], this takes no callbacks;
+        /// each selected table becomes one DataFrame in the [`Frames`]
+        /// returned by [`load`][Loader::load].
+        #[derive(Default)]
+        pub struct Loader {
+            $(
+                $table: bool,
+            )*
+        }
+
+        /// DataFrames loaded by [`Loader`], one for each selected table.
+        #[derive(Default)]
+        #[non_exhaustive]
+        pub struct Frames {
+            $(
+                pub $table: Option<DataFrame>,
+            )*
+        }
+
+        impl Loader {
+            pub fn new() -> Self {
+                Loader::default()
+            }
+
+            $(
+                #[doc = concat!("Load **", stringify!($table), ".csv** into a DataFrame.")]
+                pub fn $table(&mut self) -> &mut Self {
+                    self.$table = true;
+                    self
+                }
+            )*
+
+            /// Load the selected tables of the db-dump.tar.gz at the given
+            /// path.
+            pub fn load(&self, path: impl AsRef<Path>) -> Result<Frames> {
+                $(
+                    let $table = self
+                        .$table
+                        .then(|| RefCell::new(Table::new(stringify!($table))));
+                )*
+
+                let mut loader = crate::Loader::new();
+                $(
+                    if let Some(table) = &$table {
+                        loader.$table(|row| table.borrow_mut().append(&row));
+                    }
+                )*
+                loader.load(path)?;
+                drop(loader);
+
+                let mut frames = Frames::default();
+                $(
+                    if let Some(table) = $table {
+                        frames.$table = Some(table.into_inner().finish()?);
+                    }
+                )*
+                Ok(frames)
+            }
+        }
+    };
+}
+
+tables! {
+    categories
+    crate_downloads
+    crate_owners
+    crates
+    crates_categories
+    crates_keywords
+    default_versions
+    deleted_crates
+    dependencies
+    keywords
+    metadata
+    reserved_crate_names
+    teams
+    users
+    version_downloads
+    versions
+}
+
+struct Table {
+    builders: Vec<Builder>,
+    rows: usize,
+}
+
+impl Table {
+    fn new(table: &str) -> Self {
+        let builders = columnar::columns(table)
+            .iter()
+            .map(|&(name, kind, _nullable)| Builder::new(PlSmallStr::from_static(name), kind))
+            .collect();
+        Table { builders, rows: 0 }
+    }
+
+    fn append(&mut self, row: &impl Append) {
+        row.append(&mut Builders {
+            builders: &mut self.builders,
+            next: 0,
+        });
+        self.rows += 1;
+    }
+
+    fn finish(self) -> PolarsResult<DataFrame> {
+        let columns = self
+            .builders
+            .into_iter()
+            .map(|builder| builder.finish().map(PolarsColumn::from))
+            .collect::<PolarsResult<_>>()?;
+        DataFrame::new(self.rows, columns)
+    }
+}
+
+enum Builder {
+    UInt8(PrimitiveChunkedBuilder<UInt8Type>),
+    UInt16(PrimitiveChunkedBuilder<UInt16Type>),
+    UInt32(PrimitiveChunkedBuilder<UInt32Type>),
+    UInt64(PrimitiveChunkedBuilder<UInt64Type>),
+    Int32(PrimitiveChunkedBuilder<Int32Type>),
+    Bool(BooleanChunkedBuilder),
+    Str(StringChunkedBuilder),
+    Binary(BinaryChunkedBuilder),
+    DateTime(PrimitiveChunkedBuilder<Int64Type>),
+    Date(PrimitiveChunkedBuilder<Int32Type>),
+    List(ListStringChunkedBuilder),
+    Features(Box<Features>),
+}
+
+// The features of all versions, as one flat list of entries, and the number
+// of entries belonging to each version.
+struct Features {
+    name: PlSmallStr,
+    names: StringChunkedBuilder,
+    values: ListStringChunkedBuilder,
+    lens: Vec<usize>,
+}
+
+impl Builder {
+    fn new(name: PlSmallStr, kind: Kind) -> Self {
+        match kind {
+            Kind::UInt8 => Builder::UInt8(PrimitiveChunkedBuilder::new(name, 0)),
+            Kind::UInt16 => Builder::UInt16(PrimitiveChunkedBuilder::new(name, 0)),
+            Kind::UInt32 => Builder::UInt32(PrimitiveChunkedBuilder::new(name, 0)),
+            Kind::UInt64 => Builder::UInt64(PrimitiveChunkedBuilder::new(name, 0)),
+            Kind::Int32 => Builder::Int32(PrimitiveChunkedBuilder::new(name, 0)),
+            Kind::Bool => Builder::Bool(BooleanChunkedBuilder::new(name, 0)),
+            Kind::Str => Builder::Str(StringChunkedBuilder::new(name, 0)),
+            Kind::Checksum => Builder::Binary(BinaryChunkedBuilder::new(name, 0)),
+            Kind::DateTime => Builder::DateTime(PrimitiveChunkedBuilder::new(name, 0)),
+            Kind::Date => Builder::Date(PrimitiveChunkedBuilder::new(name, 0)),
+            Kind::List => Builder::List(ListStringChunkedBuilder::new(name, 0, 0)),
+            Kind::Features => Builder::Features(Box::new(Features {
+                name,
+                names: StringChunkedBuilder::new(PlSmallStr::from_static("feature"), 0),
+                values: ListStringChunkedBuilder::new(PlSmallStr::from_static("values"), 0, 0),
+                lens: Vec::new(),
+            })),
+        }
+    }
+
+    fn finish(self) -> PolarsResult<Series> {
+        Ok(match self {
+            Builder::UInt8(builder) => builder.finish().into_series(),
+            Builder::UInt16(builder) => builder.finish().into_series(),
+            Builder::UInt32(builder) => builder.finish().into_series(),
+            Builder::UInt64(builder) => builder.finish().into_series(),
+            Builder::Int32(builder) => builder.finish().into_series(),
+            Builder::Bool(builder) => builder.finish().into_series(),
+            Builder::Str(builder) => builder.finish().into_series(),
+            Builder::Binary(builder) => builder.finish().into_series(),
+            Builder::DateTime(builder) => builder
+                .finish()
+                .into_datetime(TimeUnit::Microseconds, None)
+                .into_series(),
+            Builder::Date(builder) => builder.finish().into_date().into_series(),
+            Builder::List(mut builder) => builder.finish().into_series(),
+            Builder::Features(mut features) => {
+                let fields = [
+                    features.names.finish().into_series(),
+                    features.values.finish().into_series(),
+                ];
+                let len = fields[0].len();
+                let entries = StructChunked::from_series(
+                    PlSmallStr::from_static("entries"),
+                    len,
+                    fields.iter(),
+                )?
+                .into_series();
+                let dtype = DataType::Struct(vec![
+                    Field::new(PlSmallStr::from_static("feature"), DataType::String),
+                    Field::new(
+                        PlSmallStr::from_static("values"),
+                        DataType::List(Box::new(DataType::String)),
+                    ),
+                ]);
+                let mut list =
+                    AnonymousOwnedListBuilder::new(features.name, features.lens.len(), Some(dtype));
+                let mut offset = 0;
+                for len in features.lens {
+                    if len == 0 {
+                        list.append_empty();
+                    } else {
+                        list.append_owned_series(
+                            entries.slice(i64::try_from(offset).unwrap(), len),
+                        )?;
+                    }
+                    offset += len;
+                }
+                list.finish().into_series()
+            }
+        })
+    }
+}
+
+struct Builders<'a> {
+    builders: &'a mut [Builder],
+    next: usize,
+}
+
+impl Builders<'_> {
+    fn next(&mut self) -> &mut Builder {
+        let builder = &mut self.builders[self.next];
+        self.next += 1;
+        builder
+    }
+}
+
+impl Columns for Builders<'_> {
+    fn u8(&mut self, value: u8) {
+        let Builder::UInt8(builder) = self.next() else {
+            unreachable!();
+        };
+        builder.append_value(value);
+    }
+
+    fn u16(&mut self, value: impl Into<Option<u16>>) {
+        let Builder::UInt16(builder) = self.next() else {
+            unreachable!();
+        };
+        builder.append_option(value.into());
+    }
+
+    fn u32(&mut self, value: impl Into<Option<u32>>) {
+        let Builder::UInt32(builder) = self.next() else {
+            unreachable!();
+        };
+        builder.append_option(value.into());
+    }
+
+    fn u64(&mut self, value: impl Into<Option<u64>>) {
+        let Builder::UInt64(builder) = self.next() else {
+            unreachable!();
+        };
+        builder.append_option(value.into());
+    }
+
+    fn i32(&mut self, value: i32) {
+        let Builder::Int32(builder) = self.next() else {
+            unreachable!();
+        };
+        builder.append_value(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        let Builder::Bool(builder) = self.next() else {
+            unreachable!();
+        };
+        builder.append_value(value);
+    }
+
+    fn opt_str(&mut self, value: Option<&str>) {
+        let Builder::Str(builder) = self.next() else {
+            unreachable!();
+        };
+        builder.append_option(value);
+    }
+
+    fn checksum(&mut self, value: Option<&[u8; 32]>) {
+        let Builder::Binary(builder) = self.next() else {
+            unreachable!();
+        };
+        builder.append_option(value);
+    }
+
+    fn datetime(&mut self, value: DateTime<Utc>) {
+        let Builder::DateTime(builder) = self.next() else {
+            unreachable!();
+        };
+        builder.append_value(value.timestamp_micros());
+    }
+
+    fn date(&mut self, value: Date<Utc>) {
+        let Builder::Date(builder) = self.next() else {
+            unreachable!();
+        };
+        builder.append_value(columnar::days_from_epoch(value));
+    }
+
+    fn strings(&mut self, values: &[impl AsRef<str>]) {
+        let Builder::List(builder) = self.next() else {
+            unreachable!();
+        };
+        builder.append_values_iter(values.iter().map(AsRef::as_ref));
+    }
+
+    fn features(&mut self, features: &FeaturesMap) {
+        let Builder::Features(builder) = self.next() else {
+            unreachable!();
+        };
+        for (feature, values) in features {
+            builder.names.append_value(feature);
+            builder
+                .values
+                .append_values_iter(values.iter().map(AsRef::as_ref));
+        }
+        builder.lens.push(features.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Loader;
+    use crate::testing::DumpBuilder;
+    use crate::Date;
+    use polars::prelude::{AnyValue, ChunkAgg, DataType, TimeUnit};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_load() {
+        let mut builder = DumpBuilder::new();
+        let serde = builder.krate("serde").id;
+        let serde_v1 = builder.version(serde, "1.2.3");
+        serde_v1.checksum = Some([0xab; 32]);
+        serde_v1
+            .features
+            .insert(Arc::from("default"), vec![Arc::from("std")]);
+        serde_v1.features.insert(Arc::from("std"), Vec::new());
+        serde_v1.keywords = vec![Arc::from("serde")];
+        let serde_v1 = serde_v1.id;
+        builder.download_series(serde_v1, Date::from_ymd(2024, 1, 1), &[3, 4]);
+        let json = builder.krate("serde_json").id;
+        builder.version(json, "1.0.0-rc.1");
+        let archive = builder.temp_archive().unwrap();
+
+        let frames = Loader::new()
+            .versions()
+            .version_downloads()
+            .load(&archive)
+            .unwrap();
+        assert!(frames.crates.is_none());
+
+        let versions = frames.versions.unwrap();
+        assert_eq!(versions.height(), 2);
+        let column = |name: &str| versions.column(name).unwrap().clone();
+        assert_eq!(
+            column("num").get(1).unwrap(),
+            AnyValue::String("1.0.0-rc.1")
+        );
+        assert_eq!(column("minor").get(0).unwrap(), AnyValue::UInt64(2));
+        assert_eq!(
+            *column("created_at").dtype(),
+            DataType::Datetime(TimeUnit::Microseconds, None),
+        );
+        let features = column("features");
+        let features = features.list().unwrap();
+        let entries = features.get_as_series(0).unwrap();
+        let entries = entries.struct_().unwrap();
+        assert_eq!(entries.len(), 2);
+        let values = entries.field_by_name("values").unwrap();
+        let values = values.list().unwrap().get_as_series(0).unwrap();
+        assert_eq!(values.get(0).unwrap(), AnyValue::String("std"));
+        assert_eq!(features.get_as_series(1).unwrap().len(), 0);
+        let checksum = column("checksum");
+        assert_eq!(checksum.get(0).unwrap(), AnyValue::Binary(&[0xab; 32]));
+        assert!(checksum.get(1).unwrap().is_null());
+
+        let downloads = frames.version_downloads.unwrap();
+        assert_eq!(downloads.height(), 2);
+        assert_eq!(*downloads.column("date").unwrap().dtype(), DataType::Date);
+        let total = downloads.column("downloads").unwrap().u64().unwrap().sum();
+        assert_eq!(total, Some(7));
+    }
+}
@@ -0,0 +1,376 @@
+use crate::crate_owners::OwnerId;
+use crate::crates::CrateId;
+use crate::teams::TeamId;
+use crate::users::UserId;
+use crate::versions::VersionId;
+use crate::Index;
+use std::fmt::{self, Debug};
+use std::ops::Deref;
+
+/// A row of **crates.csv**, together with the index needed to follow its
+/// relations to other tables.
+///
+/// Dereferences to [`crates::Row`](crate::crates::Row).
+///
+/// ```no_run
+/// fn main() -> db_dump::Result<()> {
+///     let db = db_dump::load_all("./db-dump.tar.gz")?;
+///     let index = db.index();
+///
+///     let krate = index.krate_by_name("serde").unwrap();
+///     for owner in krate.owners() {
+///         println!("owner: {}", owner.login());
+///     }
+///     for version in krate.versions() {
+///         if let Some(publisher) = version.publisher() {
+///             println!("{} published by {}", version.num, publisher.gh_login);
+///         }
+///         for dep in version.dependencies() {
+///             if let Some(dep_crate) = dep.krate() {
+///                 println!("  depends on {} {}", dep_crate.name, dep.req);
+///             }
+///         }
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Copy, Clone)]
+pub struct CrateRef<'a> {
+    index: &'a Index<'a>,
+    row: &'a crate::crates::Row,
+}
+
+/// A row of **versions.csv**, together with the index needed to follow its
+/// relations to other tables.
+///
+/// Dereferences to [`versions::Row`](crate::versions::Row).
+#[derive(Copy, Clone)]
+pub struct VersionRef<'a> {
+    index: &'a Index<'a>,
+    row: &'a crate::versions::Row,
+}
+
+/// A row of **dependencies.csv**, together with the index needed to follow
+/// its relations to other tables.
+///
+/// Dereferences to [`dependencies::Row`](crate::dependencies::Row).
+#[derive(Copy, Clone)]
+pub struct DependencyRef<'a> {
+    index: &'a Index<'a>,
+    row: &'a crate::dependencies::Row,
+}
+
+/// A row of **users.csv**, together with the index needed to follow its
+/// relations to other tables.
+///
+/// Dereferences to [`users::Row`](crate::users::Row).
+#[derive(Copy, Clone)]
+pub struct UserRef<'a> {
+    index: &'a Index<'a>,
+    row: &'a crate::users::Row,
+}
+
+/// A row of **teams.csv**, together with the index needed to follow its
+/// relations to other tables.
+///
+/// Dereferences to [`teams::Row`](crate::teams::Row).
+#[derive(Copy, Clone)]
+pub struct TeamRef<'a> {
+    index: &'a Index<'a>,
+    row: &'a crate::teams::Row,
+}
+
+/// A row of **deleted_crates.csv**, together with the index needed to follow
+/// its relations to other tables.
+///
+/// Dereferences to [`deleted_crates::Row`](crate::deleted_crates::Row).
+#[derive(Copy, Clone)]
+pub struct DeletedCrateRef<'a> {
+    index: &'a Index<'a>,
+    row: &'a crate::deleted_crates::Row,
+}
+
+/// The owner referred to by a row of **crate_owners.csv**.
+#[derive(Copy, Clone, Debug)]
+pub enum OwnerRef<'a> {
+    User(UserRef<'a>),
+    Team(TeamRef<'a>),
+}
+
+impl<'a> Index<'a> {
+    pub fn krate(&self, id: CrateId) -> Option<CrateRef<'_>> {
+        let row = self.crate_by_id(id)?;
+        Some(CrateRef { index: self, row })
+    }
+
+    pub fn krate_by_name(&self, name: &str) -> Option<CrateRef<'_>> {
+        let row = self.crate_by_name(name)?;
+        Some(CrateRef { index: self, row })
+    }
+
+    pub fn version(&self, id: VersionId) -> Option<VersionRef<'_>> {
+        let row = self.version_by_id(id)?;
+        Some(VersionRef { index: self, row })
+    }
+
+    pub fn user(&self, id: UserId) -> Option<UserRef<'_>> {
+        let row = self.user_by_id(id)?;
+        Some(UserRef { index: self, row })
+    }
+
+    pub fn team(&self, id: TeamId) -> Option<TeamRef<'_>> {
+        let row = self.team_by_id(id)?;
+        Some(TeamRef { index: self, row })
+    }
+
+    pub fn owner(&self, id: OwnerId) -> Option<OwnerRef<'_>> {
+        match id {
+            OwnerId::User(user_id) => self.user(user_id).map(OwnerRef::User),
+            OwnerId::Team(team_id) => self.team(team_id).map(OwnerRef::Team),
+        }
+    }
+
+    /// Every row of crates.csv.
+    pub fn crates(&self) -> impl Iterator<Item = CrateRef<'_>> + '_ {
+        let index: &Index = self;
+        let dump = index.dump();
+        dump.crates.iter().map(move |row| CrateRef { index, row })
+    }
+
+    /// Every row of deleted_crates.csv.
+    pub fn deleted_crates(&self) -> impl Iterator<Item = DeletedCrateRef<'_>> + '_ {
+        let index: &Index = self;
+        let dump = index.dump();
+        dump.deleted_crates
+            .iter()
+            .map(move |row| DeletedCrateRef { index, row })
+    }
+}
+
+impl<'a> CrateRef<'a> {
+    pub fn row(self) -> &'a crate::crates::Row {
+        self.row
+    }
+
+    pub fn versions(self) -> impl Iterator<Item = VersionRef<'a>> + 'a {
+        let index = self.index;
+        index
+            .versions_of(self.row.id)
+            .map(move |row| VersionRef { index, row })
+    }
+
+    /// The users and teams that own this crate. Owners that do not appear in
+    /// users.csv or teams.csv are skipped.
+    pub fn owners(self) -> impl Iterator<Item = OwnerRef<'a>> + 'a {
+        let index = self.index;
+        index
+            .owners_of(self.row.id)
+            .filter_map(move |row| index.owner(row.owner_id))
+    }
+
+    pub fn keywords(self) -> crate::Rows<'a, crate::keywords::Row> {
+        self.index.keywords_of(self.row.id)
+    }
+
+    pub fn categories(self) -> crate::Rows<'a, crate::categories::Row> {
+        self.index.categories_of(self.row.id)
+    }
+}
+
+impl<'a> VersionRef<'a> {
+    pub fn row(self) -> &'a crate::versions::Row {
+        self.row
+    }
+
+    /// The crate of which this is a version.
+    pub fn krate(self) -> Option<CrateRef<'a>> {
+        self.index.krate(self.row.crate_id)
+    }
+
+    pub fn dependencies(self) -> impl Iterator<Item = DependencyRef<'a>> + 'a {
+        let index = self.index;
+        index
+            .dependencies_of(self.row.id)
+            .map(move |row| DependencyRef { index, row })
+    }
+
+    /// The user who published this version, if recorded.
+    pub fn publisher(self) -> Option<UserRef<'a>> {
+        self.index.user(self.row.published_by?)
+    }
+}
+
+impl<'a> DependencyRef<'a> {
+    pub fn row(self) -> &'a crate::dependencies::Row {
+        self.row
+    }
+
+    /// The version that declares this dependency.
+    pub fn version(self) -> Option<VersionRef<'a>> {
+        self.index.version(self.row.version_id)
+    }
+
+    /// The crate being depended upon.
+    pub fn krate(self) -> Option<CrateRef<'a>> {
+        self.index.krate(self.row.crate_id)
+    }
+}
+
+impl<'a> UserRef<'a> {
+    pub fn row(self) -> &'a crate::users::Row {
+        self.row
+    }
+
+    /// The crates directly owned by this user, not counting ones owned through
+    /// a team.
+    pub fn crates(self) -> impl Iterator<Item = CrateRef<'a>> + 'a {
+        let index = self.index;
+        index
+            .crates_owned_by(self.row.id)
+            .map(move |row| CrateRef { index, row })
+    }
+}
+
+impl<'a> TeamRef<'a> {
+    pub fn row(self) -> &'a crate::teams::Row {
+        self.row
+    }
+
+    pub fn crates(self) -> impl Iterator<Item = CrateRef<'a>> + 'a {
+        let index = self.index;
+        index
+            .crates_owned_by(self.row.id)
+            .map(move |row| CrateRef { index, row })
+    }
+}
+
+impl<'a> DeletedCrateRef<'a> {
+    pub fn row(self) -> &'a crate::deleted_crates::Row {
+        self.row
+    }
+
+    /// The user who deleted the crate, if recorded.
+    pub fn deleted_by(self) -> Option<UserRef<'a>> {
+        self.index.user(self.row.deleted_by?)
+    }
+}
+
+impl<'a> OwnerRef<'a> {
+    pub fn id(self) -> OwnerId {
+        match self {
+            OwnerRef::User(user) => OwnerId::User(user.id),
+            OwnerRef::Team(team) => OwnerId::Team(team.id),
+        }
+    }
+
+    /// GitHub login of the user, or the `github:org:team` login of the team.
+    pub fn login(self) -> &'a str {
+        match self {
+            OwnerRef::User(user) => &user.row.gh_login,
+            OwnerRef::Team(team) => &team.row.login,
+        }
+    }
+
+    pub fn crates(self) -> impl Iterator<Item = CrateRef<'a>> + 'a {
+        let (index, owner) = match self {
+            OwnerRef::User(user) => (user.index, OwnerId::User(user.id)),
+            OwnerRef::Team(team) => (team.index, OwnerId::Team(team.id)),
+        };
+        index
+            .crates_owned_by(owner)
+            .map(move |row| CrateRef { index, row })
+    }
+}
+
+macro_rules! deref_row {
+    ($($name:ident => $table:ident,)*) => {
+        $(
+            impl<'a> Deref for $name<'a> {
+                type Target = crate::$table::Row;
+
+                fn deref(&self) -> &Self::Target {
+                    self.row
+                }
+            }
+
+            impl<'a> Debug for $name<'a> {
+                fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    Debug::fmt(self.row, formatter)
+                }
+            }
+        )*
+    };
+}
+
+deref_row! {
+    CrateRef => crates,
+    VersionRef => versions,
+    DependencyRef => dependencies,
+    UserRef => users,
+    TeamRef => teams,
+    DeletedCrateRef => deleted_crates,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OwnerRef;
+    use crate::crate_owners::OwnerId;
+    use crate::testing::DumpBuilder;
+
+    #[test]
+    fn test_refs() {
+        let mut builder = DumpBuilder::new();
+        let alice = builder.user("alice").id;
+        let team = builder.team("github:serde-rs:publish").id;
+        let serde = builder.krate("serde").id;
+        builder.version(serde, "1.0.0").published_by = Some(alice);
+        builder.version(serde, "1.0.1");
+        let json = builder.krate("serde_json").id;
+        let json_v1 = builder.version(json, "1.0.0").id;
+        builder.dependency(json_v1, serde, "^1.0");
+        builder.owner(serde, alice);
+        builder.owner(serde, team);
+        builder.owner(json, alice);
+        let db = builder.build();
+        let index = db.index();
+
+        let krate = index.krate_by_name("serde").unwrap();
+        assert_eq!(krate.id, serde);
+        let versions: Vec<String> = krate.versions().map(|v| v.num.to_string()).collect();
+        assert_eq!(versions, ["1.0.0", "1.0.1"]);
+        let logins: Vec<&str> = krate.owners().map(OwnerRef::login).collect();
+        assert_eq!(logins, ["alice", "github:serde-rs:publish"]);
+
+        let v1 = krate.versions().next().unwrap();
+        assert_eq!(v1.publisher().unwrap().gh_login, "alice");
+        assert_eq!(v1.krate().unwrap().name, "serde");
+        assert!(krate.versions().nth(1).unwrap().publisher().is_none());
+        assert_eq!(v1.dependencies().count(), 0);
+
+        let dependency = index
+            .version(json_v1)
+            .unwrap()
+            .dependencies()
+            .next()
+            .unwrap();
+        assert_eq!(dependency.krate().unwrap().name, "serde");
+        assert_eq!(
+            dependency.version().unwrap().krate().unwrap().name,
+            "serde_json"
+        );
+
+        let owner = index.owner(OwnerId::User(alice)).unwrap();
+        assert_eq!(owner.id(), OwnerId::User(alice));
+        let mut crates: Vec<&str> = owner
+            .crates()
+            .map(|krate| krate.row().name.as_str())
+            .collect();
+        crates.sort_unstable();
+        assert_eq!(crates, ["serde", "serde_json"]);
+        assert_eq!(index.team(team).unwrap().crates().count(), 1);
+        assert!(index
+            .owner(OwnerId::Team(crate::teams::TeamId(99)))
+            .is_none());
+    }
+}
@@ -0,0 +1,673 @@
+//! Cargo registry index generated from a dump, or checked against it.
+//!
+//! [`DbDump::write_registry_index`] writes the directory tree of a registry
+//! index, with one file per crate containing one line of JSON per version in
+//! the format that cargo reads from crates.io's sparse and git indices:
+//!
+//! ```text
+//! config.json
+//! 1/a
+//! 2/ab
+//! 3/s/syn
+//! se/rd/serde
+//! ```
+//!
+//! Served over HTTP at a `sparse+` URL, or committed to a git repository,
+//! the directory is an offline mirror of the crates.io index for the
+//! versions in the dump.
+//!
+//! ```no_run
+//! use db_dump::registry_index::Config;
+//!
+//! fn main() -> db_dump::Result<()> {
+//!     let mut db = db_dump::DbDump::default();
+//!     db_dump::Loader::new()
+//!         .crates(|row| db.crates.push(row))
+//!         .versions(|row| db.versions.push(row))
+//!         .dependencies(|row| db.dependencies.push(row))
+//!         .load("./db-dump.tar.gz")?;
+//!
+//!     let config = Config {
+//!         dl: "http://mirror.internal/crates".to_owned(),
+//!         api: None,
+//!     };
+//!     db.write_registry_index("./index", &config)?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Only the crates, versions and dependencies tables are needed.
+//!
+//! In the other direction, [`DbDump::check_registry_index`] compares a dump
+//! against an index already on disk, such as a checkout of crates.io's git
+//! index, to detect corruption in either one or skew between the times they
+//! were taken.
+
+use crate::crates::CrateId;
+use crate::error::{err, Result};
+use crate::{DbDump, Index};
+use chrono::SecondsFormat;
+use semver::VersionReq;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Contents of config.json at the root of the index.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Config {
+    /// Download URL of crate files, optionally containing markers like
+    /// `{crate}` and `{version}`.
+    pub dl: String,
+    /// URL of the web API, or None for a registry without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api: Option<String>,
+}
+
+impl Default for Config {
+    /// The config of crates.io.
+    fn default() -> Self {
+        Config {
+            dl: "https://static.crates.io/crates".to_owned(),
+            api: Some("https://crates.io".to_owned()),
+        }
+    }
+}
+
+/// One line of an index file, describing one version of a crate.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub struct Entry {
+    pub name: String,
+    pub vers: String,
+    pub deps: Vec<Dependency>,
+    /// Lowercase hex SHA-256 of the .crate file.
+    pub cksum: String,
+    pub features: BTreeMap<String, Vec<String>>,
+    /// Features that use the `dep:` or `?/` syntax, which older versions of
+    /// cargo do not understand.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub features2: BTreeMap<String, Vec<String>>,
+    pub yanked: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rust_version: Option<String>,
+    /// 2 if features2 is used, otherwise absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub v: Option<u32>,
+    /// Publish time, like `2024-01-31T02:00:17Z`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pubtime: Option<String>,
+}
+
+/// A dependency within an [`Entry`].
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[non_exhaustive]
+pub struct Dependency {
+    /// The name by which the dependency is referred to in Cargo.toml, which
+    /// differs from the crate name if the dependency is renamed.
+    pub name: String,
+    pub req: String,
+    pub features: Vec<String>,
+    pub optional: bool,
+    pub default_features: bool,
+    pub target: Option<String>,
+    /// `"normal"`, `"build"` or `"dev"`.
+    pub kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// The crate name, if the dependency is renamed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+}
+
+impl DbDump {
+    /// Write a registry index of every crate in this dump into the given
+    /// directory.
+    ///
+    /// The directory is created if it does not exist, and files already in
+    /// it are overwritten, so the same directory can be regenerated from a
+    /// newer dump. Index files of crates that are not written this time, such
+    /// as deleted crates, are removed, along with directories left empty.
+    /// Other files, like hidden ones such as `.git`, are left alone. Versions
+    /// without a checksum in the dump are left out, since cargo cannot verify
+    /// a download without one.
+    pub fn write_registry_index(&self, dir: impl AsRef<Path>, config: &Config) -> Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let json = serde_json::to_string_pretty(config).map_err(err)?;
+        fs::write(dir.join("config.json"), json)?;
+
+        let index = self.index();
+        let mut written = BTreeSet::new();
+        for krate in &self.crates {
+            let mut contents = String::new();
+            for entry in entries(&index, krate.id) {
+                contents += &serde_json::to_string(&entry).map_err(err)?;
+                contents.push('\n');
+            }
+            if contents.is_empty() {
+                continue;
+            }
+            let path = dir.join(path(&krate.name));
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(&path, contents)?;
+            written.insert(path);
+        }
+        remove_stale(dir, dir, &written)?;
+        Ok(())
+    }
+
+    /// Compare the versions in this dump against a registry index on disk,
+    /// such as a checkout of the crates.io-index git repository or a
+    /// directory written by [`write_registry_index`].
+    ///
+    /// Reports versions missing from either side, and versions whose
+    /// checksum, yanked state, dependencies or features differ. A dump and an
+    /// index taken at different times legitimately differ in versions
+    /// published or yanked in between. Versions without a checksum in the
+    /// dump are skipped, whether or not the index has them, consistent with
+    /// [`write_registry_index`] leaving them out. The index is read one crate
+    /// file at a time, so it is never held in memory.
+    ///
+    /// [`write_registry_index`]: DbDump::write_registry_index
+    pub fn check_registry_index(&self, dir: impl AsRef<Path>) -> Result<Vec<Mismatch>> {
+        let dir = dir.as_ref();
+        let index = self.index();
+        let mut mismatches = Vec::new();
+
+        let mut names = BTreeSet::new();
+        for krate in &self.crates {
+            names.insert(krate.name.to_ascii_lowercase());
+            let path = dir.join(path(&krate.name));
+            let mut in_index = BTreeMap::new();
+            if path.exists() {
+                for entry in read_entries(&path)? {
+                    if entry.name == krate.name {
+                        in_index.insert(entry.vers.clone(), entry);
+                    }
+                }
+            }
+            for version in index.versions_of(krate.id) {
+                if version.checksum.is_none() {
+                    in_index.remove(&version.num.to_string());
+                    continue;
+                }
+                let in_dump = entry(&index, krate, version);
+                match in_index.remove(&in_dump.vers) {
+                    Some(in_index) => compare(&in_dump, in_index, &mut mismatches),
+                    None => mismatches.push(Mismatch {
+                        name: in_dump.name,
+                        vers: in_dump.vers,
+                        kind: MismatchKind::MissingFromIndex,
+                    }),
+                }
+            }
+            for (vers, _entry) in in_index {
+                mismatches.push(Mismatch {
+                    name: krate.name.clone(),
+                    vers,
+                    kind: MismatchKind::MissingFromDump,
+                });
+            }
+        }
+
+        // Crates that are in the index but not the dump at all.
+        let mut stack = vec![dir.to_owned()];
+        while let Some(subdir) = stack.pop() {
+            for child in fs::read_dir(&subdir)? {
+                let child = child?;
+                let file_name = child.file_name();
+                let file_name = file_name.to_string_lossy();
+                if file_name.starts_with('.') || subdir == dir && file_name == "config.json" {
+                    continue;
+                }
+                if child.file_type()?.is_dir() {
+                    stack.push(child.path());
+                } else if !names.contains(&*file_name) {
+                    for entry in read_entries(&child.path())? {
+                        mismatches.push(Mismatch {
+                            name: entry.name,
+                            vers: entry.vers,
+                            kind: MismatchKind::MissingFromDump,
+                        });
+                    }
+                }
+            }
+        }
+
+        mismatches.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(mismatches)
+    }
+}
+
+// Removes the index files under `subdir` that are not among those just
+// written, recognized by being at the path that their file name maps to, and
+// the directories that this leaves empty. Returns whether `subdir` is empty.
+fn remove_stale(dir: &Path, subdir: &Path, written: &BTreeSet<PathBuf>) -> Result<bool> {
+    let mut empty = true;
+    for child in fs::read_dir(subdir)? {
+        let child = child?;
+        let child_path = child.path();
+        let file_name = child.file_name();
+        let file_name = file_name.to_string_lossy();
+        if file_name.starts_with('.') || written.contains(&child_path) {
+            empty = false;
+        } else if child.file_type()?.is_dir() {
+            if remove_stale(dir, &child_path, written)? {
+                fs::remove_dir(&child_path)?;
+            } else {
+                empty = false;
+            }
+        } else if child_path.strip_prefix(dir) == Ok(&path(&file_name)) {
+            fs::remove_file(&child_path)?;
+        } else {
+            empty = false;
+        }
+    }
+    Ok(empty)
+}
+
+/// A disagreement between a dump and a registry index about one version,
+/// found by [`DbDump::check_registry_index`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Mismatch {
+    pub name: String,
+    pub vers: String,
+    pub kind: MismatchKind,
+}
+
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum MismatchKind {
+    /// The version is in the dump but not in the index.
+    MissingFromIndex,
+    /// The version is in the index but not in the dump.
+    MissingFromDump,
+    /// Checksums as lowercase hex.
+    Checksum {
+        dump: String,
+        index: String,
+    },
+    Yanked {
+        dump: bool,
+        index: bool,
+    },
+    /// Both lists are sorted, and version requirements of the index are
+    /// normalized to the form in which the dump prints them.
+    Dependencies {
+        dump: Vec<Dependency>,
+        index: Vec<Dependency>,
+    },
+    /// On both sides, features2 is merged into features.
+    Features {
+        dump: BTreeMap<String, Vec<String>>,
+        index: BTreeMap<String, Vec<String>>,
+    },
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{} {}: ", self.name, self.vers)?;
+        match &self.kind {
+            MismatchKind::MissingFromIndex => formatter.write_str("missing from index"),
+            MismatchKind::MissingFromDump => formatter.write_str("missing from dump"),
+            MismatchKind::Checksum { dump, index } => {
+                write!(
+                    formatter,
+                    "checksum {:?} in dump, {:?} in index",
+                    dump, index
+                )
+            }
+            MismatchKind::Yanked { dump, index } => {
+                write!(
+                    formatter,
+                    "yanked={} in dump, yanked={} in index",
+                    dump, index
+                )
+            }
+            MismatchKind::Dependencies { .. } => formatter.write_str("dependencies differ"),
+            MismatchKind::Features { .. } => formatter.write_str("features differ"),
+        }
+    }
+}
+
+fn read_entries(path: &Path) -> Result<Vec<Entry>> {
+    let contents = fs::read_to_string(path)?;
+    let mut entries = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => {
+                return Err(err(format_args!("{}:{}: {}", path.display(), i + 1, e)));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn compare(dump: &Entry, index: Entry, mismatches: &mut Vec<Mismatch>) {
+    let mut push = |kind| {
+        mismatches.push(Mismatch {
+            name: dump.name.clone(),
+            vers: dump.vers.clone(),
+            kind,
+        });
+    };
+
+    if dump.cksum != index.cksum {
+        push(MismatchKind::Checksum {
+            dump: dump.cksum.clone(),
+            index: index.cksum,
+        });
+    }
+
+    if dump.yanked != index.yanked {
+        push(MismatchKind::Yanked {
+            dump: dump.yanked,
+            index: index.yanked,
+        });
+    }
+
+    let mut index_deps = index.deps;
+    for dep in &mut index_deps {
+        if let Ok(req) = VersionReq::parse(&dep.req) {
+            dep.req = req.to_string();
+        }
+        // Entries written by old versions of crates.io omit the kind of
+        // normal dependencies.
+        dep.kind.get_or_insert_with(|| "normal".to_owned());
+    }
+    index_deps.sort();
+    if dump.deps != index_deps {
+        push(MismatchKind::Dependencies {
+            dump: dump.deps.clone(),
+            index: index_deps,
+        });
+    }
+
+    let mut dump_features = dump.features.clone();
+    dump_features.extend(dump.features2.clone());
+    let mut index_features = index.features;
+    index_features.extend(index.features2);
+    if dump_features != index_features {
+        push(MismatchKind::Features {
+            dump: dump_features,
+            index: index_features,
+        });
+    }
+}
+
+/// The index entries of every version of the given crate, in order of
+/// publication.
+pub fn entries(index: &Index, crate_id: CrateId) -> Vec<Entry> {
+    let Some(krate) = index.crate_by_id(crate_id) else {
+        return Vec::new();
+    };
+
+    let mut versions: Vec<_> = index.versions_of(crate_id).collect();
+    versions.sort_by_key(|row| (row.created_at, row.id));
+
+    versions
+        .into_iter()
+        .filter(|version| version.checksum.is_some())
+        .map(|version| entry(index, krate, version))
+        .collect()
+}
+
+// The index entry of one version, which must have a checksum.
+fn entry(index: &Index, krate: &crate::crates::Row, version: &crate::versions::Row) -> Entry {
+    let cksum = version.checksum_hex().unwrap_or_default();
+
+    let mut deps = Vec::new();
+    for dependency in index.dependencies_of(version.id) {
+        let Some(target) = index.crate_by_id(dependency.crate_id) else {
+            continue;
+        };
+        let (name, package) = match &dependency.explicit_name {
+            Some(explicit_name) => (explicit_name.clone(), Some(target.name.clone())),
+            None => (target.name.clone(), None),
+        };
+        deps.push(Dependency {
+            name,
+            req: dependency.req.to_string(),
+            features: dependency
+                .features
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            optional: dependency.optional,
+            default_features: dependency.default_features,
+            target: (!dependency.target.is_empty()).then(|| dependency.target.to_string()),
+            kind: Some(dependency.kind.as_str().to_owned()),
+            registry: None,
+            package,
+        });
+    }
+    deps.sort();
+
+    // Like crates.io, put features that older cargo cannot parse into
+    // features2.
+    let mut features = BTreeMap::new();
+    let mut features2 = BTreeMap::new();
+    for (feature, values) in &version.features {
+        let new_syntax = values
+            .iter()
+            .any(|value| value.starts_with("dep:") || value.contains("?/"));
+        let values = values.iter().map(ToString::to_string).collect();
+        if new_syntax {
+            features2.insert(feature.to_string(), values);
+        } else {
+            features.insert(feature.to_string(), values);
+        }
+    }
+
+    Entry {
+        name: krate.name.clone(),
+        vers: version.num.to_string(),
+        deps,
+        cksum,
+        features,
+        v: (!features2.is_empty()).then_some(2),
+        features2,
+        yanked: version.yanked,
+        links: version.links.clone(),
+        rust_version: version.rust_version.as_ref().map(ToString::to_string),
+        pubtime: Some(
+            version
+                .created_at
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+        ),
+    }
+}
+
+/// The path of a crate's file relative to the root of the index, such as
+/// `3/s/syn` or `se/rd/serde`.
+///
+/// Paths are lowercase, so that crates whose names differ only in case share
+/// a file, as in the crates.io index.
+pub fn path(name: &str) -> PathBuf {
+    let name = name.to_ascii_lowercase();
+    match name.len() {
+        1 => ["1", &name].iter().collect(),
+        2 => ["2", &name].iter().collect(),
+        3 => ["3", &name[..1], &name].iter().collect(),
+        _ => [&name[..2], &name[2..4], &name].iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, Entry, MismatchKind};
+    use crate::testing::DumpBuilder;
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_path() {
+        assert_eq!(super::path("a"), Path::new("1/a"));
+        assert_eq!(super::path("ab"), Path::new("2/ab"));
+        assert_eq!(super::path("syn"), Path::new("3/s/syn"));
+        assert_eq!(super::path("Serde"), Path::new("se/rd/serde"));
+    }
+
+    #[test]
+    fn test_write_registry_index() {
+        let mut builder = DumpBuilder::new();
+        let serde = builder.krate("serde").id;
+        let serde_v1 = builder.version(serde, "1.0.0");
+        serde_v1.checksum = Some([0xab; 32]);
+        serde_v1
+            .features
+            .insert(Arc::from("derive"), vec![Arc::from("dep:serde_derive")]);
+        serde_v1.features.insert(Arc::from("std"), vec![]);
+        let syn = builder.krate("syn").id;
+        let syn_v1 = builder.version(syn, "1.0.0");
+        syn_v1.checksum = Some([0xcd; 32]);
+        let syn_v1 = syn_v1.id;
+        builder.dependency(syn_v1, serde, "^1.0").explicit_name = Some("serde1".to_owned());
+        let db = builder.build();
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path();
+        db.write_registry_index(dir, &Config::default()).unwrap();
+
+        let config = fs::read_to_string(dir.join("config.json")).unwrap();
+        assert!(config.contains("\"dl\": \"https://static.crates.io/crates\""));
+
+        let serde = fs::read_to_string(dir.join("se/rd/serde")).unwrap();
+        let entry: Entry = serde_json::from_str(serde.trim_end()).unwrap();
+        assert_eq!(entry.cksum, "ab".repeat(32));
+        assert_eq!(entry.features.keys().collect::<Vec<_>>(), ["std"]);
+        assert_eq!(entry.features2["derive"], ["dep:serde_derive"]);
+        assert_eq!(entry.v, Some(2));
+
+        let syn = fs::read_to_string(dir.join("3/s/syn")).unwrap();
+        assert_eq!(syn.lines().count(), 1);
+        let entry: Entry = serde_json::from_str(&syn).unwrap();
+        assert_eq!(entry.deps[0].name, "serde1");
+        assert_eq!(entry.deps[0].package.as_deref(), Some("serde"));
+        assert_eq!(entry.deps[0].req, "^1.0");
+        assert_eq!(entry.deps[0].kind.as_deref(), Some("normal"));
+        assert!(!syn.contains("features2"));
+    }
+
+    #[test]
+    fn test_regenerate_registry_index() {
+        let mut builder = DumpBuilder::new();
+        let serde = builder.krate("serde").id;
+        builder.version(serde, "1.0.0").checksum = Some([0xab; 32]);
+        let db = builder.build();
+        let syn = builder.krate("syn").id;
+        builder.version(syn, "1.0.0").checksum = Some([0xcd; 32]);
+        let with_syn = builder.build();
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path();
+        with_syn
+            .write_registry_index(dir, &Config::default())
+            .unwrap();
+        fs::create_dir(dir.join(".git")).unwrap();
+        fs::write(dir.join(".git/HEAD"), "ref: refs/heads/master\n").unwrap();
+        fs::write(dir.join("README.md"), "").unwrap();
+        assert!(dir.join("3/s/syn").exists());
+
+        // Regenerating from a dump without syn removes its file.
+        db.write_registry_index(dir, &Config::default()).unwrap();
+        assert!(!dir.join("3").exists());
+        assert!(dir.join("se/rd/serde").exists());
+        assert!(dir.join(".git/HEAD").exists());
+        assert!(dir.join("README.md").exists());
+        assert!(db.check_registry_index(dir).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_check_registry_index() {
+        let mut builder = DumpBuilder::new();
+        let serde = builder.krate("serde").id;
+        builder.version(serde, "1.0.0").checksum = Some([0xab; 32]);
+        builder.version(serde, "1.0.1").checksum = Some([0xcd; 32]);
+        let syn = builder.krate("syn").id;
+        let syn_v1 = builder.version(syn, "1.0.0");
+        syn_v1.checksum = Some([0xef; 32]);
+        let syn_v1 = syn_v1.id;
+        builder.dependency(syn_v1, serde, "^1.0");
+        builder.version(syn, "1.0.1");
+        let db = builder.build();
+
+        // syn 1.0.1 has no checksum, so it is neither written nor checked.
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path();
+        db.write_registry_index(dir, &Config::default()).unwrap();
+        assert!(!fs::read_to_string(dir.join("3/s/syn"))
+            .unwrap()
+            .contains("1.0.1"));
+        assert!(db.check_registry_index(dir).unwrap().is_empty());
+        let mut with_checksum = builder.build();
+        with_checksum.versions[3].checksum = Some([2; 32]);
+        with_checksum
+            .write_registry_index(dir, &Config::default())
+            .unwrap();
+        assert!(db.check_registry_index(dir).unwrap().is_empty());
+
+        // Same requirement written differently, and the kind left out as in
+        // old index entries.
+        let path = dir.join("3/s/syn");
+        let syn = fs::read_to_string(&path).unwrap();
+        let syn = syn
+            .replace("\"^1.0\"", "\"1.0\"")
+            .replace(",\"kind\":\"normal\"", "");
+        fs::write(&path, syn).unwrap();
+        assert!(db.check_registry_index(dir).unwrap().is_empty());
+
+        let mut other = builder.build();
+        other.versions[0].yanked = true;
+        other.versions[1].checksum = Some([0; 32]);
+        other.versions.remove(2);
+        other.dependencies.clear();
+
+        // A crate that is in the index but not in the dump.
+        let mut extra_builder = DumpBuilder::new();
+        let extra = extra_builder.krate("extra").id;
+        extra_builder.version(extra, "0.1.0").checksum = Some([1; 32]);
+        let extra_tempdir = tempfile::tempdir().unwrap();
+        let extra_dir = extra_tempdir.path();
+        extra_builder
+            .build()
+            .write_registry_index(extra_dir, &Config::default())
+            .unwrap();
+        fs::create_dir_all(dir.join("ex/tr")).unwrap();
+        fs::copy(extra_dir.join("ex/tr/extra"), dir.join("ex/tr/extra")).unwrap();
+
+        let mismatches = other.check_registry_index(dir).unwrap();
+        let kinds: Vec<String> = mismatches.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            kinds,
+            [
+                "extra 0.1.0: missing from dump",
+                "serde 1.0.0: yanked=true in dump, yanked=false in index",
+                format!(
+                    "serde 1.0.1: checksum \"{}\" in dump, \"{}\" in index",
+                    "00".repeat(32),
+                    "cd".repeat(32)
+                )
+                .as_str(),
+                "syn 1.0.0: missing from dump",
+            ]
+        );
+        assert!(matches!(
+            mismatches[1].kind,
+            MismatchKind::Yanked {
+                dump: true,
+                index: false
+            }
+        ));
+    }
+}
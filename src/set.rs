@@ -1,13 +1,19 @@
 use serde::de::{Deserializer, Unexpected, Visitor};
+use serde::ser::Serializer;
 use std::fmt;
+use std::marker::PhantomData;
 
-struct SetVisitor<'a> {
+struct SetVisitor<'a, T> {
     expecting: &'a str,
     optional: bool,
+    element: PhantomData<T>,
 }
 
-impl<'de, 'a> Visitor<'de> for SetVisitor<'a> {
-    type Value = Vec<String>;
+impl<'de, 'a, T> Visitor<'de> for SetVisitor<'a, T>
+where
+    T: for<'s> From<&'s str>,
+{
+    type Value = Vec<T>;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         formatter.write_str(self.expecting)
@@ -22,7 +28,7 @@ impl<'de, 'a> Visitor<'de> for SetVisitor<'a> {
             if csv.is_empty() {
                 Ok(Vec::new())
             } else {
-                Ok(csv.split(',').map(str::to_owned).collect())
+                Ok(csv.split(',').map(T::from).collect())
             }
         } else if self.optional && string.is_empty() {
             Ok(Vec::new())
@@ -35,22 +41,42 @@ impl<'de, 'a> Visitor<'de> for SetVisitor<'a> {
     }
 }
 
-pub(crate) fn de<'de, D>(deserializer: D, expecting: &str) -> Result<Vec<String>, D::Error>
+pub(crate) fn de<'de, D, T>(deserializer: D, expecting: &str) -> Result<Vec<T>, D::Error>
 where
     D: Deserializer<'de>,
+    T: for<'s> From<&'s str>,
 {
     deserializer.deserialize_str(SetVisitor {
         expecting,
         optional: false,
+        element: PhantomData,
     })
 }
 
-pub(crate) fn optional<'de, D>(deserializer: D, expecting: &str) -> Result<Vec<String>, D::Error>
+pub(crate) fn optional<'de, D, T>(deserializer: D, expecting: &str) -> Result<Vec<T>, D::Error>
 where
     D: Deserializer<'de>,
+    T: for<'s> From<&'s str>,
 {
     deserializer.deserialize_str(SetVisitor {
         expecting,
         optional: true,
+        element: PhantomData,
     })
 }
+
+pub(crate) fn ser<S, T>(values: &[T], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: AsRef<str>,
+{
+    let mut string = String::from("{");
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            string.push(',');
+        }
+        string.push_str(value.as_ref());
+    }
+    string.push('}');
+    serializer.serialize_str(&string)
+}
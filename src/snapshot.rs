@@ -0,0 +1,1064 @@
+//! Memory-mapped snapshot of a dump, queried in place without deserializing.
+//!
+//! A snapshot stores each table as fixed-width little-endian columns for ids,
+//! timestamps and counts, alongside string heaps for text. Opening one maps
+//! the file and reads only its table of contents. Processes that open the
+//! same snapshot share a single page-cached copy of it.
+//!
+//! ```no_run
+//! use db_dump::snapshot::Snapshot;
+//!
+//! fn main() -> db_dump::Result<()> {
+//!     // Once, after downloading a new dump:
+//!     let db = db_dump::load_all("./db-dump.tar.gz")?;
+//!     db.save_snapshot("./db-dump.snapshot")?;
+//!
+//!     // Then from any number of processes:
+//!     let snapshot = Snapshot::open("./db-dump.snapshot")?;
+//!     let crates = snapshot.crates();
+//!     let downloads = snapshot.crate_downloads();
+//!     for (crate_id, downloads) in downloads.crate_id().iter().zip(downloads.downloads()) {
+//!         if downloads > 100_000_000 {
+//!             let i = crates.id().binary_search(&crate_id).unwrap();
+//!             println!("{}", crates.name().get(i).unwrap());
+//!         }
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Tables with an id are stored in order of id, and tables keyed by crate in
+//! order of crate_id, so that [`Column::binary_search`] finds the row for a
+//! given id.
+//!
+//! Only a selection of columns is included. Optional text columns and
+//! nested data such as feature maps are omitted, and versions and version
+//! requirements are stored as strings. Use [`DbDump::save_cache`] when every
+//! column is needed.
+
+use crate::categories::CategoryId;
+use crate::crate_owners::OwnerId;
+use crate::crates::CrateId;
+use crate::deleted_crates::DeletedCrateId;
+use crate::dependencies::DependencyKind;
+use crate::error::{err, Error, Result};
+use crate::keywords::KeywordId;
+use crate::teams::TeamId;
+use crate::users::UserId;
+use crate::versions::VersionId;
+use crate::{Date, DbDump};
+use chrono::{DateTime, NaiveDate, Utc};
+use memmap::Mmap;
+use std::borrow::Cow;
+use std::fmt::{self, Debug};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::path::Path;
+use std::slice::ChunksExact;
+use std::str;
+use tempfile::NamedTempFile;
+
+const MAGIC: [u8; 8] = *b"dbdsnap\0";
+
+// Bump whenever the set of columns or the encoding of any column changes.
+const FORMAT_VERSION: u32 = 1;
+
+// Magic, format version, column count, total_downloads.
+const HEADER_LEN: usize = 8 + 4 + 4 + 8;
+
+const ALIGN: usize = 8;
+
+/// A memory-mapped snapshot written by [`DbDump::save_snapshot`].
+pub struct Snapshot {
+    mmap: Mmap,
+    total_downloads: u64,
+    layouts: Vec<Layout>,
+}
+
+struct Layout {
+    rows: usize,
+    columns: Vec<Range<usize>>,
+}
+
+/// Values that occupy a fixed number of bytes in a snapshot column.
+///
+/// This trait is sealed and cannot be implemented outside of db_dump.
+pub trait Fixed: Sized + private::Sealed {
+    #[doc(hidden)]
+    const WIDTH: usize;
+    #[doc(hidden)]
+    fn read(bytes: &[u8]) -> Self;
+    #[doc(hidden)]
+    fn write(&self, out: &mut Vec<u8>);
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// A column of fixed-width values, read in place from the snapshot.
+pub struct Column<'a, T> {
+    bytes: &'a [u8],
+    marker: PhantomData<T>,
+}
+
+/// A column of strings, read in place from the snapshot.
+#[derive(Copy, Clone)]
+pub struct Strings<'a> {
+    offsets: Column<'a, u64>,
+    heap: &'a [u8],
+}
+
+/// Iterator over the values of a [`Column`].
+pub struct ColumnIter<'a, T> {
+    chunks: ChunksExact<'a, u8>,
+    marker: PhantomData<T>,
+}
+
+/// Iterator over the values of a [`Strings`] column.
+///
+/// Yields `None` in place of any string that is not valid UTF-8.
+#[derive(Clone)]
+pub struct StringsIter<'a> {
+    strings: Strings<'a>,
+    range: Range<usize>,
+}
+
+impl DbDump {
+    /// Write a selection of columns of this dump to a file that can be
+    /// mapped and queried directly using [`Snapshot`].
+    pub fn save_snapshot(&self, path: impl AsRef<Path>) -> Result<()> {
+        write(self, path.as_ref())
+    }
+}
+
+fn write(db: &DbDump, path: &Path) -> Result<()> {
+    let mut columns = Vec::new();
+    write_columns(db, &mut columns);
+
+    let mut offset = HEADER_LEN + columns.len() * 16;
+    let mut toc = Vec::with_capacity(columns.len() * 16);
+    let mut padding = Vec::with_capacity(columns.len());
+    for column in &columns {
+        let pad = offset.next_multiple_of(ALIGN) - offset;
+        offset += pad;
+        toc.extend_from_slice(&(offset as u64).to_le_bytes());
+        toc.extend_from_slice(&(column.len() as u64).to_le_bytes());
+        padding.push(pad);
+        offset += column.len();
+    }
+
+    // Write to a uniquely named temporary file alongside the destination and
+    // rename it into place, so that readers never map a partially written
+    // snapshot. The temporary file is deleted if anything fails.
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut tmp = NamedTempFile::new_in(dir)?;
+    let mut out = BufWriter::new(tmp.as_file_mut());
+    out.write_all(&MAGIC)?;
+    out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    out.write_all(&(columns.len() as u32).to_le_bytes())?;
+    out.write_all(&db.metadata.total_downloads.to_le_bytes())?;
+    out.write_all(&toc)?;
+    for (column, pad) in columns.iter().zip(padding) {
+        out.write_all(&[0; ALIGN][..pad])?;
+        out.write_all(column)?;
+    }
+    out.into_inner()
+        .map_err(io::IntoInnerError::into_error)?
+        .sync_all()?;
+
+    tmp.persist(path).map_err(|error| error.error)?;
+    Ok(())
+}
+
+fn corrupt() -> Error {
+    err(format_args!("corrupt db dump snapshot"))
+}
+
+impl Snapshot {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file) }?;
+
+        let header = mmap.get(..HEADER_LEN).ok_or_else(corrupt)?;
+        if header[..8] != MAGIC {
+            return Err(err(format_args!("not a db dump snapshot")));
+        }
+        let version = u32::read(&header[8..12]);
+        if version != FORMAT_VERSION {
+            return Err(err(format_args!(
+                "unsupported db dump snapshot format version {}, expected {}",
+                version, FORMAT_VERSION,
+            )));
+        }
+        let count = u32::read(&header[12..16]) as usize;
+        let total_downloads = u64::read(&header[16..24]);
+
+        let toc = mmap
+            .get(HEADER_LEN..HEADER_LEN + count * 16)
+            .ok_or_else(corrupt)?;
+        let mut columns = Vec::with_capacity(count);
+        for entry in toc.chunks_exact(16) {
+            let start = usize::try_from(u64::read(&entry[..8])).map_err(|_| corrupt())?;
+            let len = usize::try_from(u64::read(&entry[8..])).map_err(|_| corrupt())?;
+            let end = start.checked_add(len).ok_or_else(corrupt)?;
+            if end > mmap.len() {
+                return Err(corrupt());
+            }
+            columns.push(start..end);
+        }
+
+        let mut columns = columns.into_iter();
+        let layouts = layouts(&mut columns)?;
+        if columns.next().is_some() {
+            return Err(corrupt());
+        }
+
+        Ok(Snapshot {
+            mmap,
+            total_downloads,
+            layouts,
+        })
+    }
+
+    /// The total_downloads column of **metadata.csv**.
+    pub fn total_downloads(&self) -> u64 {
+        self.total_downloads
+    }
+}
+
+impl Layout {
+    fn new(
+        columns: &mut impl Iterator<Item = Range<usize>>,
+        fixed: &[usize],
+        strings: usize,
+    ) -> Result<Self> {
+        let mut rows = None;
+        let mut layout = Vec::with_capacity(fixed.len() + 2 * strings);
+        let mut check_rows = |n| match rows {
+            None => {
+                rows = Some(n);
+                Ok(())
+            }
+            Some(rows) if rows == n => Ok(()),
+            Some(_) => Err(corrupt()),
+        };
+
+        for &width in fixed {
+            let column = columns.next().ok_or_else(corrupt)?;
+            if column.len() % width != 0 {
+                return Err(corrupt());
+            }
+            check_rows(column.len() / width)?;
+            layout.push(column);
+        }
+
+        for _ in 0..strings {
+            let offsets = columns.next().ok_or_else(corrupt)?;
+            let heap = columns.next().ok_or_else(corrupt)?;
+            let entries = offsets.len() / u64::WIDTH;
+            if offsets.len() % u64::WIDTH != 0 || entries == 0 {
+                return Err(corrupt());
+            }
+            check_rows(entries - 1)?;
+            layout.push(offsets);
+            layout.push(heap);
+        }
+
+        Ok(Layout {
+            rows: rows.unwrap_or(0),
+            columns: layout,
+        })
+    }
+}
+
+impl<'a, T: Fixed> Column<'a, T> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Column {
+            bytes,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len() / T::WIDTH
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Option<T> {
+        let start = i.checked_mul(T::WIDTH)?;
+        let bytes = self.bytes.get(start..start + T::WIDTH)?;
+        Some(T::read(bytes))
+    }
+
+    pub fn iter(&self) -> ColumnIter<'a, T> {
+        ColumnIter {
+            chunks: self.bytes.chunks_exact(T::WIDTH),
+            marker: PhantomData,
+        }
+    }
+
+    /// Index of the first value for which the predicate returns false, in a
+    /// column partitioned such that the predicate is true for every value
+    /// before that point. See [`slice::partition_point`].
+    pub fn partition_point(&self, mut pred: impl FnMut(&T) -> bool) -> usize {
+        let mut lo = 0;
+        let mut hi = self.len();
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if pred(&self.get(mid).unwrap()) {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    /// Binary search a sorted column for the given value. See
+    /// [`slice::binary_search`].
+    pub fn binary_search(&self, value: &T) -> std::result::Result<usize, usize>
+    where
+        T: Ord,
+    {
+        let i = self.partition_point(|v| v < value);
+        match self.get(i) {
+            Some(v) if v == *value => Ok(i),
+            _ => Err(i),
+        }
+    }
+}
+
+impl<'a> Strings<'a> {
+    pub fn len(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The string in the given row, or `None` if out of bounds or if the
+    /// snapshot is corrupt.
+    pub fn get(&self, i: usize) -> Option<&'a str> {
+        let start = usize::try_from(self.offsets.get(i)?).ok()?;
+        let end = usize::try_from(self.offsets.get(i + 1)?).ok()?;
+        str::from_utf8(self.heap.get(start..end)?).ok()
+    }
+
+    pub fn iter(&self) -> StringsIter<'a> {
+        StringsIter {
+            strings: *self,
+            range: 0..self.len(),
+        }
+    }
+
+    /// Index of the first row containing the given string. This is a linear
+    /// scan of the column.
+    pub fn position(&self, string: &str) -> Option<usize> {
+        (0..self.len()).find(|&i| self.get(i) == Some(string))
+    }
+}
+
+impl<'a, T> Copy for Column<'a, T> {}
+
+impl<'a, T> Clone for Column<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Clone for ColumnIter<'a, T> {
+    fn clone(&self) -> Self {
+        ColumnIter {
+            chunks: self.chunks.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Debug for Column<'a, T>
+where
+    T: Fixed + Debug,
+{
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a> Debug for Strings<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T: Fixed> IntoIterator for Column<'a, T> {
+    type Item = T;
+    type IntoIter = ColumnIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T: Fixed> IntoIterator for &Column<'a, T> {
+    type Item = T;
+    type IntoIter = ColumnIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &Strings<'a> {
+    type Item = Option<&'a str>;
+    type IntoIter = StringsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for Strings<'a> {
+    type Item = Option<&'a str>;
+    type IntoIter = StringsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T: Fixed> Iterator for ColumnIter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chunks.next().map(T::read)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+}
+
+impl<'a, T: Fixed> DoubleEndedIterator for ColumnIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.chunks.next_back().map(T::read)
+    }
+}
+
+impl<'a, T: Fixed> ExactSizeIterator for ColumnIter<'a, T> {}
+
+impl<'a, T: Fixed> FusedIterator for ColumnIter<'a, T> {}
+
+impl<'a> Iterator for StringsIter<'a> {
+    type Item = Option<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let i = self.range.next()?;
+        Some(self.strings.get(i))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for StringsIter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let i = self.range.next_back()?;
+        Some(self.strings.get(i))
+    }
+}
+
+impl<'a> ExactSizeIterator for StringsIter<'a> {}
+
+impl<'a> FusedIterator for StringsIter<'a> {}
+
+macro_rules! fixed_int {
+    ($($int:ty)*) => {
+        $(
+            impl private::Sealed for $int {}
+
+            impl Fixed for $int {
+                const WIDTH: usize = size_of::<$int>();
+
+                fn read(bytes: &[u8]) -> Self {
+                    <$int>::from_le_bytes(bytes.try_into().unwrap())
+                }
+
+                fn write(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+fixed_int!(u16 u32 u64 i32 i64);
+
+macro_rules! fixed_id {
+    ($($id:ident)*) => {
+        $(
+            impl private::Sealed for $id {}
+
+            impl Fixed for $id {
+                const WIDTH: usize = 4;
+
+                fn read(bytes: &[u8]) -> Self {
+                    $id(u32::read(bytes))
+                }
+
+                fn write(&self, out: &mut Vec<u8>) {
+                    self.0.write(out);
+                }
+            }
+        )*
+    };
+}
+
+fixed_id!(CategoryId CrateId DeletedCrateId KeywordId TeamId UserId VersionId);
+
+impl private::Sealed for bool {}
+
+impl Fixed for bool {
+    const WIDTH: usize = 1;
+
+    fn read(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(u8::from(*self));
+    }
+}
+
+impl private::Sealed for [u8; 32] {}
+
+impl Fixed for [u8; 32] {
+    const WIDTH: usize = 32;
+
+    fn read(bytes: &[u8]) -> Self {
+        bytes.try_into().unwrap()
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+}
+
+impl<T: Fixed> private::Sealed for Option<T> {}
+
+/// A presence byte followed by the value, which is zeroed if absent.
+impl<T: Fixed> Fixed for Option<T> {
+    const WIDTH: usize = 1 + T::WIDTH;
+
+    fn read(bytes: &[u8]) -> Self {
+        if bytes[0] == 0 {
+            None
+        } else {
+            Some(T::read(&bytes[1..]))
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.resize(out.len() + Self::WIDTH, 0),
+            Some(value) => {
+                out.push(1);
+                value.write(out);
+            }
+        }
+    }
+}
+
+impl private::Sealed for DateTime<Utc> {}
+
+/// Microseconds since the Unix epoch, which is the precision of timestamps in
+/// the dump.
+impl Fixed for DateTime<Utc> {
+    const WIDTH: usize = 8;
+
+    fn read(bytes: &[u8]) -> Self {
+        let micros = i64::read(bytes);
+        DateTime::from_timestamp_micros(micros).unwrap_or(if micros < 0 {
+            DateTime::<Utc>::MIN_UTC
+        } else {
+            DateTime::<Utc>::MAX_UTC
+        })
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        self.timestamp_micros().write(out);
+    }
+}
+
+impl private::Sealed for Date<Utc> {}
+
+/// Days since the start of the common era.
+impl Fixed for Date<Utc> {
+    const WIDTH: usize = 4;
+
+    fn read(bytes: &[u8]) -> Self {
+        let days = i32::read(bytes);
+        Date::from(
+            NaiveDate::from_num_days_from_ce_opt(days).unwrap_or(if days < 0 {
+                NaiveDate::MIN
+            } else {
+                NaiveDate::MAX
+            }),
+        )
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        chrono::Datelike::num_days_from_ce(&self.naive_utc()).write(out);
+    }
+}
+
+impl private::Sealed for OwnerId {}
+
+/// The owner_kind byte followed by the owner_id.
+impl Fixed for OwnerId {
+    const WIDTH: usize = 5;
+
+    fn read(bytes: &[u8]) -> Self {
+        let id = u32::read(&bytes[1..]);
+        OwnerId::new(id, bytes[0]).unwrap_or(OwnerId::Team(TeamId(id)))
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(self.kind());
+        self.id().write(out);
+    }
+}
+
+impl private::Sealed for DependencyKind {}
+
+impl Fixed for DependencyKind {
+    const WIDTH: usize = 1;
+
+    fn read(bytes: &[u8]) -> Self {
+        DependencyKind::from_u8(bytes[0]).unwrap_or(DependencyKind::Normal)
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(self.as_u8());
+    }
+}
+
+macro_rules! tables {
+    ($(
+        $(#[$doc:meta])*
+        $method:ident => $table:ident $(sorted by ($($key:ident),*))? {
+            $($column:ident: $ty:ty = |$row:ident| $value:expr,)*
+        }
+        strings {
+            $($string:ident = |$srow:ident| $svalue:expr,)*
+        }
+    )*) => {
+        enum Tables {
+            $($table,)*
+        }
+
+        $(
+            $(#[$doc])*
+            #[derive(Copy, Clone, Debug)]
+            pub struct $table<'a> {
+                rows: usize,
+                $(
+                    $column: Column<'a, $ty>,
+                )*
+                $(
+                    $string: Strings<'a>,
+                )*
+            }
+
+            impl<'a> $table<'a> {
+                #[allow(unused_variables, unused_mut)]
+                fn new(bytes: &'a [u8], layout: &Layout) -> Self {
+                    let mut columns = layout.columns.iter().map(|range| &bytes[range.clone()]);
+                    $table {
+                        rows: layout.rows,
+                        $(
+                            $column: Column::new(columns.next().unwrap()),
+                        )*
+                        $(
+                            $string: Strings {
+                                offsets: Column::new(columns.next().unwrap()),
+                                heap: columns.next().unwrap(),
+                            },
+                        )*
+                    }
+                }
+
+                pub fn len(&self) -> usize {
+                    self.rows
+                }
+
+                pub fn is_empty(&self) -> bool {
+                    self.rows == 0
+                }
+
+                $(
+                    pub fn $column(&self) -> Column<'a, $ty> {
+                        self.$column
+                    }
+                )*
+
+                $(
+                    pub fn $string(&self) -> Strings<'a> {
+                        self.$string
+                    }
+                )*
+            }
+        )*
+
+        impl Snapshot {
+            $(
+                pub fn $method(&self) -> $table<'_> {
+                    $table::new(&self.mmap, &self.layouts[Tables::$table as usize])
+                }
+            )*
+        }
+
+        #[allow(unused_variables)]
+        fn write_columns(db: &DbDump, columns: &mut Vec<Vec<u8>>) {
+            $(
+                let rows = AsRows::as_rows(&db.$method);
+                let rows = &*rows;
+                let order = permutation(rows, |row| ($($(row.$key,)*)?));
+                $(
+                    let mut bytes = Vec::new();
+                    for $row in in_order(rows, order.as_deref()) {
+                        Fixed::write(&$value, &mut bytes);
+                    }
+                    columns.push(bytes);
+                )*
+                $(
+                    let mut offsets = Vec::new();
+                    let mut heap = Vec::new();
+                    0u64.write(&mut offsets);
+                    for $srow in in_order(rows, order.as_deref()) {
+                        let string = $svalue;
+                        heap.extend_from_slice(AsRef::<str>::as_ref(&string).as_bytes());
+                        (heap.len() as u64).write(&mut offsets);
+                    }
+                    columns.push(offsets);
+                    columns.push(heap);
+                )*
+            )*
+        }
+
+        fn layouts(columns: &mut impl Iterator<Item = Range<usize>>) -> Result<Vec<Layout>> {
+            Ok(vec![$(
+                Layout::new(
+                    columns,
+                    &[$(<$ty as Fixed>::WIDTH),*],
+                    <[&str]>::len(&[$(stringify!($string)),*]),
+                )?,
+            )*])
+        }
+    };
+}
+
+// Rows of a table of the DbDump as a slice. Rows of the columnar
+// version_downloads table are materialized for the duration of writing it.
+trait AsRows {
+    type Row: Clone;
+    fn as_rows(&self) -> Cow<'_, [Self::Row]>;
+}
+
+impl<T: Clone> AsRows for Vec<T> {
+    type Row = T;
+    fn as_rows(&self) -> Cow<'_, [T]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl AsRows for crate::version_downloads::Table {
+    type Row = crate::version_downloads::Row;
+    fn as_rows(&self) -> Cow<'_, [Self::Row]> {
+        Cow::Owned(self.iter().collect())
+    }
+}
+
+// Indices of the rows in order of the given key, or None if the rows are in
+// that order already.
+fn permutation<T, K: Ord>(rows: &[T], key: impl Fn(&T) -> K) -> Option<Vec<usize>> {
+    if rows.is_sorted_by_key(&key) {
+        return None;
+    }
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    order.sort_by_key(|&i| key(&rows[i]));
+    Some(order)
+}
+
+fn in_order<'a, T>(rows: &'a [T], order: Option<&'a [usize]>) -> impl Iterator<Item = &'a T> {
+    let (order, rest) = match order {
+        Some(order) => (order, &[][..]),
+        None => (&[][..], rows),
+    };
+    order.iter().map(move |&i| &rows[i]).chain(rest)
+}
+
+tables! {
+    /// Columns of **categories.csv**, in order of id.
+    categories => Categories sorted by (id) {
+        id: CategoryId = |row| row.id,
+        crates_cnt: u32 = |row| row.crates_cnt,
+        created_at: DateTime<Utc> = |row| row.created_at,
+    }
+    strings {
+        category = |row| &row.category,
+        slug = |row| &row.slug,
+        description = |row| &row.description,
+        path = |row| &row.path,
+    }
+
+    /// Columns of **crate_downloads.csv**, in order of crate_id.
+    crate_downloads => CrateDownloads sorted by (crate_id) {
+        crate_id: CrateId = |row| row.crate_id,
+        downloads: u64 = |row| row.downloads,
+    }
+    strings {}
+
+    /// Columns of **crate_owners.csv**.
+    crate_owners => CrateOwners {
+        crate_id: CrateId = |row| row.crate_id,
+        owner_id: OwnerId = |row| row.owner_id,
+        created_at: DateTime<Utc> = |row| row.created_at,
+        created_by: Option<UserId> = |row| row.created_by,
+    }
+    strings {}
+
+    /// Columns of **crates.csv**, in order of id.
+    crates => Crates sorted by (id) {
+        id: CrateId = |row| row.id,
+        updated_at: DateTime<Utc> = |row| row.updated_at,
+        created_at: DateTime<Utc> = |row| row.created_at,
+        max_upload_size: Option<u64> = |row| row.max_upload_size,
+        max_features: Option<u16> = |row| row.max_features,
+        trustpub_only: bool = |row| row.trustpub_only,
+    }
+    strings {
+        name = |row| &row.name,
+        description = |row| &row.description,
+    }
+
+    /// Columns of **crates_categories.csv**.
+    crates_categories => CratesCategories {
+        crate_id: CrateId = |row| row.crate_id,
+        category_id: CategoryId = |row| row.category_id,
+    }
+    strings {}
+
+    /// Columns of **crates_keywords.csv**.
+    crates_keywords => CratesKeywords {
+        crate_id: CrateId = |row| row.crate_id,
+        keyword_id: KeywordId = |row| row.keyword_id,
+    }
+    strings {}
+
+    /// Columns of **default_versions.csv**, in order of crate_id.
+    default_versions => DefaultVersions sorted by (crate_id) {
+        crate_id: CrateId = |row| row.crate_id,
+        version_id: VersionId = |row| row.version_id,
+        num_versions: Option<u32> = |row| row.num_versions,
+    }
+    strings {}
+
+    /// Columns of **deleted_crates.csv**, in order of id.
+    deleted_crates => DeletedCrates sorted by (id) {
+        id: DeletedCrateId = |row| row.id,
+        created_at: DateTime<Utc> = |row| row.created_at,
+        deleted_at: DateTime<Utc> = |row| row.deleted_at,
+        deleted_by: Option<UserId> = |row| row.deleted_by,
+        available_at: DateTime<Utc> = |row| row.available_at,
+    }
+    strings {
+        name = |row| &row.name,
+        message = |row| &row.message,
+    }
+
+    /// Columns of **dependencies.csv**, in order of id.
+    dependencies => Dependencies sorted by (id) {
+        id: u32 = |row| row.id,
+        version_id: VersionId = |row| row.version_id,
+        crate_id: CrateId = |row| row.crate_id,
+        optional: bool = |row| row.optional,
+        default_features: bool = |row| row.default_features,
+        kind: DependencyKind = |row| row.kind,
+    }
+    strings {
+        req = |row| row.req.to_string(),
+        target = |row| &row.target,
+    }
+
+    /// Columns of **keywords.csv**, in order of id.
+    keywords => Keywords sorted by (id) {
+        id: KeywordId = |row| row.id,
+        crates_cnt: u32 = |row| row.crates_cnt,
+        created_at: DateTime<Utc> = |row| row.created_at,
+    }
+    strings {
+        keyword = |row| &row.keyword,
+    }
+
+    /// Columns of **reserved_crate_names.csv**.
+    reserved_crate_names => ReservedCrateNames {}
+    strings {
+        name = |row| &row.name,
+    }
+
+    /// Columns of **teams.csv**, in order of id.
+    teams => Teams sorted by (id) {
+        id: TeamId = |row| row.id,
+        github_id: u32 = |row| row.github_id,
+        org_id: Option<u32> = |row| row.org_id,
+    }
+    strings {
+        login = |row| &row.login,
+        name = |row| &row.name,
+        avatar = |row| &row.avatar,
+    }
+
+    /// Columns of **users.csv**, in order of id.
+    users => Users sorted by (id) {
+        id: UserId = |row| row.id,
+        gh_id: i32 = |row| row.gh_id,
+    }
+    strings {
+        gh_login = |row| &row.gh_login,
+        gh_avatar = |row| &row.gh_avatar,
+    }
+
+    /// Columns of **version_downloads.csv**, in order of version_id and date.
+    version_downloads => VersionDownloads sorted by (version_id, date) {
+        version_id: VersionId = |row| row.version_id,
+        date: Date<Utc> = |row| row.date,
+        downloads: u64 = |row| row.downloads,
+    }
+    strings {}
+
+    /// Columns of **versions.csv**, in order of id.
+    versions => Versions sorted by (id) {
+        id: VersionId = |row| row.id,
+        crate_id: CrateId = |row| row.crate_id,
+        updated_at: DateTime<Utc> = |row| row.updated_at,
+        created_at: DateTime<Utc> = |row| row.created_at,
+        downloads: u64 = |row| row.downloads,
+        yanked: bool = |row| row.yanked,
+        crate_size: Option<u64> = |row| row.crate_size,
+        published_by: Option<UserId> = |row| row.published_by,
+        checksum: Option<[u8; 32]> = |row| row.checksum,
+        has_lib: bool = |row| row.has_lib,
+        edition: Option<u16> = |row| row.edition,
+    }
+    strings {
+        num = |row| row.num.to_string(),
+        license = |row| &row.license,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Snapshot;
+    use crate::crate_owners::OwnerId;
+    use crate::crates::CrateId;
+    use crate::teams::TeamId;
+    use crate::users::UserId;
+    use crate::versions::VersionId;
+    use crate::{Date, DbDump};
+    use chrono::DateTime;
+
+    #[test]
+    fn test_snapshot() {
+        let timestamp = DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap();
+
+        let mut db = DbDump::default();
+        for (id, name) in [(2, ""), (1, "serde")] {
+            db.crates.push(crate::crates::Row {
+                id: CrateId(id),
+                name: name.to_owned(),
+                updated_at: timestamp,
+                created_at: timestamp,
+                description: "ser/de ✓".to_owned(),
+                homepage: None,
+                documentation: None,
+                readme: None,
+                repository: None,
+                max_upload_size: if id == 1 { Some(1 << 20) } else { None },
+                max_features: None,
+                trustpub_only: id == 2,
+            });
+        }
+        for (crate_id, owner_id) in [(1, OwnerId::User(UserId(7))), (2, OwnerId::Team(TeamId(3)))] {
+            db.crate_owners.push(crate::crate_owners::Row {
+                crate_id: CrateId(crate_id),
+                owner_id,
+                created_at: timestamp,
+                created_by: Some(UserId(7)),
+            });
+        }
+        for (version_id, day, downloads) in [(2, 2, 5), (1, 3, 4), (1, 1, 3)] {
+            db.version_downloads.push(crate::version_downloads::Row {
+                version_id: VersionId(version_id),
+                downloads,
+                date: Date::from_ymd(2024, 6, day),
+            });
+        }
+        db.metadata.total_downloads = 12;
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path();
+        let path = dir.join("test.snapshot");
+        db.save_snapshot(&path).unwrap();
+        let snapshot = Snapshot::open(&path).unwrap();
+
+        assert_eq!(snapshot.total_downloads(), 12);
+
+        let crates = snapshot.crates();
+        assert_eq!(crates.len(), 2);
+        assert_eq!(
+            crates.id().iter().collect::<Vec<_>>(),
+            [CrateId(1), CrateId(2)]
+        );
+        assert_eq!(crates.name().get(0), Some("serde"));
+        assert_eq!(crates.name().get(1), Some(""));
+        assert_eq!(crates.name().get(2), None);
+        assert_eq!(crates.name().position(""), Some(1));
+        assert_eq!(crates.id().binary_search(&CrateId(2)), Ok(1));
+        assert_eq!(crates.id().binary_search(&CrateId(3)), Err(2));
+        assert_eq!(crates.description().get(1), Some("ser/de ✓"));
+        assert_eq!(crates.created_at().get(0), Some(timestamp));
+        assert_eq!(crates.max_upload_size().get(0), Some(Some(1 << 20)));
+        assert_eq!(crates.max_upload_size().get(1), Some(None));
+        assert_eq!(
+            crates.trustpub_only().iter().collect::<Vec<_>>(),
+            [false, true]
+        );
+
+        let owners = snapshot.crate_owners();
+        assert_eq!(owners.owner_id().get(1), Some(OwnerId::Team(TeamId(3))));
+
+        let downloads = snapshot.version_downloads();
+        assert_eq!(downloads.downloads().iter().collect::<Vec<_>>(), [3, 4, 5]);
+        assert_eq!(downloads.date().get(1), Some(Date::from_ymd(2024, 6, 3)));
+        let version_ids = downloads.version_id();
+        assert_eq!(version_ids.binary_search(&VersionId(2)), Ok(2));
+        assert_eq!(version_ids.partition_point(|&id| id < VersionId(2)), 2);
+        assert_eq!(version_ids.binary_search(&VersionId(0)), Err(0));
+
+        assert!(snapshot.versions().is_empty());
+        assert!(snapshot.reserved_crate_names().name().is_empty());
+    }
+}
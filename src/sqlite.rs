@@ -0,0 +1,616 @@
+//! Export of a dump into a SQLite database.
+//!
+//! *This module is available only if db-dump is built with the `"sqlite"`
+//! feature.*
+//!
+//! Every table of the dump becomes a table of the same name, with the columns
+//! of the csv in the same order, except for the list-valued columns described
+//! below and versions.csv's `num_no_build`, which is `num` without build
+//! metadata and is left out. Tables have SQLite column types, primary keys,
+//! unique constraints, declared foreign keys, and an index on every foreign
+//! key column. Datetimes are stored as
+//! text in the form `2024-01-31 02:00:17.123456`, which SQLite's date and time
+//! functions understand, and dates as `2024-01-31`. Booleans are 0 or 1.
+//! Checksums are 32-byte blobs.
+//!
+//! The list-valued columns of versions.csv and dependencies.csv are
+//! normalized into child tables keyed by the parent row's id:
+//!
+//! - **version_features** (version_id, feature)
+//! - **version_feature_values** (version_id, feature, value)
+//! - **version_bin_names** (version_id, bin_name)
+//! - **version_keywords** (version_id, keyword)
+//! - **version_categories** (version_id, category)
+//! - **dependency_features** (dependency_id, feature)
+//!
+//! In crate_owners, `owner_kind` is 0 for a user and 1 for a team.
+//!
+//! Foreign keys are declared but not enforced, as the dumps occasionally
+//! contain rows referring to rows that were deleted while the dump was being
+//! taken.
+//!
+//! ```no_run
+//! fn main() -> db_dump::Result<()> {
+//!     db_dump::sqlite::export("./db-dump.tar.gz", "./crates.db")?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ```console
+//! $ sqlite3 crates.db "SELECT name FROM crates ORDER BY created_at LIMIT 3"
+//! ```
+
+use crate::error::Result;
+use crate::Loader;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, Statement};
+use std::cell::RefCell;
+use std::path::Path;
+
+const SCHEMA: &str = "
+    CREATE TABLE categories (
+        id INTEGER PRIMARY KEY,
+        category TEXT NOT NULL UNIQUE,
+        slug TEXT NOT NULL UNIQUE,
+        description TEXT NOT NULL,
+        crates_cnt INTEGER NOT NULL,
+        created_at TEXT NOT NULL,
+        path TEXT NOT NULL
+    );
+    CREATE TABLE crate_downloads (
+        crate_id INTEGER PRIMARY KEY REFERENCES crates (id),
+        downloads INTEGER NOT NULL
+    );
+    CREATE TABLE crate_owners (
+        crate_id INTEGER NOT NULL REFERENCES crates (id),
+        created_at TEXT NOT NULL,
+        created_by INTEGER REFERENCES users (id),
+        owner_id INTEGER NOT NULL,
+        owner_kind INTEGER NOT NULL,
+        PRIMARY KEY (crate_id, owner_id, owner_kind)
+    ) WITHOUT ROWID;
+    CREATE TABLE crates (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        updated_at TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        description TEXT NOT NULL,
+        homepage TEXT,
+        documentation TEXT,
+        readme TEXT,
+        repository TEXT,
+        max_upload_size INTEGER,
+        max_features INTEGER,
+        trustpub_only INTEGER NOT NULL
+    );
+    CREATE TABLE crates_categories (
+        crate_id INTEGER NOT NULL REFERENCES crates (id),
+        category_id INTEGER NOT NULL REFERENCES categories (id),
+        PRIMARY KEY (crate_id, category_id)
+    ) WITHOUT ROWID;
+    CREATE TABLE crates_keywords (
+        crate_id INTEGER NOT NULL REFERENCES crates (id),
+        keyword_id INTEGER NOT NULL REFERENCES keywords (id),
+        PRIMARY KEY (crate_id, keyword_id)
+    ) WITHOUT ROWID;
+    CREATE TABLE default_versions (
+        crate_id INTEGER PRIMARY KEY REFERENCES crates (id),
+        version_id INTEGER NOT NULL REFERENCES versions (id),
+        num_versions INTEGER
+    );
+    CREATE TABLE deleted_crates (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        deleted_at TEXT NOT NULL,
+        deleted_by INTEGER REFERENCES users (id),
+        message TEXT NOT NULL,
+        available_at TEXT NOT NULL
+    );
+    CREATE TABLE dependencies (
+        id INTEGER PRIMARY KEY,
+        version_id INTEGER NOT NULL REFERENCES versions (id),
+        crate_id INTEGER NOT NULL REFERENCES crates (id),
+        req TEXT NOT NULL,
+        optional INTEGER NOT NULL,
+        default_features INTEGER NOT NULL,
+        target TEXT NOT NULL,
+        kind INTEGER NOT NULL,
+        explicit_name TEXT
+    );
+    CREATE TABLE dependency_features (
+        dependency_id INTEGER NOT NULL REFERENCES dependencies (id),
+        feature TEXT NOT NULL
+    );
+    CREATE TABLE keywords (
+        id INTEGER PRIMARY KEY,
+        keyword TEXT NOT NULL UNIQUE,
+        crates_cnt INTEGER NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    CREATE TABLE metadata (
+        total_downloads INTEGER NOT NULL
+    );
+    CREATE TABLE reserved_crate_names (
+        name TEXT PRIMARY KEY
+    ) WITHOUT ROWID;
+    CREATE TABLE teams (
+        id INTEGER PRIMARY KEY,
+        login TEXT NOT NULL UNIQUE,
+        github_id INTEGER NOT NULL UNIQUE,
+        name TEXT NOT NULL,
+        avatar TEXT NOT NULL,
+        org_id INTEGER
+    );
+    CREATE TABLE users (
+        id INTEGER PRIMARY KEY,
+        gh_login TEXT NOT NULL,
+        name TEXT,
+        gh_avatar TEXT NOT NULL,
+        gh_id INTEGER NOT NULL
+    );
+    CREATE TABLE version_downloads (
+        version_id INTEGER NOT NULL REFERENCES versions (id),
+        downloads INTEGER NOT NULL,
+        date TEXT NOT NULL,
+        PRIMARY KEY (version_id, date)
+    ) WITHOUT ROWID;
+    CREATE TABLE versions (
+        id INTEGER PRIMARY KEY,
+        crate_id INTEGER NOT NULL REFERENCES crates (id),
+        num TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        downloads INTEGER NOT NULL,
+        yanked INTEGER NOT NULL,
+        license TEXT NOT NULL,
+        crate_size INTEGER,
+        published_by INTEGER REFERENCES users (id),
+        checksum BLOB,
+        links TEXT,
+        rust_version TEXT,
+        has_lib INTEGER NOT NULL,
+        edition INTEGER,
+        description TEXT,
+        homepage TEXT,
+        documentation TEXT,
+        repository TEXT
+    );
+    CREATE TABLE version_features (
+        version_id INTEGER NOT NULL REFERENCES versions (id),
+        feature TEXT NOT NULL,
+        PRIMARY KEY (version_id, feature)
+    ) WITHOUT ROWID;
+    CREATE TABLE version_feature_values (
+        version_id INTEGER NOT NULL REFERENCES versions (id),
+        feature TEXT NOT NULL,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE version_bin_names (
+        version_id INTEGER NOT NULL REFERENCES versions (id),
+        bin_name TEXT NOT NULL
+    );
+    CREATE TABLE version_keywords (
+        version_id INTEGER NOT NULL REFERENCES versions (id),
+        keyword TEXT NOT NULL
+    );
+    CREATE TABLE version_categories (
+        version_id INTEGER NOT NULL REFERENCES versions (id),
+        category TEXT NOT NULL
+    );
+";
+
+// Created after the rows are inserted, which is faster than maintaining them
+// during the inserts.
+const INDEXES: &str = "
+    CREATE INDEX crate_owners_owner ON crate_owners (owner_kind, owner_id);
+    CREATE INDEX crate_owners_created_by ON crate_owners (created_by);
+    CREATE INDEX crates_categories_category_id ON crates_categories (category_id);
+    CREATE INDEX crates_keywords_keyword_id ON crates_keywords (keyword_id);
+    CREATE INDEX default_versions_version_id ON default_versions (version_id);
+    CREATE INDEX deleted_crates_deleted_by ON deleted_crates (deleted_by);
+    CREATE INDEX dependencies_version_id ON dependencies (version_id);
+    CREATE INDEX dependencies_crate_id ON dependencies (crate_id);
+    CREATE INDEX dependency_features_dependency_id ON dependency_features (dependency_id);
+    CREATE INDEX version_downloads_date ON version_downloads (date);
+    CREATE INDEX versions_crate_id ON versions (crate_id);
+    CREATE INDEX versions_published_by ON versions (published_by);
+    CREATE INDEX version_feature_values_version_id ON version_feature_values (version_id);
+    CREATE INDEX version_bin_names_version_id ON version_bin_names (version_id);
+    CREATE INDEX version_keywords_version_id ON version_keywords (version_id);
+    CREATE INDEX version_keywords_keyword ON version_keywords (keyword);
+    CREATE INDEX version_categories_version_id ON version_categories (version_id);
+    CREATE INDEX version_categories_category ON version_categories (category);
+";
+
+/// Load the db-dump.tar.gz at the given path into a new SQLite database.
+///
+/// Rows are streamed from [`Loader`] directly into the database, so the dump
+/// is never held in memory. The import is a single transaction: it returns an
+/// error without modifying the database if the database already contains any
+/// of the tables, and rolls back if anything fails part-way.
+pub fn export(dump: impl AsRef<Path>, database: impl AsRef<Path>) -> Result<()> {
+    let mut conn = Connection::open(database)?;
+    // Some builds of SQLite enforce foreign keys by default. The rollback
+    // journal is kept in memory rather than turned off, because a failed
+    // import relies on rolling back the transaction.
+    conn.execute_batch(
+        "PRAGMA foreign_keys = OFF; PRAGMA journal_mode = MEMORY; PRAGMA synchronous = OFF;",
+    )?;
+    let tx = conn.transaction()?;
+    tx.execute_batch(SCHEMA)?;
+    insert(&tx, dump.as_ref())?;
+    tx.execute_batch(INDEXES)?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn insert(conn: &Connection, dump: &Path) -> Result<()> {
+    // Loader callbacks cannot return errors, so the first one is held here
+    // and the remaining rows are skipped.
+    let error = RefCell::new(None);
+
+    let mut categories = conn.prepare("INSERT INTO categories VALUES (?, ?, ?, ?, ?, ?, ?)")?;
+    let mut crate_downloads = conn.prepare("INSERT INTO crate_downloads VALUES (?, ?)")?;
+    let mut crate_owners = conn.prepare("INSERT INTO crate_owners VALUES (?, ?, ?, ?, ?)")?;
+    let mut crates =
+        conn.prepare("INSERT INTO crates VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+    let mut crates_categories = conn.prepare("INSERT INTO crates_categories VALUES (?, ?)")?;
+    let mut crates_keywords = conn.prepare("INSERT INTO crates_keywords VALUES (?, ?)")?;
+    let mut default_versions = conn.prepare("INSERT INTO default_versions VALUES (?, ?, ?)")?;
+    let mut deleted_crates =
+        conn.prepare("INSERT INTO deleted_crates VALUES (?, ?, ?, ?, ?, ?, ?)")?;
+    let mut dependencies =
+        conn.prepare("INSERT INTO dependencies VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+    let mut dependency_features = conn.prepare("INSERT INTO dependency_features VALUES (?, ?)")?;
+    let mut keywords = conn.prepare("INSERT INTO keywords VALUES (?, ?, ?, ?)")?;
+    let mut metadata = conn.prepare("INSERT INTO metadata VALUES (?)")?;
+    let mut reserved_crate_names = conn.prepare("INSERT INTO reserved_crate_names VALUES (?)")?;
+    let mut teams = conn.prepare("INSERT INTO teams VALUES (?, ?, ?, ?, ?, ?)")?;
+    let mut users = conn.prepare("INSERT INTO users VALUES (?, ?, ?, ?, ?)")?;
+    let mut version_downloads = conn.prepare("INSERT INTO version_downloads VALUES (?, ?, ?)")?;
+    let mut versions = conn.prepare(
+        "INSERT INTO versions VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )?;
+    let mut version_children = VersionChildren {
+        features: conn.prepare("INSERT INTO version_features VALUES (?, ?)")?,
+        feature_values: conn.prepare("INSERT INTO version_feature_values VALUES (?, ?, ?)")?,
+        bin_names: conn.prepare("INSERT INTO version_bin_names VALUES (?, ?)")?,
+        keywords: conn.prepare("INSERT INTO version_keywords VALUES (?, ?)")?,
+        categories: conn.prepare("INSERT INTO version_categories VALUES (?, ?)")?,
+    };
+
+    Loader::new()
+        .categories(|row| {
+            guard(&error, || {
+                categories.execute(params![
+                    row.id.0,
+                    row.category,
+                    row.slug,
+                    row.description,
+                    row.crates_cnt,
+                    datetime(row.created_at),
+                    row.path,
+                ])
+            });
+        })
+        .crate_downloads(|row| {
+            guard(&error, || {
+                crate_downloads.execute(params![row.crate_id.0, row.downloads])
+            });
+        })
+        .crate_owners(|row| {
+            guard(&error, || {
+                crate_owners.execute(params![
+                    row.crate_id.0,
+                    datetime(row.created_at),
+                    row.created_by.map(|user_id| user_id.0),
+                    row.owner_id.id(),
+                    row.owner_id.kind(),
+                ])
+            });
+        })
+        .crates(|row| {
+            guard(&error, || {
+                crates.execute(params![
+                    row.id.0,
+                    row.name,
+                    datetime(row.updated_at),
+                    datetime(row.created_at),
+                    row.description,
+                    row.homepage,
+                    row.documentation,
+                    row.readme,
+                    row.repository,
+                    row.max_upload_size,
+                    row.max_features,
+                    row.trustpub_only,
+                ])
+            });
+        })
+        .crates_categories(|row| {
+            guard(&error, || {
+                crates_categories.execute(params![row.crate_id.0, row.category_id.0])
+            });
+        })
+        .crates_keywords(|row| {
+            guard(&error, || {
+                crates_keywords.execute(params![row.crate_id.0, row.keyword_id.0])
+            });
+        })
+        .default_versions(|row| {
+            guard(&error, || {
+                default_versions.execute(params![
+                    row.crate_id.0,
+                    row.version_id.0,
+                    row.num_versions,
+                ])
+            });
+        })
+        .deleted_crates(|row| {
+            guard(&error, || {
+                deleted_crates.execute(params![
+                    row.id.0,
+                    row.name,
+                    datetime(row.created_at),
+                    datetime(row.deleted_at),
+                    row.deleted_by.map(|user_id| user_id.0),
+                    row.message,
+                    datetime(row.available_at),
+                ])
+            });
+        })
+        .dependencies(|row| {
+            guard(&error, || {
+                dependencies.execute(params![
+                    row.id,
+                    row.version_id.0,
+                    row.crate_id.0,
+                    row.req.to_string(),
+                    row.optional,
+                    row.default_features,
+                    &*row.target,
+                    row.kind.as_u8(),
+                    row.explicit_name,
+                ])?;
+                for feature in &row.features {
+                    dependency_features.execute(params![row.id, &**feature])?;
+                }
+                Ok(())
+            });
+        })
+        .keywords(|row| {
+            guard(&error, || {
+                keywords.execute(params![
+                    row.id.0,
+                    row.keyword,
+                    row.crates_cnt,
+                    datetime(row.created_at),
+                ])
+            });
+        })
+        .metadata(|row| {
+            guard(&error, || metadata.execute(params![row.total_downloads]));
+        })
+        .reserved_crate_names(|row| {
+            guard(&error, || reserved_crate_names.execute(params![row.name]));
+        })
+        .teams(|row| {
+            guard(&error, || {
+                teams.execute(params![
+                    row.id.0,
+                    row.login,
+                    row.github_id,
+                    row.name,
+                    row.avatar,
+                    row.org_id,
+                ])
+            });
+        })
+        .users(|row| {
+            guard(&error, || {
+                users.execute(params![
+                    row.id.0,
+                    row.gh_login,
+                    row.name,
+                    row.gh_avatar,
+                    row.gh_id,
+                ])
+            });
+        })
+        .version_downloads(|row| {
+            guard(&error, || {
+                version_downloads.execute(params![
+                    row.version_id.0,
+                    row.downloads,
+                    row.date.to_string(),
+                ])
+            });
+        })
+        .versions(|row| {
+            guard(&error, || {
+                versions.execute(params![
+                    row.id.0,
+                    row.crate_id.0,
+                    row.num.to_string(),
+                    datetime(row.updated_at),
+                    datetime(row.created_at),
+                    row.downloads,
+                    row.yanked,
+                    &*row.license,
+                    row.crate_size,
+                    row.published_by.map(|user_id| user_id.0),
+                    row.checksum.as_ref().map(<[u8; 32]>::as_slice),
+                    row.links,
+                    row.rust_version.as_ref().map(ToString::to_string),
+                    row.has_lib,
+                    row.edition,
+                    row.description,
+                    row.homepage,
+                    row.documentation,
+                    row.repository,
+                ])?;
+                version_children.insert(&row)
+            });
+        })
+        .load(dump)?;
+
+    match error.into_inner() {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
+}
+
+fn guard<T>(error: &RefCell<Option<rusqlite::Error>>, f: impl FnOnce() -> rusqlite::Result<T>) {
+    if error.borrow().is_none() {
+        if let Err(e) = f() {
+            *error.borrow_mut() = Some(e);
+        }
+    }
+}
+
+struct VersionChildren<'conn> {
+    features: Statement<'conn>,
+    feature_values: Statement<'conn>,
+    bin_names: Statement<'conn>,
+    keywords: Statement<'conn>,
+    categories: Statement<'conn>,
+}
+
+impl VersionChildren<'_> {
+    fn insert(&mut self, row: &crate::versions::Row) -> rusqlite::Result<()> {
+        let version_id = row.id.0;
+        for (feature, values) in &row.features {
+            self.features.execute(params![version_id, &**feature])?;
+            for value in values {
+                self.feature_values
+                    .execute(params![version_id, &**feature, &**value])?;
+            }
+        }
+        for bin_name in &row.bin_names {
+            self.bin_names.execute(params![version_id, bin_name])?;
+        }
+        for keyword in &row.keywords {
+            self.keywords.execute(params![version_id, &**keyword])?;
+        }
+        for category in &row.categories {
+            self.categories.execute(params![version_id, &**category])?;
+        }
+        Ok(())
+    }
+}
+
+fn datetime(datetime: DateTime<Utc>) -> String {
+    datetime.format("%Y-%m-%d %H:%M:%S%.6f").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dependencies::DependencyKind;
+    use crate::testing::DumpBuilder;
+    use crate::Date;
+    use rusqlite::Connection;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_export() {
+        let mut builder = DumpBuilder::new();
+        let alice = builder.user("alice").id;
+        let serde = builder.krate("serde").id;
+        let serde_v1 = builder.version(serde, "1.0.0");
+        serde_v1.checksum = Some([0xab; 32]);
+        serde_v1
+            .features
+            .insert(Arc::from("default"), vec![Arc::from("std")]);
+        serde_v1.features.insert(Arc::from("std"), Vec::new());
+        serde_v1.keywords = vec![Arc::from("serde")];
+        let serde_v1 = serde_v1.id;
+        builder.owner(serde, alice);
+        builder.keyword(serde, "serde");
+        builder.download_series(serde_v1, Date::from_ymd(2024, 1, 1), &[3, 4]);
+        let json = builder.krate("serde_json").id;
+        let json_v1 = builder.version(json, "1.0.0").id;
+        let dep = builder.dependency(json_v1, serde, "^1.0");
+        dep.features = vec![Arc::from("derive")];
+        dep.kind = DependencyKind::Build;
+        let archive = builder.temp_archive().unwrap();
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let database = tempdir.path().join("crates.db");
+        super::export(&archive, &database).unwrap();
+        let conn = Connection::open(&database).unwrap();
+        let query = |sql: &str| -> String { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+
+        assert_eq!(
+            query("SELECT group_concat(name) FROM (SELECT name FROM crates ORDER BY id)"),
+            "serde,serde_json",
+        );
+        assert_eq!(
+            query(
+                "SELECT c.name || ' ' || v.num || ' ' || d.req || ' ' || d.kind
+                FROM dependencies d
+                JOIN versions v ON v.id = d.version_id
+                JOIN crates c ON c.id = v.crate_id",
+            ),
+            "serde_json 1.0.0 ^1.0 1",
+        );
+        assert_eq!(
+            query("SELECT hex(checksum) FROM versions WHERE id = 1"),
+            "AB".repeat(32),
+        );
+        assert_eq!(
+            query("SELECT group_concat(feature || '=' || value) FROM version_feature_values"),
+            "default=std",
+        );
+        assert_eq!(
+            query("SELECT group_concat(feature) FROM version_features"),
+            "default,std",
+        );
+        assert_eq!(query("SELECT feature FROM dependency_features"), "derive");
+        assert_eq!(
+            query("SELECT u.gh_login FROM crate_owners o JOIN users u ON u.id = o.owner_id"),
+            "alice",
+        );
+        assert_eq!(
+            query("SELECT max(date) || ' ' || sum(downloads) FROM version_downloads"),
+            "2024-01-02 7",
+        );
+        assert_eq!(
+            query("SELECT created_at FROM crates WHERE id = 1"),
+            "2024-01-01 00:00:00.000000",
+        );
+        drop(conn);
+
+        // The tables already exist.
+        assert!(super::export(&archive, &database).is_err());
+    }
+
+    #[test]
+    fn test_rollback() {
+        let archive = DumpBuilder::new().temp_archive().unwrap();
+        let tempdir = tempfile::tempdir().unwrap();
+        let database = tempdir.path().join("crates.db");
+        let conn = Connection::open(&database).unwrap();
+        conn.execute_batch("CREATE TABLE versions (x); INSERT INTO versions VALUES (1);")
+            .unwrap();
+        drop(conn);
+
+        // Fails part-way through creating the schema, after creating the
+        // tables that come before versions.
+        assert!(super::export(&archive, &database).is_err());
+
+        let conn = Connection::open(&database).unwrap();
+        let tables: String = conn
+            .query_row(
+                "SELECT group_concat(name) FROM sqlite_schema WHERE type = 'table'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tables, "versions");
+        let x: i64 = conn
+            .query_row("SELECT x FROM versions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(x, 1);
+    }
+}
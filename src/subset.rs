@@ -0,0 +1,444 @@
+//! Extraction of a small self-contained dump around chosen crates.
+//!
+//! [`Subset`] keeps the chosen crates and everything they transitively depend
+//! on, together with exactly the rows that those crates reference or that
+//! reference them: versions, dependencies, owners, the users and teams who own
+//! or published them, keywords, categories, and download counts. The result
+//! passes [`DbDump::validate`], and written with
+//! [`Writer`](crate::Writer) it is an archive that
+//! [`load_all`](crate::load_all) accepts.
+//!
+//! ```no_run
+//! use chrono::Utc;
+//! use db_dump::subset::Subset;
+//!
+//! fn main() -> db_dump::Result<()> {
+//!     let db = Subset::new().krate("serde_json").extract("./db-dump.tar.gz")?;
+//!
+//!     let mut writer = db_dump::Writer::new(Utc::now());
+//!     writer.dump(&db)?;
+//!     writer.write("./db-dump-serde_json.tar.gz")?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::categories::CategoryId;
+use crate::crate_owners::OwnerId;
+use crate::crates::CrateId;
+use crate::dependencies::DependencyKind;
+use crate::error::{err, Result};
+use crate::keywords::KeywordId;
+use crate::teams::TeamId;
+use crate::users::UserId;
+use crate::versions::VersionId;
+use crate::{DbDump, IdMap, IdSet, Loader};
+use std::cell::RefCell;
+use std::collections::BTreeMap as Map;
+use std::path::Path;
+
+/// Selection of crates around which to extract a subset of a dump.
+pub struct Subset {
+    crates: Vec<String>,
+    dev_dependencies: bool,
+}
+
+impl Subset {
+    pub fn new() -> Self {
+        Subset {
+            crates: Vec::new(),
+            dev_dependencies: false,
+        }
+    }
+
+    /// Include the crate with the given name, and its transitive
+    /// dependencies.
+    pub fn krate(&mut self, name: &str) -> &mut Self {
+        self.crates.push(name.to_owned());
+        self
+    }
+
+    /// Whether to also follow dev-dependencies when collecting transitive
+    /// dependencies. Defaults to false, in which case a dev-dependency is
+    /// kept only if the crate it refers to is kept for some other reason.
+    pub fn dev_dependencies(&mut self, include: bool) -> &mut Self {
+        self.dev_dependencies = include;
+        self
+    }
+
+    /// Extract the subset from the db-dump.tar.gz at the given path.
+    ///
+    /// This makes two passes over the archive: the first to resolve the
+    /// dependency graph, and the second to collect the rows belonging to the
+    /// subset. The first pass holds every crate's name and the ids of every
+    /// version and dependency edge. The second pass holds every row of the
+    /// users, teams, keywords and categories tables, which are only pruned
+    /// once the rest of the subset is known, but of the larger tables only
+    /// the rows that are kept.
+    pub fn extract(&self, path: impl AsRef<Path>) -> Result<DbDump> {
+        let path = path.as_ref();
+
+        let mut graph = Graph::default();
+        let mut dependencies = Vec::new();
+        Loader::new()
+            .crates(|row| {
+                graph.crate_ids.insert(row.name, row.id);
+            })
+            .versions(|row| {
+                graph.versions.insert(row.id, row.crate_id);
+            })
+            .dependencies(|row| {
+                if self.follow(row.kind) {
+                    dependencies.push((row.version_id, row.crate_id));
+                }
+            })
+            .load(path)?;
+        graph.dependencies(dependencies);
+        let selection = graph.select(&self.crates)?;
+
+        let filter = RefCell::new(Filter::new(&selection));
+        Loader::new()
+            .categories(|row| filter.borrow_mut().db.categories.push(row))
+            .crate_downloads(|row| filter.borrow_mut().crate_downloads(row))
+            .crate_owners(|row| filter.borrow_mut().crate_owners(row))
+            .crates(|row| filter.borrow_mut().crates(row))
+            .crates_categories(|row| filter.borrow_mut().crates_categories(row))
+            .crates_keywords(|row| filter.borrow_mut().crates_keywords(row))
+            .default_versions(|row| filter.borrow_mut().default_versions(row))
+            .dependencies(|row| filter.borrow_mut().dependencies(row))
+            .keywords(|row| filter.borrow_mut().db.keywords.push(row))
+            .teams(|row| filter.borrow_mut().db.teams.push(row))
+            .users(|row| filter.borrow_mut().db.users.push(row))
+            .version_downloads(|row| filter.borrow_mut().version_downloads(row))
+            .versions(|row| filter.borrow_mut().versions(row))
+            .load(path)?;
+        Ok(filter.into_inner().finish())
+    }
+
+    /// Extract the subset from a dump that is already loaded.
+    pub fn extract_from(&self, db: &DbDump) -> Result<DbDump> {
+        let mut graph = Graph::default();
+        for row in &db.crates {
+            graph.crate_ids.insert(row.name.clone(), row.id);
+        }
+        for row in &db.versions {
+            graph.versions.insert(row.id, row.crate_id);
+        }
+        graph.dependencies(
+            db.dependencies
+                .iter()
+                .filter(|row| self.follow(row.kind))
+                .map(|row| (row.version_id, row.crate_id)),
+        );
+        let selection = graph.select(&self.crates)?;
+
+        let mut filter = Filter::new(&selection);
+        filter.db.categories.clone_from(&db.categories);
+        filter.db.keywords.clone_from(&db.keywords);
+        filter.db.teams.clone_from(&db.teams);
+        filter.db.users.clone_from(&db.users);
+        for row in &db.crate_downloads {
+            filter.crate_downloads(row.clone());
+        }
+        for row in &db.crate_owners {
+            filter.crate_owners(row.clone());
+        }
+        for row in &db.crates {
+            filter.crates(row.clone());
+        }
+        for row in &db.crates_categories {
+            filter.crates_categories(row.clone());
+        }
+        for row in &db.crates_keywords {
+            filter.crates_keywords(row.clone());
+        }
+        for row in &db.default_versions {
+            filter.default_versions(row.clone());
+        }
+        for row in &db.dependencies {
+            filter.dependencies(row.clone());
+        }
+        for row in &db.version_downloads {
+            filter.version_downloads(row);
+        }
+        for row in &db.versions {
+            filter.versions(row.clone());
+        }
+        Ok(filter.finish())
+    }
+
+    fn follow(&self, kind: DependencyKind) -> bool {
+        self.dev_dependencies || !matches!(kind, DependencyKind::Dev)
+    }
+}
+
+impl Default for Subset {
+    fn default() -> Self {
+        Subset::new()
+    }
+}
+
+#[derive(Default)]
+struct Graph {
+    crate_ids: Map<String, CrateId>,
+    versions: IdMap<VersionId, CrateId>,
+    dependencies: IdMap<CrateId, Vec<CrateId>>,
+}
+
+struct Selection {
+    crates: IdSet<CrateId>,
+    versions: IdSet<VersionId>,
+}
+
+impl Graph {
+    fn dependencies(&mut self, dependencies: impl IntoIterator<Item = (VersionId, CrateId)>) {
+        for (version_id, dependency) in dependencies {
+            if let Some(&crate_id) = self.versions.get(version_id) {
+                self.dependencies
+                    .entry(crate_id)
+                    .or_default()
+                    .push(dependency);
+            }
+        }
+    }
+
+    fn select(&self, roots: &[String]) -> Result<Selection> {
+        let mut crates = IdSet::new();
+        let mut stack = Vec::new();
+        for name in roots {
+            match self.crate_ids.get(name) {
+                Some(&crate_id) => stack.push(crate_id),
+                None => return Err(err(format_args!("no crate named `{}` in dump", name))),
+            }
+        }
+        while let Some(crate_id) = stack.pop() {
+            if crates.insert(crate_id) {
+                if let Some(dependencies) = self.dependencies.get(crate_id) {
+                    stack.extend_from_slice(dependencies);
+                }
+            }
+        }
+
+        let mut versions = IdSet::new();
+        for (version_id, &crate_id) in &self.versions {
+            if crates.contains(crate_id) {
+                versions.insert(version_id);
+            }
+        }
+
+        Ok(Selection { crates, versions })
+    }
+}
+
+// Accumulates the rows of the subset. Rows of users, teams, keywords and
+// categories are taken unfiltered, and pruned in `finish` to the ones that
+// turn out to be referenced.
+struct Filter<'a> {
+    selection: &'a Selection,
+    db: DbDump,
+}
+
+impl<'a> Filter<'a> {
+    fn new(selection: &'a Selection) -> Self {
+        Filter {
+            selection,
+            db: DbDump::default(),
+        }
+    }
+
+    fn crate_downloads(&mut self, row: crate::crate_downloads::Row) {
+        if self.selection.crates.contains(row.crate_id) {
+            self.db.crate_downloads.push(row);
+        }
+    }
+
+    fn crate_owners(&mut self, row: crate::crate_owners::Row) {
+        if self.selection.crates.contains(row.crate_id) {
+            self.db.crate_owners.push(row);
+        }
+    }
+
+    fn crates(&mut self, row: crate::crates::Row) {
+        if self.selection.crates.contains(row.id) {
+            self.db.crates.push(row);
+        }
+    }
+
+    fn crates_categories(&mut self, row: crate::crates_categories::Row) {
+        if self.selection.crates.contains(row.crate_id) {
+            self.db.crates_categories.push(row);
+        }
+    }
+
+    fn crates_keywords(&mut self, row: crate::crates_keywords::Row) {
+        if self.selection.crates.contains(row.crate_id) {
+            self.db.crates_keywords.push(row);
+        }
+    }
+
+    fn default_versions(&mut self, row: crate::default_versions::Row) {
+        if self.selection.crates.contains(row.crate_id) {
+            self.db.default_versions.push(row);
+        }
+    }
+
+    fn dependencies(&mut self, row: crate::dependencies::Row) {
+        if self.selection.versions.contains(row.version_id)
+            && self.selection.crates.contains(row.crate_id)
+        {
+            self.db.dependencies.push(row);
+        }
+    }
+
+    fn version_downloads(&mut self, row: crate::version_downloads::Row) {
+        if self.selection.versions.contains(row.version_id) {
+            self.db.version_downloads.push(row);
+        }
+    }
+
+    fn versions(&mut self, row: crate::versions::Row) {
+        if self.selection.versions.contains(row.id) {
+            self.db.versions.push(row);
+        }
+    }
+
+    fn finish(self) -> DbDump {
+        let mut db = self.db;
+
+        let mut users = IdSet::<UserId>::new();
+        let mut teams = IdSet::<TeamId>::new();
+        for row in &db.crate_owners {
+            match row.owner_id {
+                OwnerId::User(user_id) => users.insert(user_id),
+                OwnerId::Team(team_id) => teams.insert(team_id),
+            };
+            if let Some(created_by) = row.created_by {
+                users.insert(created_by);
+            }
+        }
+        for row in &db.versions {
+            if let Some(published_by) = row.published_by {
+                users.insert(published_by);
+            }
+        }
+        db.users.retain(|row| users.contains(row.id));
+        db.teams.retain(|row| teams.contains(row.id));
+
+        // Counters over the crates of the subset only.
+        let mut keywords = IdMap::<KeywordId, u32>::new();
+        for row in &db.crates_keywords {
+            *keywords.entry(row.keyword_id).or_default() += 1;
+        }
+        db.keywords.retain_mut(|row| match keywords.get(row.id) {
+            Some(&crates_cnt) => {
+                row.crates_cnt = crates_cnt;
+                true
+            }
+            None => false,
+        });
+        let mut categories = IdMap::<CategoryId, u32>::new();
+        for row in &db.crates_categories {
+            *categories.entry(row.category_id).or_default() += 1;
+        }
+        db.categories
+            .retain_mut(|row| match categories.get(row.id) {
+                Some(&crates_cnt) => {
+                    row.crates_cnt = crates_cnt;
+                    true
+                }
+                None => false,
+            });
+        db.metadata.total_downloads = db.crate_downloads.iter().map(|row| row.downloads).sum();
+
+        db
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Subset;
+    use crate::crate_owners::OwnerId;
+    use crate::dependencies::DependencyKind;
+    use crate::testing::DumpBuilder;
+    use crate::Date;
+
+    #[test]
+    fn test_subset() {
+        let mut builder = DumpBuilder::new();
+        let alice = builder.user("alice").id;
+        let bob = builder.user("bob").id;
+        let carol = builder.user("carol").id;
+        let team = builder.team("github:rust-lang:libs").id;
+
+        let core = builder.krate("core-lib").id;
+        let core_v1 = builder.version(core, "1.0.0").id;
+        builder.owner(core, team);
+        builder.keyword(core, "core");
+        builder.download_series(core_v1, Date::from_ymd(2024, 1, 1), &[1, 2]);
+
+        let mid = builder.krate("mid").id;
+        let mid_v1 = builder.version(mid, "0.1.0").id;
+        builder.version(mid, "0.2.0").published_by = Some(bob);
+        builder.dependency(mid_v1, core, "^1");
+        builder.owner(mid, alice);
+        builder.keyword(mid, "core");
+        builder.category(mid, "encoding");
+
+        let test_helper = builder.krate("test-helper").id;
+        builder.version(test_helper, "1.0.0");
+        builder.owner(test_helper, carol);
+        builder.keyword(test_helper, "testing");
+
+        let app = builder.krate("app").id;
+        let app_v1 = builder.version(app, "1.0.0").id;
+        builder.dependency(app_v1, mid, "^0.1");
+        builder.dependency(app_v1, test_helper, "^1").kind = DependencyKind::Dev;
+        builder.owner(app, alice);
+        builder.downloads(app_v1, Date::from_ymd(2024, 1, 1), 7);
+
+        let unrelated = builder.krate("unrelated").id;
+        builder.version(unrelated, "1.0.0");
+        builder.owner(unrelated, carol);
+        builder.deleted_crate("gone");
+
+        let db = builder.build();
+        let subset = Subset::new().krate("app").extract_from(&db).unwrap();
+
+        let crates: Vec<&str> = subset.crates.iter().map(|row| row.name.as_str()).collect();
+        assert_eq!(crates, ["core-lib", "mid", "app"]);
+        assert_eq!(subset.versions.len(), 4);
+        // The dev-dependency on test-helper is not followed.
+        assert_eq!(subset.dependencies.len(), 2);
+        let users: Vec<&str> = subset
+            .users
+            .iter()
+            .map(|row| row.gh_login.as_str())
+            .collect();
+        assert_eq!(users, ["alice", "bob"]);
+        assert_eq!(subset.teams.len(), 1);
+        assert!(matches!(subset.crate_owners[0].owner_id, OwnerId::Team(_)));
+        assert_eq!(subset.keywords.len(), 1);
+        assert_eq!(subset.keywords[0].crates_cnt, 2);
+        assert_eq!(subset.categories.len(), 1);
+        assert_eq!(subset.version_downloads.len(), 3);
+        assert_eq!(subset.metadata.total_downloads, 10);
+        assert!(subset.deleted_crates.is_empty());
+        assert!(subset.validate().is_empty());
+        assert!(subset.check_counters().is_empty());
+
+        let archive = builder.temp_archive().unwrap();
+        let extracted = Subset::new()
+            .krate("app")
+            .dev_dependencies(true)
+            .extract(&archive)
+            .unwrap();
+        assert_eq!(extracted.crates.len(), 4);
+        assert_eq!(extracted.dependencies.len(), 3);
+        assert_eq!(extracted.users.len(), 3);
+        assert!(extracted.validate().is_empty());
+
+        let Err(error) = Subset::new().krate("missing").extract_from(&db) else {
+            panic!("expected error for nonexistent crate");
+        };
+        assert_eq!(error.to_string(), "no crate named `missing` in dump");
+    }
+}
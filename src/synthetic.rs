@@ -0,0 +1,1080 @@
+//! Deterministic synthetic dumps at realistic scale.
+//!
+//! [`Generator`] produces a complete, internally consistent dump from a seed,
+//! for benchmarking [`Loader`](crate::Loader) and analyses without depending
+//! on a real 1GB download. The same seed and configuration always produce the
+//! same dump.
+//!
+//! The distributions are modeled loosely on crates.io:
+//!
+//! - A crate's popularity is heavy-tailed (Pareto). Popular crates are both
+//!   downloaded more and depended upon more often.
+//! - Versions follow semver progressions: 0.x series bumping minor for
+//!   breaking changes, occasional 1.0 releases and major bumps, and prerelease
+//!   trains like `2.0.0-alpha.1` → `2.0.0-beta.1` → `2.0.0`.
+//! - Crates depend only on crates published before them, on a version that
+//!   existed at the time, with requirements like `^1.2` or `^0.4.7`.
+//! - Daily downloads cover the most recent versions of each crate, with a
+//!   weekly cycle, and all-time version downloads include history predating
+//!   the daily window.
+//!
+//! ```
+//! use db_dump::synthetic::Generator;
+//!
+//! let db = Generator::new(42)
+//!     .crates(200)
+//!     .versions_per_crate(5.0)
+//!     .dependencies_per_version(3.0)
+//!     .download_days(30)
+//!     .generate();
+//!
+//! assert_eq!(db.crates.len(), 200);
+//! assert!(db.validate().is_empty());
+//! assert!(db.check_counters().is_empty());
+//! ```
+
+// Sampling converts between floats and integers throughout.
+#![allow(
+    clippy::cast_possible_wrap,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+
+use crate::categories::CategoryId;
+use crate::crate_owners::OwnerId;
+use crate::crates::CrateId;
+use crate::deleted_crates::DeletedCrateId;
+use crate::dependencies::DependencyKind;
+use crate::error::Result;
+use crate::keywords::KeywordId;
+use crate::teams::TeamId;
+use crate::users::UserId;
+use crate::versions::VersionId;
+use crate::{Date, DbDump, Writer};
+use chrono::{DateTime, Datelike, TimeDelta, Utc, Weekday};
+use semver::{Prerelease, Version, VersionReq};
+use std::collections::{BTreeMap as Map, HashSet as Set};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Seeded generator of a synthetic [`DbDump`].
+///
+/// ```no_run
+/// # fn main() -> db_dump::Result<()> {
+/// db_dump::synthetic::Generator::new(1)
+///     .crates(150_000)
+///     .write("./synthetic-db-dump.tar.gz")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Generator {
+    seed: u64,
+    crates: usize,
+    versions_per_crate: f64,
+    dependencies_per_version: f64,
+    download_days: u32,
+    users: Option<usize>,
+    timestamp: DateTime<Utc>,
+}
+
+// How far before the dump's timestamp the oldest crate is published.
+const HISTORY_DAYS: i64 = 10 * 365;
+
+// How many of each crate's most recent versions receive daily downloads.
+const DOWNLOADED_VERSIONS: usize = 5;
+
+const MAX_VERSIONS: usize = 1000;
+const MAX_DEPENDENCIES: usize = 100;
+
+impl Generator {
+    /// A generator of 1000 crates, with 10 versions and 6 dependencies per
+    /// version on average and 90 days of download history, like crates.io
+    /// retains.
+    pub fn new(seed: u64) -> Self {
+        Generator {
+            seed,
+            crates: 1000,
+            versions_per_crate: 10.0,
+            dependencies_per_version: 6.0,
+            download_days: 90,
+            users: None,
+            timestamp: DateTime::from_timestamp(1_704_067_200, 0).unwrap(),
+        }
+    }
+
+    /// Number of crates.
+    pub fn crates(&mut self, crates: usize) -> &mut Self {
+        self.crates = crates;
+        self
+    }
+
+    /// Average number of versions per crate. Every crate has at least one.
+    pub fn versions_per_crate(&mut self, mean: f64) -> &mut Self {
+        self.versions_per_crate = mean;
+        self
+    }
+
+    /// Average number of dependencies per version.
+    pub fn dependencies_per_version(&mut self, mean: f64) -> &mut Self {
+        self.dependencies_per_version = mean;
+        self
+    }
+
+    /// Number of days, ending the day before the timestamp, covered by
+    /// version_downloads.
+    pub fn download_days(&mut self, days: u32) -> &mut Self {
+        self.download_days = days;
+        self
+    }
+
+    /// Number of users. Defaults to one third of the number of crates.
+    pub fn users(&mut self, users: usize) -> &mut Self {
+        self.users = Some(users);
+        self
+    }
+
+    /// Time at which the dump is taken. No row is dated later than this.
+    /// Defaults to 2024-01-01 00:00:00 UTC.
+    pub fn timestamp(&mut self, timestamp: DateTime<Utc>) -> &mut Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn generate(&self) -> DbDump {
+        let mut state = State {
+            config: self,
+            rng: Rng::new(self.seed),
+            db: DbDump::default(),
+            names: RESERVED_CRATE_NAMES
+                .iter()
+                .map(|&name| name.to_owned())
+                .collect(),
+            user_weights: Weighted::default(),
+            crate_weights: Weighted::default(),
+            crate_versions: Vec::new(),
+            start: self.timestamp - TimeDelta::days(HISTORY_DAYS),
+        };
+        state.users();
+        state.teams();
+        state.keywords_and_categories();
+        state.crates();
+        state.deleted_crates();
+        state.downloads();
+
+        let mut db = state.db;
+        crate::testing::derive(&mut db);
+        db
+    }
+
+    /// Write the generated dump as a db-dump.tar.gz archive to the given
+    /// path.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = Writer::new(self.timestamp);
+        writer.dump(&self.generate())?;
+        writer.write(path)
+    }
+}
+
+struct State<'a> {
+    config: &'a Generator,
+    rng: Rng,
+    db: DbDump,
+    names: Set<String>,
+    user_weights: Weighted,
+    crate_weights: Weighted,
+    // Indices into db.versions of each crate's versions, oldest first.
+    crate_versions: Vec<Range<usize>>,
+    start: DateTime<Utc>,
+}
+
+impl State<'_> {
+    fn users(&mut self) {
+        let count = self.config.users.unwrap_or(self.config.crates / 3).max(1);
+        let mut logins = Set::new();
+        for i in 1..=count {
+            let syllables = 1 + self.rng.below(2);
+            let mut login = self.rng.word(syllables);
+            if self.rng.chance(0.3) {
+                login += &self.rng.below(100).to_string();
+            }
+            while !logins.insert(login.clone()) {
+                login += &self.rng.below(10).to_string();
+            }
+            let name = self.rng.chance(0.6).then(|| {
+                let first = self.rng.pick(FIRST_NAMES);
+                let last = capitalize(&self.rng.word(2));
+                format!("{} {}", first, last)
+            });
+            let gh_id = 10_000 + i as i32;
+            self.db.users.push(crate::users::Row {
+                id: UserId(i as u32),
+                gh_login: login,
+                name,
+                gh_avatar: format!("https://avatars.githubusercontent.com/u/{}?v=4", gh_id),
+                gh_id,
+            });
+            // A few prolific users publish most of the crates.
+            let weight = self.rng.pareto(1.2);
+            self.user_weights.push(weight);
+        }
+    }
+
+    fn teams(&mut self) {
+        let mut orgs = Set::new();
+        for i in 1..=self.config.crates / 50 {
+            let mut org = self.rng.word(2) + "-rs";
+            while !orgs.insert(org.clone()) {
+                org.insert(0, 'x');
+            }
+            let team = self.rng.pick(&["owners", "publish", "maintainers", "core"]);
+            let github_id = 100_000 + i as u32;
+            self.db.teams.push(crate::teams::Row {
+                id: TeamId(i as u32),
+                login: format!("github:{}:{}", org, team),
+                github_id,
+                name: team.to_owned(),
+                avatar: format!("https://avatars.githubusercontent.com/u/{}?v=4", github_id),
+                org_id: Some(200_000 + i as u32),
+            });
+        }
+    }
+
+    fn keywords_and_categories(&mut self) {
+        for (i, &keyword) in KEYWORDS.iter().enumerate() {
+            self.db.keywords.push(crate::keywords::Row {
+                id: KeywordId(i as u32 + 1),
+                keyword: keyword.to_owned(),
+                crates_cnt: 0,
+                created_at: self.start,
+            });
+        }
+        for (i, &(slug, category)) in CATEGORIES.iter().enumerate() {
+            self.db.categories.push(crate::categories::Row {
+                id: CategoryId(i as u32 + 1),
+                category: category.to_owned(),
+                slug: slug.to_owned(),
+                description: format!("Crates for {}.", category.to_lowercase()),
+                crates_cnt: 0,
+                created_at: self.start,
+                path: format!("root.{}", slug.replace('-', "_").replace("::", ".")),
+            });
+        }
+    }
+
+    fn crates(&mut self) {
+        let end = self.config.timestamp;
+        // The rate of new crates grows over time.
+        let mut created: Vec<DateTime<Utc>> = (0..self.config.crates)
+            .map(|_| {
+                let fraction = self.rng.f64().sqrt();
+                self.start + scale(end - self.start, fraction)
+            })
+            .collect();
+        created.sort();
+
+        let licenses: Vec<(Arc<str>, f64)> = LICENSES
+            .iter()
+            .map(|&(license, weight)| (Arc::from(license), weight))
+            .collect();
+        let targets: Vec<Arc<str>> = TARGETS.iter().map(|&target| Arc::from(target)).collect();
+        let default_features = (Arc::<str>::from("default"), Arc::<str>::from("std"));
+
+        for (i, created_at) in created.into_iter().enumerate() {
+            let crate_id = CrateId(i as u32 + 1);
+            let name = self.crate_name();
+            let popularity = self.rng.pareto(1.1);
+            self.crate_weights.push(popularity);
+
+            // Owners.
+            let mut owners = Vec::new();
+            let num_users = if self.rng.chance(0.2) { 2 } else { 1 };
+            for _ in 0..num_users {
+                let user_id =
+                    UserId(self.user_weights.sample(&mut self.rng, usize::MAX) as u32 + 1);
+                if !owners.contains(&user_id) {
+                    owners.push(user_id);
+                }
+            }
+            for (k, &user_id) in owners.iter().enumerate() {
+                self.db.crate_owners.push(crate::crate_owners::Row {
+                    crate_id,
+                    owner_id: OwnerId::User(user_id),
+                    created_at,
+                    created_by: (k > 0).then_some(owners[0]),
+                });
+            }
+            if !self.db.teams.is_empty() && self.rng.chance(0.08) {
+                let team_id = TeamId(self.rng.below(self.db.teams.len()) as u32 + 1);
+                self.db.crate_owners.push(crate::crate_owners::Row {
+                    crate_id,
+                    owner_id: OwnerId::Team(team_id),
+                    created_at,
+                    created_by: Some(owners[0]),
+                });
+            }
+
+            // Keywords and categories.
+            let mut keywords = Vec::new();
+            for _ in 0..self.rng.below(6) {
+                let k = self.rng.zipf(KEYWORDS.len());
+                if !keywords.contains(&k) {
+                    keywords.push(k);
+                    self.db.crates_keywords.push(crate::crates_keywords::Row {
+                        crate_id,
+                        keyword_id: KeywordId(k as u32 + 1),
+                    });
+                }
+            }
+            let mut categories = Vec::new();
+            for _ in 0..self.rng.below(3) {
+                let c = self.rng.zipf(CATEGORIES.len());
+                if !categories.contains(&c) {
+                    categories.push(c);
+                    self.db
+                        .crates_categories
+                        .push(crate::crates_categories::Row {
+                            crate_id,
+                            category_id: CategoryId(c as u32 + 1),
+                        });
+                }
+            }
+            let keywords: Vec<Arc<str>> =
+                keywords.iter().map(|&k| Arc::from(KEYWORDS[k])).collect();
+            let categories: Vec<Arc<str>> = categories
+                .iter()
+                .map(|&c| Arc::from(CATEGORIES[c].0))
+                .collect();
+
+            // Dependencies, upon crates published earlier. Each version
+            // carries over most of the crate's dependencies.
+            let mean = self.config.dependencies_per_version;
+            let num_dependencies = (self.rng.geometric(mean + 1.0) - 1)
+                .min(i)
+                .min(MAX_DEPENDENCIES);
+            let mut dependencies: Vec<Dependency> = Vec::new();
+            for _ in 0..num_dependencies * 2 {
+                if dependencies.len() == num_dependencies {
+                    break;
+                }
+                let target = self.crate_weights.sample(&mut self.rng, i);
+                if dependencies.iter().any(|dep| dep.target == target) {
+                    continue;
+                }
+                let kind = match self.rng.below(20) {
+                    0 => DependencyKind::Build,
+                    1..=3 => DependencyKind::Dev,
+                    _ => DependencyKind::Normal,
+                };
+                let optional = matches!(kind, DependencyKind::Normal) && self.rng.chance(0.1);
+                dependencies.push(Dependency {
+                    target,
+                    kind,
+                    optional,
+                    default_features: self.rng.chance(0.9),
+                    target_cfg: if self.rng.chance(0.05) {
+                        Arc::clone(&targets[1 + self.rng.below(targets.len() - 1)])
+                    } else {
+                        Arc::clone(&targets[0])
+                    },
+                });
+            }
+
+            // Versions.
+            let description = format!(
+                "{} {} for Rust",
+                capitalize(self.rng.pick(ADJECTIVES)),
+                self.rng.pick(NOUNS),
+            );
+            let count = self
+                .rng
+                .geometric(self.config.versions_per_crate)
+                .min(MAX_VERSIONS);
+            let mut published: Vec<DateTime<Utc>> = (1..count)
+                .map(|_| created_at + scale(end - created_at, self.rng.f64()))
+                .collect();
+            published.sort();
+            published.insert(0, created_at);
+
+            let license = Arc::clone(&licenses[self.rng.weighted(&licenses)].0);
+            let has_std_feature = self.rng.chance(0.4);
+            let has_lib = self.rng.chance(0.9);
+            let bin_names = if !has_lib || self.rng.chance(0.05) {
+                vec![name.clone()]
+            } else {
+                Vec::new()
+            };
+            let user_owners = owners;
+            let first = self.db.versions.len();
+            let mut num = initial_version(&mut self.rng);
+            for (k, &created_at) in published.iter().enumerate() {
+                if k > 0 {
+                    num = next_version(&mut self.rng, &num);
+                }
+                let version_id = VersionId(self.db.versions.len() as u32 + 1);
+                let yanked = self.rng.chance(0.02);
+                let mut features = Map::new();
+                if has_std_feature {
+                    let (default, std) = &default_features;
+                    features.insert(Arc::clone(default), vec![Arc::clone(std)]);
+                    features.insert(Arc::clone(std), Vec::new());
+                }
+                let crate_size = (self.rng.normal() * 1.2 + 10.0).exp() as u64;
+                let mut checksum = [0u8; 32];
+                for chunk in checksum.chunks_mut(8) {
+                    chunk.copy_from_slice(&self.rng.next_u64().to_le_bytes());
+                }
+                let rust_version = self.rng.chance(0.2).then(|| {
+                    let minor = rust_minor(created_at);
+                    Version::new(1, minor.saturating_sub(self.rng.below(10) as u64), 0)
+                });
+                self.db.versions.push(crate::versions::Row {
+                    id: version_id,
+                    crate_id,
+                    num: num.clone(),
+                    updated_at: if yanked {
+                        created_at + scale(end - created_at, self.rng.f64())
+                    } else {
+                        created_at
+                    },
+                    created_at,
+                    downloads: 0,
+                    features,
+                    yanked,
+                    license: Arc::clone(&license),
+                    crate_size: Some(crate_size),
+                    published_by: Some(self.rng.pick(&user_owners)),
+                    checksum: Some(checksum),
+                    links: None,
+                    rust_version,
+                    has_lib,
+                    bin_names: bin_names.clone(),
+                    edition: Some(edition(created_at)),
+                    description: Some(description.clone()),
+                    homepage: None,
+                    documentation: None,
+                    repository: None,
+                    categories: categories.clone(),
+                    keywords: keywords.clone(),
+                });
+
+                for dependency in &dependencies {
+                    if k > 0 && self.rng.chance(0.1) {
+                        continue;
+                    }
+                    let req = self.requirement(dependency.target, created_at);
+                    self.db.dependencies.push(crate::dependencies::Row {
+                        id: self.db.dependencies.len() as u32 + 1,
+                        version_id,
+                        crate_id: CrateId(dependency.target as u32 + 1),
+                        req,
+                        optional: dependency.optional,
+                        default_features: dependency.default_features,
+                        features: Vec::new(),
+                        target: Arc::clone(&dependency.target_cfg),
+                        kind: dependency.kind,
+                        explicit_name: None,
+                    });
+                }
+            }
+            self.crate_versions.push(first..self.db.versions.len());
+
+            let login = &self.db.users[user_owners[0].0 as usize - 1].gh_login;
+            let repository = self
+                .rng
+                .chance(0.8)
+                .then(|| format!("https://github.com/{}/{}", login, name));
+            let documentation = self
+                .rng
+                .chance(0.3)
+                .then(|| format!("https://docs.rs/{}", name));
+            self.db.crates.push(crate::crates::Row {
+                id: crate_id,
+                name,
+                updated_at: *published.last().unwrap(),
+                created_at,
+                description,
+                homepage: None,
+                documentation,
+                readme: None,
+                repository,
+                max_upload_size: None,
+                max_features: None,
+                trustpub_only: self.rng.chance(0.02),
+            });
+        }
+    }
+
+    // A requirement satisfied by the version of the given crate that was
+    // newest at the given time.
+    fn requirement(&mut self, target: usize, time: DateTime<Utc>) -> VersionReq {
+        let versions = &self.db.versions[self.crate_versions[target].clone()];
+        let existing = versions.partition_point(|version| version.created_at <= time);
+        let version = &versions[existing.saturating_sub(1)].num;
+        let req = if version.pre.is_empty() {
+            match self.rng.below(40) {
+                0 => "*".to_owned(),
+                1 => format!("={}", version),
+                2..=19 => format!("^{}.{}", version.major, version.minor),
+                _ => format!("^{}", version),
+            }
+        } else {
+            format!("^{}", version)
+        };
+        VersionReq::parse(&req).unwrap()
+    }
+
+    fn crate_name(&mut self) -> String {
+        let mut name = if !self.db.crates.is_empty() && self.rng.chance(0.1) {
+            // Companion of an existing popular crate, like serde_derive.
+            let base = self
+                .crate_weights
+                .sample(&mut self.rng, self.db.crates.len());
+            let separator = if self.rng.chance(0.5) { '-' } else { '_' };
+            let suffix = self.rng.pick(SUFFIXES);
+            format!("{}{}{}", self.db.crates[base].name, separator, suffix)
+        } else {
+            let syllables = 1 + self.rng.below(3);
+            self.rng.word(syllables)
+        };
+        while !self.names.insert(name.clone()) {
+            name += &self.rng.below(10).to_string();
+        }
+        name
+    }
+
+    fn deleted_crates(&mut self) {
+        let end = self.config.timestamp;
+        for i in 1..=self.config.crates / 200 {
+            let name = self.crate_name();
+            let created_at = self.start + scale(end - self.start, self.rng.f64());
+            let deleted_at = created_at + scale(end - created_at, self.rng.f64());
+            let deleted_by = UserId(self.user_weights.sample(&mut self.rng, usize::MAX) as u32 + 1);
+            self.db.deleted_crates.push(crate::deleted_crates::Row {
+                id: DeletedCrateId(i as u32),
+                name,
+                created_at,
+                deleted_at,
+                deleted_by: self.rng.chance(0.9).then_some(deleted_by),
+                message: if self.rng.chance(0.5) {
+                    "no longer maintained".to_owned()
+                } else {
+                    String::new()
+                },
+                available_at: deleted_at + TimeDelta::days(1),
+            });
+        }
+        for &name in RESERVED_CRATE_NAMES {
+            self.db
+                .reserved_crate_names
+                .push(crate::reserved_crate_names::Row {
+                    name: name.to_owned(),
+                });
+        }
+    }
+
+    fn downloads(&mut self) {
+        let days = i64::from(self.config.download_days);
+        let end = Date::from(self.config.timestamp.date_naive());
+        let window_start = end - TimeDelta::days(days);
+        let window_start_time = self.config.timestamp - TimeDelta::days(days);
+
+        for crate_index in 0..self.crate_versions.len() {
+            let range = self.crate_versions[crate_index].clone();
+            let rate = self.crate_weights.weight(crate_index) * 1.5;
+
+            // History predating the window, while each version was the newest.
+            for v in range.clone() {
+                let version = &self.db.versions[v];
+                let superseded = if v + 1 < range.end {
+                    self.db.versions[v + 1].created_at
+                } else {
+                    window_start_time
+                };
+                let live = superseded.min(window_start_time) - version.created_at;
+                let live_days = (live.num_seconds() as f64 / 86400.0).max(0.0);
+                let noise = 0.5 + self.rng.f64();
+                self.db.versions[v].downloads += (rate * 0.7 * live_days * noise) as u64;
+            }
+
+            let candidates: Vec<(usize, Date<Utc>)> = range
+                .rev()
+                .filter(|&v| !self.db.versions[v].yanked)
+                .take(DOWNLOADED_VERSIONS)
+                .map(|v| (v, Date::from(self.db.versions[v].created_at.date_naive())))
+                .collect();
+            for day in 0..days {
+                let date = window_start + TimeDelta::days(day);
+                let weekend = matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+                let daily = rate * if weekend { 0.6 } else { 1.0 } * (0.75 + 0.5 * self.rng.f64());
+                // The newest version gets most of the downloads.
+                let mut share = 1.0;
+                let mut total = 0.0;
+                for &(_, published) in &candidates {
+                    if published <= date {
+                        total += share;
+                        share *= 0.3;
+                    }
+                }
+                share = 1.0;
+                for &(v, published) in &candidates {
+                    if published > date {
+                        continue;
+                    }
+                    let downloads = self.rng.round(daily * share / total);
+                    share *= 0.3;
+                    if downloads > 0 {
+                        let version = &mut self.db.versions[v];
+                        version.downloads += downloads;
+                        self.db
+                            .version_downloads
+                            .push(crate::version_downloads::Row {
+                                version_id: version.id,
+                                downloads,
+                                date,
+                            });
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct Dependency {
+    target: usize,
+    kind: DependencyKind,
+    optional: bool,
+    default_features: bool,
+    target_cfg: Arc<str>,
+}
+
+fn initial_version(rng: &mut Rng) -> Version {
+    match rng.below(20) {
+        0..=12 => Version::new(0, 1, 0),
+        13..=14 => Version::new(0, 0, 1),
+        _ => Version::new(1, 0, 0),
+    }
+}
+
+fn next_version(rng: &mut Rng, prev: &Version) -> Version {
+    if !prev.pre.is_empty() {
+        if rng.chance(0.5) {
+            return Version::new(prev.major, prev.minor, prev.patch);
+        }
+        let (tag, n) = prev.pre.as_str().split_once('.').unwrap();
+        let n: u32 = n.parse().unwrap();
+        let pre = match tag {
+            "alpha" if rng.chance(0.4) => "beta.1".to_owned(),
+            "beta" if rng.chance(0.4) => "rc.1".to_owned(),
+            _ => format!("{}.{}", tag, n + 1),
+        };
+        let mut next = prev.clone();
+        next.pre = Prerelease::new(&pre).unwrap();
+        return next;
+    }
+
+    let breaking = if prev.major == 0 {
+        Version::new(0, prev.minor + 1, 0)
+    } else {
+        Version::new(prev.major + 1, 0, 0)
+    };
+    if rng.chance(0.04) {
+        let mut next = breaking;
+        next.pre = Prerelease::new(rng.pick(&["alpha.1", "beta.1", "rc.1"])).unwrap();
+        return next;
+    }
+    match rng.below(100) {
+        0..=1 if prev.major == 0 && prev.minor > 0 => Version::new(1, 0, 0),
+        0..=64 => Version::new(prev.major, prev.minor, prev.patch + 1),
+        65..=94 if prev.major > 0 => Version::new(prev.major, prev.minor + 1, 0),
+        _ => breaking,
+    }
+}
+
+// Newest Rust 1.x release as of the given time, at six week intervals from
+// 1.0 on 2015-05-15.
+fn rust_minor(time: DateTime<Utc>) -> u64 {
+    let rust_1_0 = DateTime::from_timestamp(1_431_648_000, 0).unwrap();
+    ((time - rust_1_0).num_days().max(0) / 42) as u64
+}
+
+fn edition(time: DateTime<Utc>) -> u16 {
+    let timestamp = time.timestamp();
+    if timestamp < 1_544_054_400 {
+        2015
+    } else if timestamp < 1_634_774_400 {
+        2018
+    } else if timestamp < 1_740_009_600 {
+        2021
+    } else {
+        2024
+    }
+}
+
+fn scale(delta: TimeDelta, fraction: f64) -> TimeDelta {
+    let micros = delta.num_microseconds().unwrap() as f64 * fraction;
+    TimeDelta::microseconds(micros as i64)
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// xoshiro256** seeded by splitmix64. Implemented here rather than taken from
+// a dependency so that output for a given seed never changes between
+// versions of this crate.
+struct Rng {
+    s: [u64; 4],
+}
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        let mut state = seed;
+        let mut splitmix = || {
+            state = state.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+        Rng {
+            s: [splitmix(), splitmix(), splitmix(), splitmix()],
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let result = self.s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.s[1] << 17;
+        self.s[2] ^= self.s[0];
+        self.s[3] ^= self.s[1];
+        self.s[1] ^= self.s[2];
+        self.s[0] ^= self.s[3];
+        self.s[2] ^= t;
+        self.s[3] = self.s[3].rotate_left(45);
+        result
+    }
+
+    // Uniform in [0, 1).
+    fn f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Uniform in 0..n.
+    fn below(&mut self, n: usize) -> usize {
+        ((u128::from(self.next_u64()) * n as u128) >> 64) as usize
+    }
+
+    fn chance(&mut self, p: f64) -> bool {
+        self.f64() < p
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())]
+    }
+
+    // Index into a list of (item, weight).
+    fn weighted<T>(&mut self, items: &[(T, f64)]) -> usize {
+        let total: f64 = items.iter().map(|(_, weight)| weight).sum();
+        let mut x = self.f64() * total;
+        for (i, (_, weight)) in items.iter().enumerate() {
+            if x < *weight {
+                return i;
+            }
+            x -= weight;
+        }
+        items.len() - 1
+    }
+
+    // Pareto distribution with minimum 1.
+    fn pareto(&mut self, alpha: f64) -> f64 {
+        (1.0 - self.f64()).powf(-1.0 / alpha)
+    }
+
+    // Geometric distribution on 1.. with the given mean.
+    fn geometric(&mut self, mean: f64) -> usize {
+        if mean <= 1.0 {
+            return 1;
+        }
+        let p = 1.0 / mean;
+        1 + ((1.0 - self.f64()).ln() / (1.0 - p).ln()) as usize
+    }
+
+    // Standard normal distribution, by Box-Muller.
+    fn normal(&mut self) -> f64 {
+        let u = 1.0 - self.f64();
+        let v = self.f64();
+        (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+    }
+
+    // Index in 0..n, favoring small ones.
+    fn zipf(&mut self, n: usize) -> usize {
+        ((n as f64).powf(self.f64()) - 1.0) as usize
+    }
+
+    // Round to an integer, up or down in proportion to the fractional part.
+    fn round(&mut self, x: f64) -> u64 {
+        let floor = x.floor();
+        floor as u64 + u64::from(self.chance(x - floor))
+    }
+
+    fn word(&mut self, syllables: usize) -> String {
+        let mut word = String::new();
+        for _ in 0..syllables {
+            word += self.pick(SYLLABLES);
+        }
+        word
+    }
+}
+
+// Cumulative weights, for sampling an index in proportion to its weight.
+#[derive(Default)]
+struct Weighted {
+    cumulative: Vec<f64>,
+}
+
+impl Weighted {
+    fn push(&mut self, weight: f64) {
+        let total = self.cumulative.last().copied().unwrap_or(0.0);
+        self.cumulative.push(total + weight);
+    }
+
+    fn weight(&self, i: usize) -> f64 {
+        self.cumulative[i] - i.checked_sub(1).map_or(0.0, |j| self.cumulative[j])
+    }
+
+    // Sample among the first `n` pushed.
+    fn sample(&self, rng: &mut Rng, n: usize) -> usize {
+        let n = n.min(self.cumulative.len());
+        let x = rng.f64() * self.cumulative[n - 1];
+        self.cumulative[..n]
+            .partition_point(|&total| total <= x)
+            .min(n - 1)
+    }
+}
+
+const SYLLABLES: &[&str] = &[
+    "ar", "ax", "bi", "by", "co", "cr", "de", "do", "el", "en", "fa", "fu", "ga", "hy", "io", "ka",
+    "ki", "la", "lo", "ma", "mi", "mo", "na", "nu", "om", "pa", "per", "qu", "ra", "rex", "ro",
+    "ser", "sy", "ta", "to", "tok", "tu", "ul", "va", "vo", "wa", "xi", "yo", "ze", "zo",
+];
+
+const SUFFIXES: &[&str] = &["derive", "macros", "core", "sys", "impl", "cli", "utils"];
+
+const ADJECTIVES: &[&str] = &[
+    "fast",
+    "simple",
+    "async",
+    "safe",
+    "minimal",
+    "flexible",
+    "zero-copy",
+    "ergonomic",
+    "lightweight",
+    "portable",
+];
+
+const NOUNS: &[&str] = &[
+    "parser",
+    "runtime",
+    "serialization framework",
+    "HTTP client",
+    "logger",
+    "allocator",
+    "command-line toolkit",
+    "hash map",
+    "bindings",
+    "testing library",
+];
+
+const FIRST_NAMES: &[&str] = &[
+    "Alex", "Sam", "Jordan", "Taylor", "Morgan", "Casey", "Riley", "Jamie", "Robin", "Avery",
+];
+
+const KEYWORDS: &[&str] = &[
+    "cli",
+    "async",
+    "serde",
+    "parser",
+    "json",
+    "web",
+    "macro",
+    "derive",
+    "no-std",
+    "http",
+    "embedded",
+    "crypto",
+    "database",
+    "wasm",
+    "testing",
+    "logging",
+    "network",
+    "api",
+    "game",
+    "graphics",
+    "gui",
+    "terminal",
+    "compression",
+    "image",
+    "audio",
+    "math",
+    "time",
+    "encoding",
+    "hash",
+    "tokio",
+    "sql",
+    "config",
+    "error",
+    "ffi",
+    "linux",
+    "windows",
+    "random",
+    "text",
+    "unicode",
+    "utility",
+];
+
+const CATEGORIES: &[(&str, &str)] = &[
+    ("development-tools", "Development tools"),
+    ("command-line-utilities", "Command line utilities"),
+    ("web-programming", "Web programming"),
+    ("api-bindings", "API bindings"),
+    ("asynchronous", "Asynchronous"),
+    ("encoding", "Encoding"),
+    ("network-programming", "Network programming"),
+    ("data-structures", "Data structures"),
+    ("algorithms", "Algorithms"),
+    ("embedded", "Embedded development"),
+    ("no-std", "No standard library"),
+    ("cryptography", "Cryptography"),
+    ("parser-implementations", "Parser implementations"),
+    ("science", "Science"),
+    ("game-development", "Game development"),
+    ("database", "Database interfaces"),
+    ("command-line-interface", "Command-line interface"),
+    ("rust-patterns", "Rust patterns"),
+    ("gui", "GUI"),
+    ("text-processing", "Text processing"),
+    ("mathematics", "Mathematics"),
+    ("development-tools::testing", "Testing"),
+    (
+        "development-tools::procedural-macro-helpers",
+        "Procedural macro helpers",
+    ),
+    ("web-programming::http-client", "HTTP client"),
+    ("web-programming::http-server", "HTTP server"),
+    ("filesystem", "Filesystem"),
+    ("config", "Configuration"),
+    ("date-and-time", "Date and time"),
+    ("compression", "Compression"),
+    ("wasm", "WebAssembly"),
+];
+
+const LICENSES: &[(&str, f64)] = &[
+    ("MIT OR Apache-2.0", 50.0),
+    ("MIT", 25.0),
+    ("Apache-2.0", 10.0),
+    ("MIT/Apache-2.0", 5.0),
+    ("BSD-3-Clause", 3.0),
+    ("GPL-3.0", 2.0),
+    ("MPL-2.0", 2.0),
+    ("Unlicense OR MIT", 2.0),
+    ("ISC", 1.0),
+];
+
+const TARGETS: &[&str] = &[
+    "",
+    "cfg(unix)",
+    "cfg(windows)",
+    "cfg(target_os = \"linux\")",
+    "cfg(target_arch = \"wasm32\")",
+];
+
+const RESERVED_CRATE_NAMES: &[&str] = &[
+    "alloc",
+    "core",
+    "std",
+    "proc_macro",
+    "test",
+    "con",
+    "nul",
+    "aux",
+    "prn",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::Generator;
+
+    #[test]
+    fn test_deterministic() {
+        let mut generator = Generator::new(7);
+        generator
+            .crates(300)
+            .versions_per_crate(4.0)
+            .dependencies_per_version(3.0)
+            .download_days(14);
+        let db = generator.generate();
+        let again = generator.generate();
+        assert_eq!(
+            format!("{:?}", db.versions),
+            format!("{:?}", again.versions)
+        );
+        assert_eq!(
+            format!("{:?}", db.dependencies),
+            format!("{:?}", again.dependencies),
+        );
+        assert_eq!(
+            format!("{:?}", db.version_downloads),
+            format!("{:?}", again.version_downloads),
+        );
+
+        let other = Generator::new(8)
+            .crates(300)
+            .versions_per_crate(4.0)
+            .dependencies_per_version(3.0)
+            .download_days(14)
+            .generate();
+        assert_ne!(format!("{:?}", db.crates), format!("{:?}", other.crates));
+    }
+
+    #[test]
+    fn test_consistent() {
+        let db = Generator::new(1).crates(500).download_days(30).generate();
+        assert!(db.validate().is_empty(), "{:?}", db.validate().problems());
+        assert!(db.check_counters().is_empty());
+
+        assert_eq!(db.crates.len(), 500);
+        assert!(db.versions.len() > 2000);
+        assert!(db.dependencies.len() > db.versions.len());
+        assert!(db.version_downloads.len() > db.crates.len() * 10);
+
+        // Heavy tail: the top 1% of crates account for a large share of all
+        // downloads.
+        let mut downloads: Vec<u64> = db.crate_downloads.iter().map(|row| row.downloads).collect();
+        downloads.sort_unstable_by(|a, b| b.cmp(a));
+        let top: u64 = downloads[..5].iter().sum();
+        assert!(top * 10 > db.metadata.total_downloads);
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path();
+        let path = dir.join("db-dump.tar.gz");
+        Generator::new(1)
+            .crates(500)
+            .download_days(30)
+            .write(&path)
+            .unwrap();
+        let loaded = crate::load_all(&path).unwrap();
+        assert_eq!(
+            format!("{:?}", loaded.versions),
+            format!("{:?}", db.versions)
+        );
+        assert_eq!(
+            format!("{:?}", loaded.dependencies),
+            format!("{:?}", db.dependencies),
+        );
+    }
+}
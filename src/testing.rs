@@ -0,0 +1,445 @@
+//! Small synthetic dumps for use in tests.
+//!
+//! [`DumpBuilder`] declares crates, versions, dependencies, owners and
+//! download history in a few lines, fills in every other column with a
+//! plausible default, and derives the denormalized tables and counters
+//! (crate_downloads, default_versions, crates_cnt, total_downloads) from what
+//! was declared. The result is available as an in-memory [`DbDump`] or as a
+//! db-dump.tar.gz archive for exercising [`Loader`](crate::Loader).
+//!
+//! ```
+//! use db_dump::testing::DumpBuilder;
+//! use db_dump::Date;
+//!
+//! fn main() -> db_dump::Result<()> {
+//!     let mut builder = DumpBuilder::new();
+//!     let alice = builder.user("alice").id;
+//!     let serde = builder.krate("serde").id;
+//!     let serde_v1 = builder.version(serde, "1.0.0").id;
+//!     let json = builder.krate("serde_json").id;
+//!     let json_v1 = builder.version(json, "1.0.0").id;
+//!     builder.dependency(json_v1, serde, "^1.0");
+//!     builder.owner(serde, alice);
+//!     builder.download_series(serde_v1, Date::from_ymd(2024, 1, 1), &[10, 20, 30]);
+//!
+//!     let archive = builder.temp_archive()?;
+//!     let db = db_dump::load_all(&archive)?;
+//!     assert_eq!(db.crates.len(), 2);
+//!     assert_eq!(db.metadata.total_downloads, 60);
+//!     assert!(db.validate().is_empty());
+//!     Ok(())
+//! }
+//! ```
+
+use crate::categories::CategoryId;
+use crate::crate_owners::OwnerId;
+use crate::crates::CrateId;
+use crate::deleted_crates::DeletedCrateId;
+use crate::dependencies::DependencyKind;
+use crate::error::Result;
+use crate::keywords::KeywordId;
+use crate::teams::TeamId;
+use crate::users::UserId;
+use crate::versions::VersionId;
+use crate::{Date, DbDump, Writer};
+use chrono::{DateTime, TimeDelta, Utc};
+use semver::{Version, VersionReq};
+use std::collections::{BTreeMap as Map, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::TempPath;
+
+/// Builder for a synthetic [`DbDump`].
+///
+/// Methods that add a row return a mutable reference to it, for customizing
+/// columns beyond the ones passed in. Ids are assigned sequentially starting
+/// at 1 for each table.
+pub struct DumpBuilder {
+    timestamp: DateTime<Utc>,
+    db: DbDump,
+    keywords: HashMap<String, KeywordId>,
+    categories: HashMap<String, CategoryId>,
+}
+
+/// A db-dump.tar.gz in the system's temporary directory, deleted on drop.
+pub struct TempArchive {
+    path: TempPath,
+}
+
+impl DumpBuilder {
+    pub fn new() -> Self {
+        DumpBuilder {
+            timestamp: DateTime::from_timestamp(1_704_067_200, 0).unwrap(),
+            db: DbDump::default(),
+            keywords: HashMap::new(),
+            categories: HashMap::new(),
+        }
+    }
+
+    /// The created_at and updated_at of subsequently added rows, and the
+    /// datestamp of the archive. Defaults to 2024-01-01 00:00:00 UTC.
+    pub fn timestamp(&mut self, timestamp: DateTime<Utc>) -> &mut Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn krate(&mut self, name: &str) -> &mut crate::crates::Row {
+        let id = CrateId(self.db.crates.len() as u32 + 1);
+        self.db.crates.push(crate::crates::Row {
+            id,
+            name: name.to_owned(),
+            updated_at: self.timestamp,
+            created_at: self.timestamp,
+            description: String::new(),
+            homepage: None,
+            documentation: None,
+            readme: None,
+            repository: None,
+            max_upload_size: None,
+            max_features: None,
+            trustpub_only: false,
+        });
+        self.db.crates.last_mut().unwrap()
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `num` is not a valid semver version.
+    pub fn version(&mut self, crate_id: CrateId, num: &str) -> &mut crate::versions::Row {
+        let id = VersionId(self.db.versions.len() as u32 + 1);
+        self.db.versions.push(crate::versions::Row {
+            id,
+            crate_id,
+            num: Version::parse(num).unwrap(),
+            updated_at: self.timestamp,
+            created_at: self.timestamp,
+            downloads: 0,
+            features: Map::new(),
+            yanked: false,
+            license: Arc::from("MIT OR Apache-2.0"),
+            crate_size: Some(1024),
+            published_by: None,
+            checksum: None,
+            links: None,
+            rust_version: None,
+            has_lib: true,
+            bin_names: Vec::new(),
+            edition: Some(2021),
+            description: None,
+            homepage: None,
+            documentation: None,
+            repository: None,
+            categories: Vec::new(),
+            keywords: Vec::new(),
+        });
+        self.db.versions.last_mut().unwrap()
+    }
+
+    /// A normal dependency of the given version upon the given crate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `req` is not a valid semver version requirement.
+    pub fn dependency(
+        &mut self,
+        version_id: VersionId,
+        crate_id: CrateId,
+        req: &str,
+    ) -> &mut crate::dependencies::Row {
+        let id = self.db.dependencies.len() as u32 + 1;
+        self.db.dependencies.push(crate::dependencies::Row {
+            id,
+            version_id,
+            crate_id,
+            req: VersionReq::parse(req).unwrap(),
+            optional: false,
+            default_features: true,
+            features: Vec::new(),
+            target: Arc::from(""),
+            kind: DependencyKind::Normal,
+            explicit_name: None,
+        });
+        self.db.dependencies.last_mut().unwrap()
+    }
+
+    pub fn user(&mut self, login: &str) -> &mut crate::users::Row {
+        let id = UserId(self.db.users.len() as u32 + 1);
+        let gh_id = 1000 + i32::try_from(id.0).unwrap();
+        self.db.users.push(crate::users::Row {
+            id,
+            gh_login: login.to_owned(),
+            name: None,
+            gh_avatar: format!("https://avatars.githubusercontent.com/u/{}?v=4", gh_id),
+            gh_id,
+        });
+        self.db.users.last_mut().unwrap()
+    }
+
+    /// A team, with a login of the form `github:org:team`.
+    pub fn team(&mut self, login: &str) -> &mut crate::teams::Row {
+        let id = TeamId(self.db.teams.len() as u32 + 1);
+        let github_id = 2000 + id.0;
+        let name = login.rsplit(':').next().unwrap_or(login).to_owned();
+        self.db.teams.push(crate::teams::Row {
+            id,
+            login: login.to_owned(),
+            github_id,
+            name,
+            avatar: format!("https://avatars.githubusercontent.com/t/{}?v=4", github_id),
+            org_id: Some(github_id + 1000),
+        });
+        self.db.teams.last_mut().unwrap()
+    }
+
+    pub fn owner(
+        &mut self,
+        crate_id: CrateId,
+        owner_id: impl Into<OwnerId>,
+    ) -> &mut crate::crate_owners::Row {
+        self.db.crate_owners.push(crate::crate_owners::Row {
+            crate_id,
+            owner_id: owner_id.into(),
+            created_at: self.timestamp,
+            created_by: None,
+        });
+        self.db.crate_owners.last_mut().unwrap()
+    }
+
+    /// Tag a crate with a keyword, adding the keyword if it is new.
+    pub fn keyword(&mut self, crate_id: CrateId, keyword: &str) -> KeywordId {
+        let keyword_id = if let Some(&keyword_id) = self.keywords.get(keyword) {
+            keyword_id
+        } else {
+            let keyword_id = KeywordId(self.db.keywords.len() as u32 + 1);
+            self.db.keywords.push(crate::keywords::Row {
+                id: keyword_id,
+                keyword: keyword.to_owned(),
+                crates_cnt: 0,
+                created_at: self.timestamp,
+            });
+            self.keywords.insert(keyword.to_owned(), keyword_id);
+            keyword_id
+        };
+        self.db.crates_keywords.push(crate::crates_keywords::Row {
+            crate_id,
+            keyword_id,
+        });
+        keyword_id
+    }
+
+    /// Place a crate in a category, adding the category if it is new. The
+    /// category is identified by its slug, such as `"parser-implementations"`
+    /// or `"development-tools::testing"`.
+    pub fn category(&mut self, crate_id: CrateId, slug: &str) -> CategoryId {
+        let category_id = if let Some(&category_id) = self.categories.get(slug) {
+            category_id
+        } else {
+            let category_id = CategoryId(self.db.categories.len() as u32 + 1);
+            self.db.categories.push(crate::categories::Row {
+                id: category_id,
+                category: slug.rsplit("::").next().unwrap().to_owned(),
+                slug: slug.to_owned(),
+                description: String::new(),
+                crates_cnt: 0,
+                created_at: self.timestamp,
+                path: format!("root.{}", slug.replace('-', "_").replace("::", ".")),
+            });
+            self.categories.insert(slug.to_owned(), category_id);
+            category_id
+        };
+        self.db
+            .crates_categories
+            .push(crate::crates_categories::Row {
+                crate_id,
+                category_id,
+            });
+        category_id
+    }
+
+    /// Record downloads of a version on one day. These also count toward the
+    /// version's all-time downloads.
+    pub fn downloads(
+        &mut self,
+        version_id: VersionId,
+        date: Date<Utc>,
+        downloads: u64,
+    ) -> &mut Self {
+        self.db
+            .version_downloads
+            .push(crate::version_downloads::Row {
+                version_id,
+                downloads,
+                date,
+            });
+        if let Some(version) = self.db.versions.iter_mut().find(|row| row.id == version_id) {
+            version.downloads += downloads;
+        }
+        self
+    }
+
+    /// Record downloads of a version on consecutive days starting at `start`.
+    pub fn download_series(
+        &mut self,
+        version_id: VersionId,
+        start: Date<Utc>,
+        downloads: &[u64],
+    ) -> &mut Self {
+        let mut date = start;
+        for &downloads in downloads {
+            self.downloads(version_id, date, downloads);
+            date += TimeDelta::days(1);
+        }
+        self
+    }
+
+    pub fn deleted_crate(&mut self, name: &str) -> &mut crate::deleted_crates::Row {
+        let id = DeletedCrateId(self.db.deleted_crates.len() as u32 + 1);
+        self.db.deleted_crates.push(crate::deleted_crates::Row {
+            id,
+            name: name.to_owned(),
+            created_at: self.timestamp,
+            deleted_at: self.timestamp,
+            deleted_by: None,
+            message: String::new(),
+            available_at: self.timestamp,
+        });
+        self.db.deleted_crates.last_mut().unwrap()
+    }
+
+    pub fn reserved_crate_name(&mut self, name: &str) -> &mut Self {
+        self.db
+            .reserved_crate_names
+            .push(crate::reserved_crate_names::Row {
+                name: name.to_owned(),
+            });
+        self
+    }
+
+    /// The dump as declared, plus the derived tables and counters.
+    ///
+    /// Every crate gets a row in crate_downloads, and every crate that has
+    /// versions gets a row in default_versions pointing to its greatest
+    /// version, preferring ones that are neither yanked nor prereleases.
+    pub fn build(&self) -> DbDump {
+        let mut db = self.db.clone();
+        derive(&mut db);
+        db
+    }
+
+    /// The dump as a db-dump.tar.gz archive in memory.
+    pub fn archive(&self) -> Result<Vec<u8>> {
+        let mut archive = Vec::new();
+        self.writer()?.write_to(&mut archive)?;
+        Ok(archive)
+    }
+
+    /// Write the dump as a db-dump.tar.gz archive to the given path.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.writer()?.write(path)
+    }
+
+    /// Write the dump as a db-dump.tar.gz archive to a new file in the
+    /// system's temporary directory.
+    pub fn temp_archive(&self) -> Result<TempArchive> {
+        let path = tempfile::Builder::new()
+            .prefix("db-dump-")
+            .suffix(".tar.gz")
+            .tempfile()?
+            .into_temp_path();
+        self.write(&path)?;
+        Ok(TempArchive { path })
+    }
+
+    fn writer(&self) -> Result<Writer> {
+        let mut writer = Writer::new(self.timestamp);
+        writer.dump(&self.build())?;
+        Ok(writer)
+    }
+}
+
+impl Default for DumpBuilder {
+    fn default() -> Self {
+        DumpBuilder::new()
+    }
+}
+
+impl TempArchive {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempArchive {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+// Fills in the tables and counters that crates.io derives from the others:
+// crate_downloads, default_versions, metadata.total_downloads, and the
+// crates_cnt of keywords and categories. Also puts version_downloads in the
+// order in which it appears in a real dump.
+pub(crate) fn derive(db: &mut DbDump) {
+    let mut crate_downloads = Map::new();
+    let mut default_versions = Map::new();
+    for version in &db.versions {
+        *crate_downloads.entry(version.crate_id).or_insert(0) += version.downloads;
+        let rank = |version: &crate::versions::Row| {
+            (
+                !version.yanked,
+                version.num.pre.is_empty(),
+                version.num.clone(),
+            )
+        };
+        default_versions
+            .entry(version.crate_id)
+            .and_modify(
+                |(default, num_versions): &mut (&crate::versions::Row, u32)| {
+                    if rank(version) > rank(default) {
+                        *default = version;
+                    }
+                    *num_versions += 1;
+                },
+            )
+            .or_insert((version, 1));
+    }
+
+    db.default_versions = default_versions
+        .into_iter()
+        .map(
+            |(crate_id, (version, num_versions))| crate::default_versions::Row {
+                crate_id,
+                version_id: version.id,
+                num_versions: Some(num_versions),
+            },
+        )
+        .collect();
+
+    db.crate_downloads = db
+        .crates
+        .iter()
+        .map(|krate| crate::crate_downloads::Row {
+            crate_id: krate.id,
+            downloads: crate_downloads.get(&krate.id).copied().unwrap_or(0),
+        })
+        .collect();
+    db.metadata.total_downloads = db.crate_downloads.iter().map(|row| row.downloads).sum();
+
+    let mut keyword_crates = Map::new();
+    for row in &db.crates_keywords {
+        *keyword_crates.entry(row.keyword_id).or_insert(0) += 1;
+    }
+    for keyword in &mut db.keywords {
+        keyword.crates_cnt = keyword_crates.get(&keyword.id).copied().unwrap_or(0);
+    }
+
+    let mut category_crates = Map::new();
+    for row in &db.crates_categories {
+        *category_crates.entry(row.category_id).or_insert(0) += 1;
+    }
+    for category in &mut db.categories {
+        category.crates_cnt = category_crates.get(&category.id).copied().unwrap_or(0);
+    }
+
+    db.version_downloads.sort();
+}
@@ -0,0 +1,796 @@
+//! Integrity checks across the tables of a loaded [`DbDump`].
+//!
+//! [`DbDump::validate`] checks the relational structure of the dump: foreign
+//! keys, primary keys, and rows that should exist alongside one another.
+//! [`DbDump::check_counters`] checks the columns which duplicate data that can
+//! be derived from other tables, such as download totals and crate counts.
+//!
+//! ```no_run
+//! fn main() -> db_dump::Result<()> {
+//!     let db = db_dump::load_all("./db-dump.tar.gz")?;
+//!
+//!     let report = db.validate();
+//!     for problem in report.table("dependencies") {
+//!         eprintln!("{}", problem);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::crate_owners::OwnerId;
+use crate::error::{err, Result};
+use crate::idmap::Id;
+use crate::DbDump;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap as Map, HashSet as Set};
+use std::fmt::{self, Display};
+use std::hash::Hash;
+
+/// Result of [`DbDump::validate`].
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    problems: Vec<Problem>,
+}
+
+/// One violation of the relational structure of the dump.
+///
+/// Rows are identified by their position in the corresponding `Vec` of the
+/// `DbDump`, which is the same as their order in the csv file.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Problem {
+    /// A column refers to a row that does not exist in the referenced table.
+    DanglingForeignKey {
+        table: &'static str,
+        row: usize,
+        column: &'static str,
+        references: &'static str,
+        key: u32,
+    },
+    /// Two rows of a table have the same value in a column or combination of
+    /// columns which is supposed to be unique.
+    DuplicatePrimaryKey {
+        table: &'static str,
+        row: usize,
+        columns: &'static str,
+        first_row: usize,
+    },
+    /// A row that is expected to have at least one corresponding row in some
+    /// other table has none.
+    Orphan {
+        table: &'static str,
+        row: usize,
+        missing: &'static str,
+    },
+}
+
+/// A denormalized counter whose recorded value does not match the value
+/// computed from the table it summarizes.
+#[derive(Clone, Debug)]
+pub struct Discrepancy {
+    pub counter: Counter,
+    /// Position of the row holding the counter within its table.
+    pub row: usize,
+    /// The value found in the dump.
+    pub recorded: u64,
+    /// The value derived from the other tables.
+    pub computed: u64,
+}
+
+/// The denormalized columns checked by [`DbDump::check_counters`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum Counter {
+    /// `crate_downloads.downloads`, the sum of `versions.downloads` over the
+    /// crate's versions.
+    CrateDownloads,
+    /// `keywords.crates_cnt`, the number of rows in crates_keywords.csv
+    /// referring to the keyword.
+    KeywordCratesCnt,
+    /// `categories.crates_cnt`, the number of rows in crates_categories.csv
+    /// referring to the category.
+    CategoryCratesCnt,
+    /// `default_versions.num_versions`, the number of rows in versions.csv
+    /// belonging to the crate.
+    NumVersions,
+    /// `metadata.total_downloads`, which is at least the sum of
+    /// `versions.downloads`. It also counts downloads of crates that have
+    /// since been deleted, so only a total below the sum is a discrepancy.
+    TotalDownloads,
+}
+
+impl DbDump {
+    /// Check every foreign key for a referenced row, every primary key and
+    /// unique column for duplicates, and every crate for the rows that
+    /// crates.io always creates alongside it.
+    ///
+    /// A table that is empty altogether, because the dump predates it or the
+    /// [`Loader`][crate::Loader] skipped it, is not required to have a row for
+    /// every crate.
+    pub fn validate(&self) -> Report {
+        let mut report = Report::default();
+        let problems = &mut report.problems;
+
+        let categories = unique(problems, "categories", "id", &self.categories, |row| row.id);
+        unique(
+            problems,
+            "categories",
+            "category",
+            &self.categories,
+            |row| row.category.as_str(),
+        );
+        unique(problems, "categories", "slug", &self.categories, |row| {
+            row.slug.as_str()
+        });
+        let crates = unique(problems, "crates", "id", &self.crates, |row| row.id);
+        unique(problems, "crates", "name", &self.crates, |row| {
+            row.name.as_str()
+        });
+        let keywords = unique(problems, "keywords", "id", &self.keywords, |row| row.id);
+        unique(problems, "keywords", "keyword", &self.keywords, |row| {
+            row.keyword.as_str()
+        });
+        let teams = unique(problems, "teams", "id", &self.teams, |row| row.id);
+        unique(problems, "teams", "login", &self.teams, |row| {
+            row.login.as_str()
+        });
+        let users = unique(problems, "users", "id", &self.users, |row| row.id);
+        let versions = unique(problems, "versions", "id", &self.versions, |row| row.id);
+        unique(problems, "dependencies", "id", &self.dependencies, |row| {
+            row.id
+        });
+        unique(
+            problems,
+            "deleted_crates",
+            "id",
+            &self.deleted_crates,
+            |row| row.id,
+        );
+        unique(
+            problems,
+            "reserved_crate_names",
+            "name",
+            &self.reserved_crate_names,
+            |row| row.name.as_str(),
+        );
+        let crate_downloads = unique(
+            problems,
+            "crate_downloads",
+            "crate_id",
+            &self.crate_downloads,
+            |row| row.crate_id,
+        );
+        let default_versions = unique(
+            problems,
+            "default_versions",
+            "crate_id",
+            &self.default_versions,
+            |row| row.crate_id,
+        );
+        unique(
+            problems,
+            "crate_owners",
+            "crate_id, owner_id, owner_kind",
+            &self.crate_owners,
+            |row| (row.crate_id, row.owner_id),
+        );
+        unique(
+            problems,
+            "crates_categories",
+            "crate_id, category_id",
+            &self.crates_categories,
+            |row| (row.crate_id, row.category_id),
+        );
+        unique(
+            problems,
+            "crates_keywords",
+            "crate_id, keyword_id",
+            &self.crates_keywords,
+            |row| (row.crate_id, row.keyword_id),
+        );
+        let mut version_downloads = Map::with_capacity(self.version_downloads.len());
+        for (i, row) in self.version_downloads.iter().enumerate() {
+            match version_downloads.entry((row.version_id, row.date)) {
+                Entry::Vacant(entry) => {
+                    entry.insert(i);
+                }
+                Entry::Occupied(entry) => problems.push(Problem::DuplicatePrimaryKey {
+                    table: "version_downloads",
+                    row: i,
+                    columns: "version_id, date",
+                    first_row: *entry.get(),
+                }),
+            }
+        }
+
+        for (i, row) in self.crate_downloads.iter().enumerate() {
+            let table = "crate_downloads";
+            foreign(problems, table, i, "crate_id", &crates, row.crate_id);
+        }
+
+        for (i, row) in self.crate_owners.iter().enumerate() {
+            let table = "crate_owners";
+            foreign(problems, table, i, "crate_id", &crates, row.crate_id);
+            match row.owner_id {
+                OwnerId::User(user_id) => {
+                    foreign(problems, table, i, "owner_id", &users, user_id);
+                }
+                OwnerId::Team(team_id) => {
+                    foreign(problems, table, i, "owner_id", &teams, team_id);
+                }
+            }
+            if let Some(created_by) = row.created_by {
+                foreign(problems, table, i, "created_by", &users, created_by);
+            }
+        }
+
+        for (i, row) in self.crates_categories.iter().enumerate() {
+            let table = "crates_categories";
+            foreign(problems, table, i, "crate_id", &crates, row.crate_id);
+            foreign(
+                problems,
+                table,
+                i,
+                "category_id",
+                &categories,
+                row.category_id,
+            );
+        }
+
+        for (i, row) in self.crates_keywords.iter().enumerate() {
+            let table = "crates_keywords";
+            foreign(problems, table, i, "crate_id", &crates, row.crate_id);
+            foreign(problems, table, i, "keyword_id", &keywords, row.keyword_id);
+        }
+
+        for (i, row) in self.default_versions.iter().enumerate() {
+            let table = "default_versions";
+            foreign(problems, table, i, "crate_id", &crates, row.crate_id);
+            foreign(problems, table, i, "version_id", &versions, row.version_id);
+        }
+
+        for (i, row) in self.deleted_crates.iter().enumerate() {
+            if let Some(deleted_by) = row.deleted_by {
+                let table = "deleted_crates";
+                foreign(problems, table, i, "deleted_by", &users, deleted_by);
+            }
+        }
+
+        for (i, row) in self.dependencies.iter().enumerate() {
+            let table = "dependencies";
+            foreign(problems, table, i, "version_id", &versions, row.version_id);
+            foreign(problems, table, i, "crate_id", &crates, row.crate_id);
+        }
+
+        for (i, row) in self.version_downloads.iter().enumerate() {
+            let table = "version_downloads";
+            foreign(problems, table, i, "version_id", &versions, row.version_id);
+        }
+
+        let mut crates_with_versions = Set::new();
+        for (i, row) in self.versions.iter().enumerate() {
+            let table = "versions";
+            foreign(problems, table, i, "crate_id", &crates, row.crate_id);
+            if let Some(published_by) = row.published_by {
+                foreign(problems, table, i, "published_by", &users, published_by);
+            }
+            crates_with_versions.insert(row.crate_id);
+        }
+
+        for (i, row) in self.crates.iter().enumerate() {
+            let table = "crates";
+            if !self.versions.is_empty() && !crates_with_versions.contains(&row.id) {
+                problems.push(Problem::Orphan {
+                    table,
+                    row: i,
+                    missing: "versions",
+                });
+            }
+            if !self.crate_downloads.is_empty() && !crate_downloads.rows.contains_key(&row.id) {
+                problems.push(Problem::Orphan {
+                    table,
+                    row: i,
+                    missing: "crate_downloads",
+                });
+            }
+            if !self.default_versions.is_empty() && !default_versions.rows.contains_key(&row.id) {
+                problems.push(Problem::Orphan {
+                    table,
+                    row: i,
+                    missing: "default_versions",
+                });
+            }
+        }
+
+        report
+    }
+}
+
+impl DbDump {
+    /// Recompute each denormalized counter in the dump from the rows it
+    /// summarizes, and list the ones that disagree with the recorded value.
+    ///
+    /// Counters whose recorded value is absent, such as a null
+    /// `num_versions`, are not checked.
+    pub fn check_counters(&self) -> Vec<Discrepancy> {
+        let mut discrepancies = Vec::new();
+        let mut check = |counter, row, recorded: u64, computed: u64| {
+            if recorded != computed {
+                discrepancies.push(Discrepancy {
+                    counter,
+                    row,
+                    recorded,
+                    computed,
+                });
+            }
+        };
+
+        let mut version_downloads = Map::new();
+        let mut num_versions = Map::new();
+        let mut total_downloads = 0u64;
+        for row in &self.versions {
+            *version_downloads.entry(row.crate_id).or_insert(0u64) += row.downloads;
+            *num_versions.entry(row.crate_id).or_insert(0u64) += 1;
+            total_downloads = total_downloads.saturating_add(row.downloads);
+        }
+
+        for (i, row) in self.crate_downloads.iter().enumerate() {
+            let computed = version_downloads.get(&row.crate_id).copied();
+            check(
+                Counter::CrateDownloads,
+                i,
+                row.downloads,
+                computed.unwrap_or(0),
+            );
+        }
+
+        if self.metadata.total_downloads < total_downloads {
+            check(
+                Counter::TotalDownloads,
+                0,
+                self.metadata.total_downloads,
+                total_downloads,
+            );
+        }
+
+        let mut keyword_crates = Map::new();
+        for row in &self.crates_keywords {
+            *keyword_crates.entry(row.keyword_id).or_insert(0u64) += 1;
+        }
+        for (i, row) in self.keywords.iter().enumerate() {
+            let computed = keyword_crates.get(&row.id).copied();
+            check(
+                Counter::KeywordCratesCnt,
+                i,
+                u64::from(row.crates_cnt),
+                computed.unwrap_or(0),
+            );
+        }
+
+        let mut category_crates = Map::new();
+        for row in &self.crates_categories {
+            *category_crates.entry(row.category_id).or_insert(0u64) += 1;
+        }
+        for (i, row) in self.categories.iter().enumerate() {
+            let computed = category_crates.get(&row.id).copied();
+            check(
+                Counter::CategoryCratesCnt,
+                i,
+                u64::from(row.crates_cnt),
+                computed.unwrap_or(0),
+            );
+        }
+
+        for (i, row) in self.default_versions.iter().enumerate() {
+            if let Some(recorded) = row.num_versions {
+                let computed = num_versions.get(&row.crate_id).copied();
+                check(
+                    Counter::NumVersions,
+                    i,
+                    u64::from(recorded),
+                    computed.unwrap_or(0),
+                );
+            }
+        }
+
+        discrepancies
+    }
+}
+
+struct Keys<K> {
+    table: &'static str,
+    rows: Map<K, usize>,
+}
+
+// Reports duplicates of `key` within `rows`, and returns the distinct keys.
+fn unique<'a, T, K>(
+    problems: &mut Vec<Problem>,
+    table: &'static str,
+    columns: &'static str,
+    rows: &'a [T],
+    key: impl Fn(&'a T) -> K,
+) -> Keys<K>
+where
+    K: Eq + Hash,
+{
+    let mut seen = Map::with_capacity(rows.len());
+    for (i, row) in rows.iter().enumerate() {
+        match seen.entry(key(row)) {
+            Entry::Vacant(entry) => {
+                entry.insert(i);
+            }
+            Entry::Occupied(entry) => problems.push(Problem::DuplicatePrimaryKey {
+                table,
+                row: i,
+                columns,
+                first_row: *entry.get(),
+            }),
+        }
+    }
+    Keys { table, rows: seen }
+}
+
+fn foreign<K>(
+    problems: &mut Vec<Problem>,
+    table: &'static str,
+    row: usize,
+    column: &'static str,
+    referenced: &Keys<K>,
+    key: K,
+) where
+    K: Id + Eq + Hash,
+{
+    if !referenced.rows.contains_key(&key) {
+        problems.push(Problem::DanglingForeignKey {
+            table,
+            row,
+            column,
+            references: referenced.table,
+            key: key.raw(),
+        });
+    }
+}
+
+impl Report {
+    /// Whether the dump passed every check.
+    pub fn is_empty(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    /// Problems found in the rows of one table, identified by its csv file
+    /// name without extension, such as `"dependencies"`.
+    pub fn table<'a>(&'a self, table: &'a str) -> impl Iterator<Item = &'a Problem> + 'a {
+        self.problems
+            .iter()
+            .filter(move |problem| problem.table() == table)
+    }
+}
+
+impl Problem {
+    /// The table containing the offending row.
+    pub fn table(&self) -> &'static str {
+        match self {
+            Problem::DanglingForeignKey { table, .. }
+            | Problem::DuplicatePrimaryKey { table, .. }
+            | Problem::Orphan { table, .. } => table,
+        }
+    }
+
+    /// Position of the offending row within its table.
+    pub fn row(&self) -> usize {
+        match self {
+            Problem::DanglingForeignKey { row, .. }
+            | Problem::DuplicatePrimaryKey { row, .. }
+            | Problem::Orphan { row, .. } => *row,
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::DanglingForeignKey {
+                table,
+                row,
+                column,
+                references,
+                key,
+            } => write!(
+                formatter,
+                "{}.csv row {}: {} {} not found in {}",
+                table, row, column, key, references,
+            ),
+            Problem::DuplicatePrimaryKey {
+                table,
+                row,
+                columns,
+                first_row,
+            } => write!(
+                formatter,
+                "{}.csv row {}: duplicate {} of row {}",
+                table, row, columns, first_row,
+            ),
+            Problem::Orphan {
+                table,
+                row,
+                missing,
+            } => write!(
+                formatter,
+                "{}.csv row {}: no corresponding rows in {}",
+                table, row, missing,
+            ),
+        }
+    }
+}
+
+impl Discrepancy {
+    /// Overwrite the recorded counter in `db` with the computed value.
+    ///
+    /// Returns an error, leaving `db` unchanged, if the row no longer holds
+    /// the recorded value, for example because `db` is not the dump that the
+    /// discrepancy was found in, or if the computed value does not fit in the
+    /// column's type.
+    pub fn apply(&self, db: &mut DbDump) -> Result<()> {
+        let table = self.counter.table();
+        let column = self.counter.column();
+        let current = match self.counter {
+            Counter::CrateDownloads => db.crate_downloads.get(self.row).map(|row| row.downloads),
+            Counter::KeywordCratesCnt => db
+                .keywords
+                .get(self.row)
+                .map(|row| u64::from(row.crates_cnt)),
+            Counter::CategoryCratesCnt => db
+                .categories
+                .get(self.row)
+                .map(|row| u64::from(row.crates_cnt)),
+            Counter::NumVersions => db
+                .default_versions
+                .get(self.row)
+                .and_then(|row| row.num_versions)
+                .map(u64::from),
+            Counter::TotalDownloads => Some(db.metadata.total_downloads),
+        };
+        match current {
+            None => {
+                return Err(err(format_args!(
+                    "{}.csv row {}: no {} to apply to",
+                    table, self.row, column,
+                )));
+            }
+            Some(current) if current != self.recorded => {
+                return Err(err(format_args!(
+                    "{}.csv row {}: {} is {}, not the recorded {}",
+                    table, self.row, column, current, self.recorded,
+                )));
+            }
+            Some(_) => {}
+        }
+
+        let computed = self.computed;
+        let narrow = || {
+            u32::try_from(computed).map_err(|_| {
+                err(format_args!(
+                    "{}.csv row {}: {} {} does not fit in u32",
+                    table, self.row, column, computed,
+                ))
+            })
+        };
+        match self.counter {
+            Counter::CrateDownloads => db.crate_downloads[self.row].downloads = computed,
+            Counter::KeywordCratesCnt => db.keywords[self.row].crates_cnt = narrow()?,
+            Counter::CategoryCratesCnt => db.categories[self.row].crates_cnt = narrow()?,
+            Counter::NumVersions => {
+                db.default_versions[self.row].num_versions = Some(narrow()?);
+            }
+            Counter::TotalDownloads => db.metadata.total_downloads = computed,
+        }
+        Ok(())
+    }
+}
+
+impl Counter {
+    /// The table containing the counter.
+    pub fn table(self) -> &'static str {
+        match self {
+            Counter::CrateDownloads => "crate_downloads",
+            Counter::KeywordCratesCnt => "keywords",
+            Counter::CategoryCratesCnt => "categories",
+            Counter::NumVersions => "default_versions",
+            Counter::TotalDownloads => "metadata",
+        }
+    }
+
+    /// The column containing the counter.
+    pub fn column(self) -> &'static str {
+        match self {
+            Counter::CrateDownloads => "downloads",
+            Counter::KeywordCratesCnt | Counter::CategoryCratesCnt => "crates_cnt",
+            Counter::NumVersions => "num_versions",
+            Counter::TotalDownloads => "total_downloads",
+        }
+    }
+}
+
+impl Display for Discrepancy {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let at_least = match self.counter {
+            Counter::TotalDownloads => "at least ",
+            _ => "",
+        };
+        write!(
+            formatter,
+            "{}.csv row {}: {} is {} but should be {}{}",
+            self.counter.table(),
+            self.row,
+            self.counter.column(),
+            self.recorded,
+            at_least,
+            self.computed,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Counter, Discrepancy, Problem};
+    use crate::crates::CrateId;
+    use crate::keywords::KeywordId;
+    use crate::testing::DumpBuilder;
+    use crate::DbDump;
+
+    #[test]
+    fn test_validate() {
+        let mut db = DbDump::default();
+        db.crate_downloads.push(crate::crate_downloads::Row {
+            crate_id: CrateId(1),
+            downloads: 0,
+        });
+        for _ in 0..2 {
+            db.crates_keywords.push(crate::crates_keywords::Row {
+                crate_id: CrateId(1),
+                keyword_id: KeywordId(1),
+            });
+        }
+
+        let report = db.validate();
+        let problems = report.problems();
+        assert_eq!(problems.len(), 6);
+        assert!(matches!(
+            problems[0],
+            Problem::DuplicatePrimaryKey {
+                table: "crates_keywords",
+                row: 1,
+                first_row: 0,
+                ..
+            },
+        ));
+        assert_eq!(
+            problems[1].to_string(),
+            "crate_downloads.csv row 0: crate_id 1 not found in crates",
+        );
+        assert_eq!(report.table("crates_keywords").count(), 5);
+    }
+
+    #[test]
+    fn test_validate_skipped_tables() {
+        let mut builder = DumpBuilder::new();
+        let serde = builder.krate("serde").id;
+        builder.version(serde, "1.0.0");
+        let json = builder.krate("serde_json").id;
+        builder.version(json, "1.0.0");
+        let mut db = builder.build();
+        assert!(db.validate().problems().is_empty());
+
+        // An older dump, or a Loader that skipped these tables.
+        db.crate_downloads.clear();
+        db.default_versions.clear();
+        db.versions.clear();
+        assert!(db.validate().problems().is_empty());
+
+        // A table that is present but missing a crate is still reported.
+        db.crate_downloads.push(crate::crate_downloads::Row {
+            crate_id: serde,
+            downloads: 0,
+        });
+        let report = db.validate();
+        let problems = report.problems();
+        assert_eq!(problems.len(), 1);
+        assert!(matches!(
+            problems[0],
+            Problem::Orphan {
+                table: "crates",
+                row: 1,
+                missing: "crate_downloads",
+            },
+        ));
+    }
+
+    #[test]
+    fn test_check_counters() {
+        let mut db = DbDump::default();
+        db.crate_downloads.push(crate::crate_downloads::Row {
+            crate_id: CrateId(1),
+            downloads: 10,
+        });
+        // May include downloads of deleted crates.
+        db.metadata.total_downloads = 25;
+
+        let discrepancies = db.check_counters();
+        assert_eq!(discrepancies.len(), 1);
+        assert_eq!(
+            discrepancies[0].to_string(),
+            "crate_downloads.csv row 0: downloads is 10 but should be 0",
+        );
+
+        discrepancies[0].apply(&mut db).unwrap();
+        assert!(db.check_counters().is_empty());
+        assert_eq!(db.metadata.total_downloads, 25);
+
+        db.keywords.push(crate::keywords::Row {
+            id: KeywordId(1),
+            keyword: "serde".to_owned(),
+            crates_cnt: 0,
+            created_at: chrono::DateTime::UNIX_EPOCH,
+        });
+        let overflow = Discrepancy {
+            counter: Counter::KeywordCratesCnt,
+            row: 0,
+            recorded: 0,
+            computed: u64::from(u32::MAX) + 1,
+        };
+        assert_eq!(
+            overflow.apply(&mut db).unwrap_err().to_string(),
+            "keywords.csv row 0: crates_cnt 4294967296 does not fit in u32",
+        );
+        assert_eq!(db.keywords[0].crates_cnt, 0);
+    }
+
+    #[test]
+    fn test_total_downloads() {
+        let mut builder = DumpBuilder::new();
+        let serde = builder.krate("serde").id;
+        builder.version(serde, "1.0.0").downloads = 10;
+        let mut db = builder.build();
+        db.metadata.total_downloads = 4;
+
+        let discrepancies = db.check_counters();
+        assert_eq!(discrepancies.len(), 1);
+        assert_eq!(
+            discrepancies[0].to_string(),
+            "metadata.csv row 0: total_downloads is 4 but should be at least 10",
+        );
+        discrepancies[0].apply(&mut db).unwrap();
+        assert_eq!(db.metadata.total_downloads, 10);
+        assert!(db.check_counters().is_empty());
+    }
+
+    #[test]
+    fn test_apply_mismatch() {
+        let mut db = DbDump::default();
+        db.crate_downloads.push(crate::crate_downloads::Row {
+            crate_id: CrateId(1),
+            downloads: 10,
+        });
+        let discrepancy = db.check_counters().remove(0);
+
+        // Already applied.
+        let mut fixed = db.clone();
+        discrepancy.apply(&mut fixed).unwrap();
+        assert_eq!(
+            discrepancy.apply(&mut fixed).unwrap_err().to_string(),
+            "crate_downloads.csv row 0: downloads is 0, not the recorded 10",
+        );
+
+        // A different dump.
+        let mut other = DbDump::default();
+        assert_eq!(
+            discrepancy.apply(&mut other).unwrap_err().to_string(),
+            "crate_downloads.csv row 0: no downloads to apply to",
+        );
+    }
+}
@@ -1,12 +1,15 @@
 //! <b style="font-variant:small-caps">version_downloads.csv</b>
 
 use crate::versions::VersionId;
-use chrono::Utc;
+use chrono::{Datelike, NaiveDate, Utc};
 use db_dump::Date;
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::iter::FusedIterator;
+use std::ops::{Bound, Range, RangeBounds};
 
 /// One row of **version_downloads.csv**.
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 #[non_exhaustive]
 pub struct Row {
@@ -14,3 +17,352 @@ pub struct Row {
     pub downloads: u64,
     pub date: Date<Utc>,
 }
+
+/// Columnar in-memory storage for the rows of **version_downloads.csv**.
+///
+/// The table holds one `Vec` per column instead of a `Vec` of [`Row`], which
+/// makes aggregations over a single column scan contiguous memory, and
+/// supports range queries by version and date once sorted.
+///
+/// [`DbDump::version_downloads`][crate::DbDump::version_downloads] is a
+/// `Table` holding the rows in the order of the csv, which is not necessarily
+/// sorted; call [`sort`][Table::sort] before making range queries. A `Table`
+/// can also be filled from a [`Loader`][crate::Loader] callback as below, or
+/// collected from an iterator of rows.
+///
+/// ```no_run
+/// use db_dump::version_downloads;
+///
+/// fn main() -> db_dump::Result<()> {
+///     let mut table = version_downloads::Table::new();
+///     db_dump::Loader::new()
+///         .version_downloads(|row| table.push(row))
+///         .load("./db-dump.tar.gz")?;
+///
+///     let total: u64 = table.downloads().iter().sum();
+///     println!("{}", total);
+///
+///     table.sort();
+///     # let version_id = db_dump::versions::VersionId(0);
+///     for row in table.version(version_id) {
+///         println!("{},{}", row.date, row.downloads);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Table {
+    version_ids: Vec<VersionId>,
+    days: Vec<i32>,
+    downloads: Vec<u64>,
+    sorted: bool,
+}
+
+/// A contiguous range of rows of a sorted [`Table`].
+#[derive(Copy, Clone, Debug)]
+pub struct Slice<'a> {
+    table: &'a Table,
+    range: (usize, usize),
+}
+
+/// Iterator over the rows of a [`Table`] or [`Slice`].
+#[derive(Clone, Debug)]
+pub struct Iter<'a> {
+    table: &'a Table,
+    range: Range<usize>,
+}
+
+impl Table {
+    pub const fn new() -> Self {
+        Table {
+            version_ids: Vec::new(),
+            days: Vec::new(),
+            downloads: Vec::new(),
+            sorted: true,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Table {
+            version_ids: Vec::with_capacity(capacity),
+            days: Vec::with_capacity(capacity),
+            downloads: Vec::with_capacity(capacity),
+            sorted: true,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.version_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.version_ids.is_empty()
+    }
+
+    #[allow(clippy::needless_pass_by_value)] // for use as Loader callback
+    pub fn push(&mut self, row: Row) {
+        let version_id = row.version_id;
+        let day = day(row.date);
+        if let (Some(&last_version_id), Some(&last_day)) =
+            (self.version_ids.last(), self.days.last())
+        {
+            if (last_version_id, last_day) > (version_id, day) {
+                self.sorted = false;
+            }
+        }
+        self.version_ids.push(version_id);
+        self.days.push(day);
+        self.downloads.push(row.downloads);
+    }
+
+    pub fn get(&self, i: usize) -> Option<Row> {
+        Some(Row {
+            version_id: *self.version_ids.get(i)?,
+            downloads: self.downloads[i],
+            date: date(self.days[i]),
+        })
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            table: self,
+            range: 0..self.len(),
+        }
+    }
+
+    /// The version_id column.
+    pub fn version_ids(&self) -> &[VersionId] {
+        &self.version_ids
+    }
+
+    /// The downloads column.
+    pub fn downloads(&self) -> &[u64] {
+        &self.downloads
+    }
+
+    /// The date column.
+    pub fn dates(&self) -> impl ExactSizeIterator<Item = Date<Utc>> + '_ {
+        self.days.iter().map(|&day| date(day))
+    }
+
+    /// Whether rows are in order of (version_id, date), which is required by
+    /// the range queries.
+    pub fn is_sorted(&self) -> bool {
+        self.sorted
+    }
+
+    /// Sort rows by (version_id, date). This is cheap if the rows were pushed
+    /// in that order already.
+    pub fn sort(&mut self) {
+        if self.sorted {
+            return;
+        }
+
+        let mut rows: Vec<(VersionId, i32, u64)> = (0..self.len())
+            .map(|i| (self.version_ids[i], self.days[i], self.downloads[i]))
+            .collect();
+        rows.sort_unstable_by_key(|&(version_id, day, _downloads)| (version_id, day));
+
+        for (i, (version_id, day, downloads)) in rows.into_iter().enumerate() {
+            self.version_ids[i] = version_id;
+            self.days[i] = day;
+            self.downloads[i] = downloads;
+        }
+        self.sorted = true;
+    }
+
+    /// All rows for the given version.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table is not sorted.
+    pub fn version(&self, version_id: VersionId) -> Slice<'_> {
+        self.range(version_id, ..)
+    }
+
+    /// Rows for the given version on dates within the given range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table is not sorted.
+    pub fn range(&self, version_id: VersionId, dates: impl RangeBounds<Date<Utc>>) -> Slice<'_> {
+        assert!(
+            self.sorted,
+            "version_downloads::Table must be sorted before range queries",
+        );
+
+        let lo = match dates.start_bound() {
+            Bound::Included(start) => (version_id, day(*start), Ordering::Less),
+            Bound::Excluded(start) => (version_id, day(*start), Ordering::Equal),
+            Bound::Unbounded => (version_id, i32::MIN, Ordering::Less),
+        };
+        let hi = match dates.end_bound() {
+            Bound::Included(end) => (version_id, day(*end), Ordering::Equal),
+            Bound::Excluded(end) => (version_id, day(*end), Ordering::Less),
+            Bound::Unbounded => (version_id, i32::MAX, Ordering::Equal),
+        };
+
+        let start = self.partition_point(lo);
+        let end = self.partition_point(hi).max(start);
+        Slice {
+            table: self,
+            range: (start, end),
+        }
+    }
+
+    // Index of the first row whose (version_id, day) compares greater than the
+    // bound, or greater-or-equal if `ordering` is Less.
+    fn partition_point(&self, (version_id, day, ordering): (VersionId, i32, Ordering)) -> usize {
+        let rows = self.version_ids.partition_point(|&v| v < version_id);
+        let rows_end = rows + self.version_ids[rows..].partition_point(|&v| v == version_id);
+        rows + self.days[rows..rows_end].partition_point(|&d| d.cmp(&day) <= ordering)
+    }
+}
+
+fn day(date: Date<Utc>) -> i32 {
+    date.naive_utc().num_days_from_ce()
+}
+
+fn date(day: i32) -> Date<Utc> {
+    Date::from(NaiveDate::from_num_days_from_ce_opt(day).unwrap())
+}
+
+impl<'a> Slice<'a> {
+    pub fn len(&self) -> usize {
+        self.range.1 - self.range.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Position of this slice's rows within the table.
+    pub fn range(&self) -> Range<usize> {
+        self.range.0..self.range.1
+    }
+
+    pub fn iter(&self) -> Iter<'a> {
+        Iter {
+            table: self.table,
+            range: self.range(),
+        }
+    }
+
+    /// The downloads column for the rows in this slice.
+    pub fn downloads(&self) -> &'a [u64] {
+        &self.table.downloads[self.range()]
+    }
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Table::new()
+    }
+}
+
+impl Extend<Row> for Table {
+    fn extend<I: IntoIterator<Item = Row>>(&mut self, iter: I) {
+        for row in iter {
+            self.push(row);
+        }
+    }
+}
+
+impl FromIterator<Row> for Table {
+    fn from_iter<I: IntoIterator<Item = Row>>(iter: I) -> Self {
+        let mut table = Table::new();
+        table.extend(iter);
+        table
+    }
+}
+
+impl<'a> IntoIterator for &'a Table {
+    type Item = Row;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &Slice<'a> {
+    type Item = Row;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for Slice<'a> {
+    type Item = Row;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Row;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let i = self.range.next()?;
+        self.table.get(i)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Iter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let i = self.range.next_back()?;
+        self.table.get(i)
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
+impl<'a> FusedIterator for Iter<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Row, Table};
+    use crate::versions::VersionId;
+    use crate::Date;
+
+    #[test]
+    fn test_range() {
+        let row = |version_id, day, downloads| Row {
+            version_id: VersionId(version_id),
+            downloads,
+            date: Date::from_ymd(2020, 1, day),
+        };
+
+        let mut table = Table::new();
+        table.push(row(2, 1, 5));
+        table.push(row(1, 3, 3));
+        table.push(row(1, 1, 1));
+        table.push(row(1, 2, 2));
+        table.push(row(3, 1, 6));
+        table.push(row(2, 2, 4));
+        assert!(!table.is_sorted());
+
+        table.sort();
+        assert!(table.is_sorted());
+        let downloads: Vec<u64> = table.iter().map(|row| row.downloads).collect();
+        assert_eq!(downloads, [1, 2, 3, 5, 4, 6]);
+
+        let jan = |day| Date::from_ymd(2020, 1, day);
+        assert_eq!(table.version(VersionId(1)).downloads(), [1, 2, 3]);
+        assert_eq!(table.version(VersionId(4)).len(), 0);
+        assert_eq!(table.range(VersionId(1), jan(2)..).downloads(), [2, 3]);
+        assert_eq!(table.range(VersionId(1), ..jan(3)).downloads(), [1, 2]);
+        assert_eq!(table.range(VersionId(1), ..=jan(3)).downloads(), [1, 2, 3]);
+        assert_eq!(table.range(VersionId(2), jan(2)..=jan(2)).downloads(), [4]);
+        assert_eq!(table.range(VersionId(2), jan(3)..).range(), 5..5);
+    }
+}
@@ -2,10 +2,12 @@
 
 use crate::crates::CrateId;
 use crate::ignore::IgnoredStr;
+use crate::intern::{Intern, Interner};
 use crate::users::UserId;
 use chrono::{DateTime, Utc};
 use semver::{BuildMetadata, Op, Version, VersionReq};
 use serde::de::{Deserialize, Deserializer, Unexpected, Visitor};
+use serde::ser::{Serialize, Serializer};
 use serde_derive::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use std::cmp::Ordering;
@@ -13,6 +15,7 @@ use std::collections::BTreeMap as Map;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::str::FromStr;
+use std::sync::Arc;
 
 /// Primary key of **versions.csv**.
 #[derive(Serialize, Deserialize, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
@@ -31,9 +34,9 @@ pub struct Row {
     pub updated_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
     pub downloads: u64,
-    pub features: Map<String, Vec<String>>,
+    pub features: Map<Arc<str>, Vec<Arc<str>>>,
     pub yanked: bool,
-    pub license: String,
+    pub license: Arc<str>,
     pub crate_size: Option<u64>,
     pub published_by: Option<UserId>,
     pub checksum: Option<[u8; 32]>,
@@ -46,8 +49,17 @@ pub struct Row {
     pub homepage: Option<String>,
     pub documentation: Option<String>,
     pub repository: Option<String>,
-    pub categories: Vec<String>,
-    pub keywords: Vec<String>,
+    pub categories: Vec<Arc<str>>,
+    pub keywords: Vec<Arc<str>>,
+}
+
+impl Row {
+    /// The checksum as 64 lowercase hex digits, as cargo and crates.io show
+    /// it.
+    pub fn checksum_hex(&self) -> Option<String> {
+        self.checksum
+            .map(|checksum| crate::hex::Hex(&checksum).to_string())
+    }
 }
 
 impl<'de> Deserialize<'de> for Row {
@@ -71,10 +83,10 @@ impl<'de> Deserialize<'de> for Row {
             created_at: DateTime<Utc>,
             downloads: u64,
             #[serde(deserialize_with = "features_map")]
-            features: Map<String, Vec<String>>,
+            features: Map<Arc<str>, Vec<Arc<str>>>,
             #[serde(deserialize_with = "crate::bool::de")]
             yanked: bool,
-            license: String,
+            license: Arc<str>,
             crate_size: Option<u64>,
             published_by: Option<UserId>,
             #[serde(deserialize_with = "checksum", default)]
@@ -93,9 +105,9 @@ impl<'de> Deserialize<'de> for Row {
             documentation: Option<String>,
             repository: Option<String>,
             #[serde(default, deserialize_with = "categories")]
-            categories: Vec<String>,
+            categories: Vec<Arc<str>>,
             #[serde(default, deserialize_with = "keywords")]
-            keywords: Vec<String>,
+            keywords: Vec<Arc<str>>,
         }
 
         let Row {
@@ -152,6 +164,90 @@ impl<'de> Deserialize<'de> for Row {
     }
 }
 
+impl Serialize for Row {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct Row<'a> {
+            id: VersionId,
+            crate_id: CrateId,
+            num: &'a Version,
+            num_no_build: Version,
+            #[serde(serialize_with = "crate::datetime::ser")]
+            updated_at: DateTime<Utc>,
+            #[serde(serialize_with = "crate::datetime::ser")]
+            created_at: DateTime<Utc>,
+            downloads: u64,
+            #[serde(serialize_with = "ser::features_map")]
+            features: &'a FeaturesMap,
+            #[serde(serialize_with = "crate::bool::ser")]
+            yanked: bool,
+            license: &'a str,
+            crate_size: Option<u64>,
+            published_by: Option<UserId>,
+            #[serde(serialize_with = "ser::checksum")]
+            checksum: Option<[u8; 32]>,
+            links: Option<&'a str>,
+            rust_version: Option<&'a Version>,
+            #[serde(serialize_with = "crate::bool::ser")]
+            has_lib: bool,
+            #[serde(serialize_with = "crate::set::ser")]
+            bin_names: &'a [String],
+            edition: Option<u16>,
+            description: Option<&'a str>,
+            homepage: Option<&'a str>,
+            documentation: Option<&'a str>,
+            repository: Option<&'a str>,
+            #[serde(serialize_with = "crate::set::ser")]
+            categories: &'a [Arc<str>],
+            #[serde(serialize_with = "crate::set::ser")]
+            keywords: &'a [Arc<str>],
+        }
+
+        Row {
+            id: self.id,
+            crate_id: self.crate_id,
+            num: &self.num,
+            num_no_build: Version {
+                build: BuildMetadata::EMPTY,
+                ..self.num.clone()
+            },
+            updated_at: self.updated_at,
+            created_at: self.created_at,
+            downloads: self.downloads,
+            features: &self.features,
+            yanked: self.yanked,
+            license: &self.license,
+            crate_size: self.crate_size,
+            published_by: self.published_by,
+            checksum: self.checksum,
+            links: self.links.as_deref(),
+            rust_version: self.rust_version.as_ref(),
+            has_lib: self.has_lib,
+            bin_names: &self.bin_names,
+            edition: self.edition,
+            description: self.description.as_deref(),
+            homepage: self.homepage.as_deref(),
+            documentation: self.documentation.as_deref(),
+            repository: self.repository.as_deref(),
+            categories: &self.categories,
+            keywords: &self.keywords,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Intern for Row {
+    fn intern(&mut self, interner: &mut Interner) {
+        interner.intern_map(&mut self.features);
+        interner.intern(&mut self.license);
+        interner.intern_all(&mut self.categories);
+        interner.intern_all(&mut self.keywords);
+    }
+}
+
 impl Ord for Row {
     fn cmp(&self, other: &Self) -> Ordering {
         VersionId::cmp(&self.id, &other.id)
@@ -231,10 +327,12 @@ where
     deserializer.deserialize_str(VersionVisitor)
 }
 
+pub(crate) type FeaturesMap = Map<Arc<str>, Vec<Arc<str>>>;
+
 struct FeaturesMapVisitor;
 
 impl<'de> Visitor<'de> for FeaturesMapVisitor {
-    type Value = Map<String, Vec<String>>;
+    type Value = FeaturesMap;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         formatter.write_str("features map")
@@ -248,7 +346,7 @@ impl<'de> Visitor<'de> for FeaturesMapVisitor {
     }
 }
 
-fn features_map<'de, D>(deserializer: D) -> Result<Map<String, Vec<String>>, D::Error>
+fn features_map<'de, D>(deserializer: D) -> Result<FeaturesMap, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -368,16 +466,103 @@ where
     crate::set::optional(deserializer, "binary names set")
 }
 
-fn categories<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+fn categories<'de, D>(deserializer: D) -> Result<Vec<Arc<str>>, D::Error>
 where
     D: Deserializer<'de>,
 {
     crate::set::de(deserializer, "categories set")
 }
 
-fn keywords<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+fn keywords<'de, D>(deserializer: D) -> Result<Vec<Arc<str>>, D::Error>
 where
     D: Deserializer<'de>,
 {
     crate::set::de(deserializer, "keywords set")
 }
+
+mod ser {
+    use super::FeaturesMap;
+    use crate::hex::Hex;
+    use serde::ser::{Error, Serializer};
+
+    // A JSON object within the CSV field, like the dump.
+    pub(super) fn features_map<S>(features: &FeaturesMap, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let json = serde_json::to_string(features).map_err(S::Error::custom)?;
+        serializer.serialize_str(&json)
+    }
+
+    // Lowercase hex, or empty if there is no checksum.
+    #[allow(clippy::ref_option)]
+    pub(super) fn checksum<S>(checksum: &Option<[u8; 32]>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match checksum {
+            Some(checksum) => serializer.collect_str(&Hex(checksum)),
+            None => serializer.serialize_str(""),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Row, VersionId};
+    use crate::crates::CrateId;
+    use crate::users::UserId;
+    use chrono::DateTime;
+    use semver::Version;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_round_trip() {
+        let timestamp = DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap();
+        let row = Row {
+            id: VersionId(1),
+            crate_id: CrateId(2),
+            num: Version::parse("1.0.0-rc.1+build.5").unwrap(),
+            updated_at: timestamp,
+            created_at: timestamp,
+            downloads: 100,
+            features: [
+                (Arc::from("default"), vec![Arc::from("std")]),
+                (Arc::from("std"), Vec::new()),
+            ]
+            .into_iter()
+            .collect(),
+            yanked: true,
+            license: Arc::from("MIT OR Apache-2.0"),
+            crate_size: Some(4096),
+            published_by: Some(UserId(5)),
+            checksum: Some([0x0f; 32]),
+            links: Some("z".to_owned()),
+            rust_version: Some(Version::new(1, 60, 0)),
+            has_lib: true,
+            bin_names: vec!["a".to_owned(), "b".to_owned()],
+            edition: Some(2021),
+            description: Some("demo, \"quoted\"".to_owned()),
+            homepage: None,
+            documentation: None,
+            repository: Some("https://github.com/dtolnay/db-dump".to_owned()),
+            categories: Vec::new(),
+            keywords: vec![Arc::from("x")],
+        };
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.serialize(&row).unwrap();
+        let csv = writer.into_inner().unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.contains(",t,"));
+        assert!(csv.contains(&"0f".repeat(32)));
+        assert!(csv.contains("{a,b}"));
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let rows: Vec<Row> = reader.deserialize().collect::<Result<_, _>>().unwrap();
+        assert_eq!(format!("{:?}", rows), format!("{:?}", [&row]));
+
+        let json = serde_json::to_string(&row).unwrap();
+        let from_json: Row = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{:?}", from_json), format!("{:?}", row));
+    }
+}
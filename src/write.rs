@@ -0,0 +1,446 @@
+use crate::error::{err, Result};
+use crate::DbDump;
+use chrono::{DateTime, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::ser::Serialize;
+use std::fs::File;
+use std::io::{Seek, Write};
+use std::path::Path;
+
+/// Write rows into a new DB dump, in the same layout as the ones published by
+/// crates.io.
+///
+/// The output is a gzipped tarball containing one
+/// <code>&lt;datestamp&gt;/data/&lt;table&gt;.csv</code> per table, which
+/// [`Loader`](crate::Loader) and [`load_all`](crate::load_all) read back
+/// exactly as written. Every table is present in the output, including ones to
+/// which no rows were written.
+///
+/// Rows are spooled to one temporary file per table until the archive is
+/// written, so memory use does not grow with the number of rows.
+///
+/// # Example
+///
+/// This example produces a trimmed dump with just the crates that have
+/// "serde" in their name, and their versions.
+///
+/// ```no_run
+/// use chrono::Utc;
+/// use std::collections::BTreeSet as Set;
+///
+/// fn main() -> db_dump::Result<()> {
+///     let db = db_dump::load_all("./db-dump.tar.gz")?;
+///
+///     let mut writer = db_dump::Writer::new(Utc::now());
+///     let mut crate_ids = Set::new();
+///     for row in &db.crates {
+///         if row.name.contains("serde") {
+///             crate_ids.insert(row.id);
+///             writer.crates(row)?;
+///         }
+///     }
+///     for row in &db.versions {
+///         if crate_ids.contains(&row.crate_id) {
+///             writer.versions(row)?;
+///         }
+///     }
+///     writer.write("./db-dump-serde.tar.gz")?;
+///
+///     Ok(())
+/// }
+/// ```
+pub struct Writer {
+    timestamp: DateTime<Utc>,
+    tables: Tables,
+}
+
+struct Table {
+    // Created on the first row, to keep Writer::new infallible.
+    csv: Option<csv::Writer<File>>,
+}
+
+impl Table {
+    fn new() -> Self {
+        Table { csv: None }
+    }
+
+    fn serialize(&mut self, name: &'static str, row: &impl Serialize) -> Result<()> {
+        let csv = match &mut self.csv {
+            Some(csv) => csv,
+            None => self
+                .csv
+                .insert(csv::Writer::from_writer(tempfile::tempfile()?)),
+        };
+        csv.serialize(row).map_err(|e| {
+            let mut err = err(e);
+            err.e.path = Some(Path::new(name));
+            err
+        })
+    }
+
+    // CSV content of the table, rewound to the start, and its size in bytes.
+    // The header comes from the field names of the serialized rows, or from
+    // `headers` if no rows were written.
+    fn finish(self, headers: &[&str]) -> Result<(File, u64)> {
+        let csv = if let Some(csv) = self.csv {
+            csv
+        } else {
+            let mut csv = csv::Writer::from_writer(tempfile::tempfile()?);
+            csv.write_record(headers).map_err(err)?;
+            csv
+        };
+        let mut file = csv.into_inner().map_err(csv::IntoInnerError::into_error)?;
+        let size = file.stream_position()?;
+        file.rewind()?;
+        Ok((file, size))
+    }
+}
+
+macro_rules! tables {
+    ($($table:ident [$($column:literal),* $(,)?])*) => {
+        struct Tables {
+            $(
+                $table: Table,
+            )*
+        }
+
+        impl Tables {
+            fn new() -> Self {
+                Tables {
+                    $(
+                        $table: Table::new(),
+                    )*
+                }
+            }
+
+            fn finish(self) -> Result<Vec<(&'static str, (File, u64))>> {
+                Ok(vec![$(
+                    (stringify!($table), self.$table.finish(header(stringify!($table)))?),
+                )*])
+            }
+        }
+
+        // The columns of the given table's csv file, in order.
+        pub(crate) fn header(table: &str) -> &'static [&'static str] {
+            match table {
+                $(
+                    stringify!($table) => &[$($column),*],
+                )*
+                _ => &[],
+            }
+        }
+
+        impl Writer {
+            $(
+                #[doc = concat!("Append a row to **", stringify!($table), ".csv**.")]
+                pub fn $table(&mut self, row: &crate::$table::Row) -> Result<()> {
+                    self.tables.$table.serialize(stringify!($table), row)
+                }
+            )*
+        }
+    };
+}
+
+tables! {
+    categories ["id", "category", "slug", "description", "crates_cnt", "created_at", "path"]
+    crate_downloads ["crate_id", "downloads"]
+    crate_owners ["crate_id", "created_at", "created_by", "owner_id", "owner_kind"]
+    crates [
+        "id",
+        "name",
+        "updated_at",
+        "created_at",
+        "description",
+        "homepage",
+        "documentation",
+        "readme",
+        "repository",
+        "max_upload_size",
+        "max_features",
+        "trustpub_only",
+    ]
+    crates_categories ["crate_id", "category_id"]
+    crates_keywords ["crate_id", "keyword_id"]
+    default_versions ["crate_id", "version_id", "num_versions"]
+    deleted_crates [
+        "id",
+        "name",
+        "created_at",
+        "deleted_at",
+        "deleted_by",
+        "message",
+        "available_at",
+    ]
+    dependencies [
+        "id",
+        "version_id",
+        "crate_id",
+        "req",
+        "optional",
+        "default_features",
+        "features",
+        "target",
+        "kind",
+        "explicit_name",
+    ]
+    keywords ["id", "keyword", "crates_cnt", "created_at"]
+    metadata ["total_downloads"]
+    reserved_crate_names ["name"]
+    teams ["id", "login", "github_id", "name", "avatar", "org_id"]
+    users ["id", "gh_login", "name", "gh_avatar", "gh_id"]
+    version_downloads ["version_id", "downloads", "date"]
+    versions [
+        "id",
+        "crate_id",
+        "num",
+        "num_no_build",
+        "updated_at",
+        "created_at",
+        "downloads",
+        "features",
+        "yanked",
+        "license",
+        "crate_size",
+        "published_by",
+        "checksum",
+        "links",
+        "rust_version",
+        "has_lib",
+        "bin_names",
+        "edition",
+        "description",
+        "homepage",
+        "documentation",
+        "repository",
+        "categories",
+        "keywords",
+    ]
+}
+
+impl Writer {
+    /// The timestamp determines the name of the top-level directory inside
+    /// the archive, such as `2024-01-31-020017`, and the modification time of
+    /// its files.
+    pub fn new(timestamp: DateTime<Utc>) -> Self {
+        Writer {
+            timestamp,
+            tables: Tables::new(),
+        }
+    }
+
+    /// Append every row of every table of the given dump.
+    pub fn dump(&mut self, db: &DbDump) -> Result<()> {
+        let DbDump {
+            categories,
+            crate_downloads,
+            crate_owners,
+            crates,
+            crates_categories,
+            crates_keywords,
+            default_versions,
+            deleted_crates,
+            dependencies,
+            keywords,
+            metadata,
+            reserved_crate_names,
+            teams,
+            users,
+            version_downloads,
+            versions,
+        } = db;
+
+        for row in categories {
+            self.categories(row)?;
+        }
+        for row in crate_downloads {
+            self.crate_downloads(row)?;
+        }
+        for row in crate_owners {
+            self.crate_owners(row)?;
+        }
+        for row in crates {
+            self.crates(row)?;
+        }
+        for row in crates_categories {
+            self.crates_categories(row)?;
+        }
+        for row in crates_keywords {
+            self.crates_keywords(row)?;
+        }
+        for row in default_versions {
+            self.default_versions(row)?;
+        }
+        for row in deleted_crates {
+            self.deleted_crates(row)?;
+        }
+        for row in dependencies {
+            self.dependencies(row)?;
+        }
+        for row in keywords {
+            self.keywords(row)?;
+        }
+        self.metadata(metadata)?;
+        for row in reserved_crate_names {
+            self.reserved_crate_names(row)?;
+        }
+        for row in teams {
+            self.teams(row)?;
+        }
+        for row in users {
+            self.users(row)?;
+        }
+        for row in version_downloads {
+            self.version_downloads(&row)?;
+        }
+        for row in versions {
+            self.versions(row)?;
+        }
+        Ok(())
+    }
+
+    /// Write the archive to a file at the given path.
+    pub fn write(self, path: impl AsRef<Path>) -> Result<()> {
+        let file = File::create(path)?;
+        self.write_to(file)
+    }
+
+    /// Write the archive to the given destination.
+    pub fn write_to(self, out: impl Write) -> Result<()> {
+        let datestamp = self.timestamp.format("%Y-%m-%d-%H%M%S").to_string();
+        let mtime = u64::try_from(self.timestamp.timestamp()).unwrap_or(0);
+
+        let gz = GzEncoder::new(out, Compression::default());
+        let mut tar = tar::Builder::new(gz);
+        for (table, (content, size)) in self.tables.finish()? {
+            let path = format!("{}/data/{}.csv", datestamp, table);
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o644);
+            header.set_mtime(mtime);
+            header.set_size(size);
+            tar.append_data(&mut header, path, content)?;
+        }
+        tar.into_inner()?.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Writer;
+    use crate::crate_owners::OwnerId;
+    use crate::crates::CrateId;
+    use crate::testing::DumpBuilder;
+    use crate::users::UserId;
+    use crate::versions::VersionId;
+    use crate::Date;
+    use chrono::DateTime;
+    use std::io::{BufRead, BufReader};
+
+    // First line of each table's csv, and whether any rows follow it.
+    fn headers(writer: Writer) -> Vec<(&'static str, String, bool)> {
+        let mut headers = Vec::new();
+        for (table, (file, _size)) in writer.tables.finish().unwrap() {
+            let mut file = BufReader::new(file);
+            let mut header = String::new();
+            file.read_line(&mut header).unwrap();
+            let rows = file.read_line(&mut String::new()).unwrap() > 0;
+            headers.push((table, header, rows));
+        }
+        headers
+    }
+
+    // The headers written for empty tables must match the ones that serde
+    // produces from the rows.
+    #[test]
+    fn test_empty_headers() {
+        let mut builder = DumpBuilder::new();
+        let alice = builder.user("alice").id;
+        let team = builder.team("github:serde-rs:publish").id;
+        let serde = builder.krate("serde").id;
+        let serde_v1 = builder.version(serde, "1.0.0").id;
+        builder.dependency(serde_v1, serde, "^1.0");
+        builder.owner(serde, alice);
+        builder.owner(serde, team);
+        builder.keyword(serde, "serialization");
+        builder.category(serde, "encoding");
+        builder.downloads(serde_v1, Date::from_ymd(2024, 1, 1), 1);
+        builder.deleted_crate("gone");
+        builder.reserved_crate_name("std");
+        let db = builder.build();
+
+        let timestamp = DateTime::UNIX_EPOCH;
+        let mut full = Writer::new(timestamp);
+        full.dump(&db).unwrap();
+        let full = headers(full);
+        let empty = headers(Writer::new(timestamp));
+        assert_eq!(full.len(), 16);
+        for ((table, full, rows), (_, empty, _)) in full.iter().zip(&empty) {
+            assert!(rows, "{}.csv", table);
+            assert_eq!(empty, full, "{}.csv", table);
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        let timestamp = DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap();
+        let mut db = crate::DbDump::default();
+        db.crates.push(crate::crates::Row {
+            id: CrateId(1),
+            name: "demo".to_owned(),
+            updated_at: timestamp,
+            created_at: timestamp,
+            description: "multi\nline, \"quoted\"".to_owned(),
+            homepage: Some("https://example.com".to_owned()),
+            documentation: None,
+            readme: None,
+            repository: None,
+            max_upload_size: Some(10),
+            max_features: None,
+            trustpub_only: true,
+        });
+        db.crate_owners.push(crate::crate_owners::Row {
+            crate_id: CrateId(1),
+            owner_id: OwnerId::User(UserId(2)),
+            created_at: timestamp,
+            created_by: None,
+        });
+        for (version_id, day, downloads) in [(1, 2, 5), (1, 1, 3)] {
+            db.version_downloads.push(crate::version_downloads::Row {
+                version_id: VersionId(version_id),
+                downloads,
+                date: Date::from_ymd(2024, 6, day),
+            });
+        }
+        db.metadata.total_downloads = 8;
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path();
+        let path = dir.join("db-dump.tar.gz");
+        let mut writer = Writer::new(timestamp);
+        writer.dump(&db).unwrap();
+        writer.write(&path).unwrap();
+        let loaded = crate::load_all(&path).unwrap();
+
+        let mut csv_files = Vec::new();
+        let file = std::fs::File::open(&path).unwrap();
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+        for entry in archive.entries().unwrap() {
+            csv_files.push(entry.unwrap().path().unwrap().display().to_string());
+        }
+
+        assert_eq!(csv_files.len(), 16);
+        assert_eq!(csv_files[0], "2023-11-14-221320/data/categories.csv");
+        assert_eq!(format!("{:?}", loaded.crates), format!("{:?}", db.crates));
+        assert_eq!(
+            format!("{:?}", loaded.crate_owners),
+            format!("{:?}", db.crate_owners),
+        );
+        assert_eq!(
+            format!("{:?}", loaded.version_downloads),
+            format!("{:?}", db.version_downloads),
+        );
+        assert_eq!(loaded.metadata.total_downloads, 8);
+        assert!(loaded.versions.is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+// Usage:
+//
+//     cargo run --release -- path/to/db-dump.tar.gz serde_json tokio
+//
+// Writes a new dump containing just the named crates, everything they
+// transitively depend on, and the owners, users, teams, keywords, categories
+// and download counts belonging to those crates. The output is loadable by
+// db_dump::load_all like any other dump, which makes it suitable for bug
+// reports and test fixtures.
+//
+// Dev-dependencies are not followed unless --dev is passed.
+//
+// The output file will be named db-dump-subset.tar.gz, or can be specified
+// explicitly using the --out flag. It keeps the datestamp of the input dump.
+
+#![allow(clippy::uninlined_format_args)]
+
+use anyhow::{format_err, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use clap::Parser;
+use db_dump::subset::Subset;
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use tar::Archive;
+
+#[derive(Parser, Debug)]
+#[command(name = "db-dump-subset", author, version)]
+struct Opt {
+    #[arg(short, long, default_value = "db-dump-subset.tar.gz")]
+    out: PathBuf,
+
+    /// Also follow dev-dependencies
+    #[arg(long)]
+    dev: bool,
+
+    dump: PathBuf,
+
+    #[arg(required = true)]
+    crates: Vec<String>,
+}
+
+fn main() -> Result<()> {
+    let opt = Opt::parse();
+
+    let stderr = io::stderr();
+    let mut stderr = stderr.lock();
+
+    let timestamp = timestamp(&opt.dump)
+        .map_err(|e| format_err!("{}: {}", opt.dump.display(), e))?
+        .unwrap_or_else(Utc::now);
+
+    let mut subset = Subset::new();
+    for name in &opt.crates {
+        subset.krate(name);
+    }
+    subset.dev_dependencies(opt.dev);
+    let db = subset.extract(&opt.dump)?;
+    let _ = writeln!(
+        stderr,
+        "extracted {} crates, {} versions, {} dependencies",
+        db.crates.len(),
+        db.versions.len(),
+        db.dependencies.len(),
+    );
+
+    let mut writer = db_dump::Writer::new(timestamp);
+    writer.dump(&db)?;
+    writer.write(&opt.out)?;
+    let _ = writeln!(stderr, "wrote {}", opt.out.display());
+    Ok(())
+}
+
+// The datestamp of the dump, from the name of its top-level directory.
+fn timestamp(path: &Path) -> Result<Option<DateTime<Utc>>> {
+    let file = File::open(path)?;
+    let mut archive = Archive::new(GzDecoder::new(file));
+    let Some(entry) = archive.entries()?.next() else {
+        return Ok(None);
+    };
+    let entry = entry?;
+    let path = entry.path()?;
+    let Some(Component::Normal(datestamp)) = path.components().next() else {
+        return Ok(None);
+    };
+    let datestamp = datestamp.to_string_lossy();
+    let timestamp = NaiveDateTime::parse_from_str(&datestamp, "%Y-%m-%d-%H%M%S").ok();
+    Ok(timestamp.map(|timestamp| timestamp.and_utc()))
+}
@@ -0,0 +1,283 @@
+use db_dump::crate_owners::OwnerId;
+use db_dump::dependencies::DependencyKind;
+use db_dump::testing::DumpBuilder;
+use db_dump::{Date, DbDump, Loader};
+use std::cell::RefCell;
+use std::sync::Arc;
+
+fn fixture() -> DumpBuilder {
+    let mut builder = DumpBuilder::new();
+    let dtolnay = builder.user("dtolnay").id;
+    let serde_rs = builder.team("github:serde-rs:publish").id;
+
+    let serde = builder.krate("serde").id;
+    let serde_v1 = builder.version(serde, "1.0.0").id;
+    let serde_v2 = builder.version(serde, "1.0.200").id;
+    builder.version(serde, "2.0.0-alpha.1");
+    builder.owner(serde, dtolnay);
+    builder.owner(serde, serde_rs);
+    builder.keyword(serde, "serialization");
+    builder.category(serde, "encoding");
+    builder.download_series(serde_v1, Date::from_ymd(2024, 1, 1), &[5, 6, 7]);
+    builder.downloads(serde_v2, Date::from_ymd(2024, 1, 2), 100);
+
+    let json = builder.krate("serde_json").id;
+    let json_v1 = builder.version(json, "1.0.100").id;
+    builder.version(json, "1.0.101").yanked = true;
+    builder.dependency(json_v1, serde, "^1.0.100");
+    builder.dependency(json_v1, serde, "^1.0").kind = DependencyKind::Dev;
+    builder.owner(json, dtolnay);
+    builder.keyword(json, "serialization");
+    builder.keyword(json, "json");
+    builder.category(json, "encoding");
+    builder.downloads(json_v1, Date::from_ymd(2024, 1, 3), 50);
+
+    let empty = builder.krate("empty").id;
+    builder.version(empty, "0.0.0");
+    builder.deleted_crate("gone");
+    builder.reserved_crate_name("std");
+    builder
+}
+
+#[test]
+fn test_load_all() {
+    let builder = fixture();
+    let archive = builder.temp_archive().unwrap();
+    let db = db_dump::load_all(&archive).unwrap();
+    let expected = builder.build();
+
+    assert_eq!(format!("{:?}", db.crates), format!("{:?}", expected.crates));
+    assert_eq!(
+        format!("{:?}", db.versions),
+        format!("{:?}", expected.versions)
+    );
+    assert_eq!(
+        format!("{:?}", db.dependencies),
+        format!("{:?}", expected.dependencies),
+    );
+    assert_eq!(
+        format!("{:?}", db.crate_owners),
+        format!("{:?}", expected.crate_owners),
+    );
+    assert_eq!(
+        format!("{:?}", db.version_downloads),
+        format!("{:?}", expected.version_downloads),
+    );
+    assert_eq!(db.deleted_crates.len(), 1);
+    assert_eq!(db.reserved_crate_names[0].name, "std");
+    assert_eq!(db.metadata.total_downloads, 168);
+
+    // The fixture writes version_downloads.csv in order of version and date,
+    // so the table supports range queries as loaded.
+    let serde_v1 = db.versions[0].id;
+    assert!(db.version_downloads.is_sorted());
+    assert_eq!(
+        db.version_downloads.version(serde_v1).downloads(),
+        [5, 6, 7]
+    );
+
+    let report = db.validate();
+    assert!(report.is_empty(), "{:?}", report.problems());
+    assert!(db.check_counters().is_empty());
+}
+
+#[test]
+fn test_derived() {
+    let db = fixture().build();
+
+    let downloads: Vec<u64> = db.crate_downloads.iter().map(|row| row.downloads).collect();
+    assert_eq!(downloads, [118, 50, 0]);
+
+    // serde's default is the greatest stable version, not the prerelease;
+    // serde_json's is the greatest one that is not yanked.
+    let defaults: Vec<(u32, u32, Option<u32>)> = db
+        .default_versions
+        .iter()
+        .map(|row| (row.crate_id.0, row.version_id.0, row.num_versions))
+        .collect();
+    assert_eq!(
+        defaults,
+        [(1, 2, Some(3)), (2, 4, Some(2)), (3, 6, Some(1))]
+    );
+
+    let keywords: Vec<(&str, u32)> = db
+        .keywords
+        .iter()
+        .map(|row| (row.keyword.as_str(), row.crates_cnt))
+        .collect();
+    assert_eq!(keywords, [("serialization", 2), ("json", 1)]);
+    assert_eq!(db.categories.len(), 1);
+    assert_eq!(db.categories[0].crates_cnt, 2);
+}
+
+#[test]
+fn test_selective_loader() {
+    let archive = fixture().temp_archive().unwrap();
+
+    let crates = RefCell::new(Vec::new());
+    let mut downloads = 0;
+    Loader::new()
+        .crates(|row| crates.borrow_mut().push(row.name))
+        .version_downloads(|row| downloads += row.downloads)
+        .load(&archive)
+        .unwrap();
+
+    assert_eq!(crates.into_inner(), ["serde", "serde_json", "empty"]);
+    assert_eq!(downloads, 168);
+}
+
+#[test]
+fn test_intern_strings() {
+    let archive = fixture().temp_archive().unwrap();
+
+    let mut interned = Vec::new();
+    Loader::new()
+        .versions(|row| interned.push(row.license))
+        .intern_strings()
+        .load(&archive)
+        .unwrap();
+    assert_eq!(&*interned[0], "MIT OR Apache-2.0");
+    assert!(Arc::ptr_eq(&interned[0], &interned[1]));
+
+    let mut separate = Vec::new();
+    Loader::new()
+        .versions(|row| separate.push(row.license))
+        .load(&archive)
+        .unwrap();
+    assert_eq!(separate[0], separate[1]);
+    assert!(!Arc::ptr_eq(&separate[0], &separate[1]));
+}
+
+#[test]
+fn test_loader_cache() {
+    let archive = fixture().temp_archive().unwrap();
+    let tempdir = tempfile::tempdir().unwrap();
+    let cache = tempdir.path().join("db-dump.cache");
+
+    for _ in 0..2 {
+        let mut versions = Vec::new();
+        Loader::new()
+            .cache(&cache)
+            .versions(|row| versions.push(row.num.to_string()))
+            .load(&archive)
+            .unwrap();
+        assert_eq!(
+            versions,
+            [
+                "1.0.0",
+                "1.0.200",
+                "2.0.0-alpha.1",
+                "1.0.100",
+                "1.0.101",
+                "0.0.0"
+            ],
+        );
+        assert!(cache.exists());
+    }
+
+    let cached = DbDump::load_cache(&cache).unwrap();
+    assert_eq!(cached.metadata.total_downloads, 168);
+
+    // A cache that cannot be written does not fail the load.
+    let unwritable = tempdir.path().join("missing").join("cache");
+    let mut crates = 0;
+    Loader::new()
+        .cache(&unwritable)
+        .crates(|_row| crates += 1)
+        .load(&archive)
+        .unwrap();
+    assert_eq!(crates, 3);
+    assert!(!unwritable.exists());
+}
+
+#[test]
+fn test_index() {
+    let archive = fixture().temp_archive().unwrap();
+    let db = db_dump::load_all(&archive).unwrap();
+    let index = db.index();
+
+    let serde = index.crate_by_name("serde").unwrap();
+    let json = index.crate_by_name("serde_json").unwrap();
+    assert_eq!(index.versions_of(serde.id).count(), 3);
+
+    let json_v1 = index.versions_of(json.id).next().unwrap();
+    let deps: Vec<&str> = index
+        .dependencies_of(json_v1.id)
+        .map(|dep| index.crate_by_id(dep.crate_id).unwrap().name.as_str())
+        .collect();
+    assert_eq!(deps, ["serde", "serde"]);
+
+    let dtolnay = index.user_by_login("dtolnay").unwrap();
+    let owned: Vec<&str> = index
+        .crates_owned_by(dtolnay.id)
+        .map(|krate| krate.name.as_str())
+        .collect();
+    assert_eq!(owned, ["serde", "serde_json"]);
+
+    let owners: Vec<OwnerId> = index.owners_of(serde.id).map(|row| row.owner_id).collect();
+    assert_eq!(owners.len(), 2);
+    assert!(matches!(owners[1], OwnerId::Team(_)));
+
+    let keywords: Vec<&str> = index
+        .keywords_of(json.id)
+        .map(|row| row.keyword.as_str())
+        .collect();
+    assert_eq!(keywords, ["serialization", "json"]);
+}
+
+#[test]
+fn test_default_version() {
+    let mut db = fixture().build();
+    let default = |db: &DbDump, name: &str| {
+        let index = db.index();
+        let krate = index.crate_by_name(name).unwrap();
+        index
+            .default_version(krate.id)
+            .map(|row| row.num.to_string())
+    };
+    assert_eq!(default(&db, "serde").unwrap(), "1.0.200");
+    assert_eq!(default(&db, "serde_json").unwrap(), "1.0.100");
+
+    // Without default_versions.csv, the greatest stable version that is not
+    // yanked, as crates.io would pick.
+    db.default_versions.clear();
+    assert_eq!(default(&db, "serde").unwrap(), "1.0.200");
+    assert_eq!(default(&db, "serde_json").unwrap(), "1.0.100");
+    assert_eq!(default(&db, "empty").unwrap(), "0.0.0");
+}
+
+#[test]
+fn test_reverse_dependencies() {
+    let db = fixture().build();
+    let index = db.index();
+
+    // serde_json depends on serde as both a normal and a dev-dependency, and
+    // is listed once, by the normal one.
+    let serde = index.crate_by_name("serde").unwrap();
+    let dependents = index.reverse_dependencies(serde.id);
+    assert_eq!(dependents.len(), 1);
+    assert_eq!(dependents[0].krate.name, "serde_json");
+    assert_eq!(dependents[0].version.num.to_string(), "1.0.100");
+    assert!(matches!(
+        dependents[0].dependency.kind,
+        DependencyKind::Normal
+    ));
+    assert_eq!(dependents[0].dependency.req.to_string(), "^1.0.100");
+
+    let json = index.crate_by_name("serde_json").unwrap();
+    assert!(index.reverse_dependencies(json.id).is_empty());
+}
+
+#[test]
+fn test_in_memory_archive() {
+    let builder = fixture();
+    let archive = builder.archive().unwrap();
+    assert_eq!(&archive[..2], b"\x1f\x8b");
+
+    let tempdir = tempfile::tempdir().unwrap();
+    let dir = tempdir.path();
+    let path = dir.join("db-dump.tar.gz");
+    std::fs::write(&path, &archive).unwrap();
+    let db = db_dump::load_all(&path).unwrap();
+    assert_eq!(db.crates.len(), 3);
+}